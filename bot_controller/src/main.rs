@@ -1,38 +1,43 @@
+use common::api::api_reference::match_controller_client::MatchController;
+use common::models::match_controller::ControllerEvent;
+use common::paths::MatchLayout;
+use common::PlayerNum;
 use std::fs::{self, File, OpenOptions};
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::process::Command;
 use tokio::net::lookup_host;
-use tracing::info;
+use tracing::{error, info};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
+const EVENT_RETRIES: u32 = 10;
+
 #[tokio::main]
 async fn main() {
-    let _guards = init_controller_logs();
+    let layout = MatchLayout::from_env();
+    let _guards = init_controller_logs(&layout);
 
     // Run the bot in a spawned task to prevent panics from terminating the program
-    let bot_task = tokio::spawn(async {
-        run_bot().await;
-    });
+    let bot_layout = layout.clone();
+    let bot_task = tokio::spawn(async move { run_bot(&bot_layout).await });
 
     // Wait for the bot task to complete (whether it panics or returns normally)
-    let exit_code = if bot_task.await.is_err() { "2" } else { "0" };
-    let _ = std::fs::write("/logs/signal.exit", exit_code);
+    let exit_code = bot_task.await.unwrap_or(2);
+    let _ = std::fs::write(layout.signal_exit_file(), exit_code.to_string());
+    report_bot_exited(exit_code).await;
 
     // Notice: The controller will keep running even after the bot process exits.
     // When it's in Kubenetes environment, this allows the Kubernetes Job to complete without restarts.
-    // The `signal.exit` file signals to the match_controller that the bot process exited,
-    // so that it can create the match result accordingly.
-    // The responsibility for this signal will be removed from the bot controller in the next iteration,
-    // so that base bot Docker images have simpler interface and responsibilities.
+    // The `BotExited` event and the `signal.exit` file signal to the match_controller that the bot
+    // process exited, so that it can create the match result accordingly.
     wait_for_sigterm().await;
 
     info!("Bot controller exits");
 }
 
-async fn run_bot() {
+async fn run_bot(layout: &MatchLayout) -> i32 {
     let game_host = std::env::var("GAME_HOST").unwrap_or_else(|_| "127.0.0.1".into());
     let game_port = std::env::var("GAME_PORT").expect("Missing GAME_PORT environment variable");
     let game_pass = std::env::var("GAME_PASS").unwrap_or_else(|_| game_port.clone());
@@ -48,12 +53,14 @@ async fn run_bot() {
     }
     .unwrap_or(game_host);
 
-    fs::create_dir_all("/bot/logs").expect("Could not create bot logs directory");
+    let bot_folder = &layout.bot_folder;
+    let bot_logs = layout.bot_logs_folder();
+    fs::create_dir_all(&bot_logs).expect("Could not create bot logs directory");
 
-    let mut command = construct_bot_command("/bot", &bot_name);
+    let mut command = construct_bot_command(&bot_folder.to_string_lossy(), &bot_name);
     let command = command
-        .stdout(create_log_file("/bot/logs/stdout.log"))
-        .stderr(create_log_file("/bot/logs/stderr.log"))
+        .stdout(create_log_file(&bot_logs.join("stdout.log")))
+        .stderr(create_log_file(&bot_logs.join("stderr.log")))
         .arg("--GamePort")
         .arg(&game_port)
         .arg("--LadderServer")
@@ -62,26 +69,53 @@ async fn run_bot() {
         .arg(&game_pass)
         .arg("--OpponentId")
         .arg(opponent_id)
-        .current_dir("/bot");
+        .current_dir(bot_folder);
 
     info!("Starting bot with command {:?}", &command);
     match command.status() {
         Ok(exit_status) => {
             info!("Bot process exited with status: {}", exit_status);
-            let exit_code = exit_status.code().unwrap_or(2).to_string();
-            let _ = std::fs::write("/logs/signal.exit", exit_code);
+            exit_status.code().unwrap_or(2)
         }
         Err(e) => {
             panic!("Bot process failed with error: {}", e);
         }
+    }
+}
+
+/// Reports the exit of the bot to the match controller, if its URL and the player number are set
+async fn report_bot_exited(exit_code: i32) {
+    let Some(match_controller) = MatchController::from_env() else {
+        return;
     };
+    let player_num = match std::env::var("PLAYER_NUM").as_deref() {
+        Ok("1") => PlayerNum::One,
+        Ok("2") => PlayerNum::Two,
+        Ok(player_num) => {
+            error!("Invalid PLAYER_NUM {}", player_num);
+            return;
+        }
+        Err(_) => return,
+    };
+    let event = ControllerEvent::BotExited {
+        player_num,
+        exit_code,
+    };
+    if match_controller
+        .send_event_with_retry(&event, EVENT_RETRIES)
+        .await
+    {
+        info!("Reported the bot exit to the match controller");
+    }
 }
 
-fn init_controller_logs() -> (
+fn init_controller_logs(
+    layout: &MatchLayout,
+) -> (
     tracing_appender::non_blocking::WorkerGuard,
     tracing_appender::non_blocking::WorkerGuard,
 ) {
-    let controller_logs = create_log_file("/logs/controller.log");
+    let controller_logs = create_log_file(&layout.log_file("controller.log"));
 
     let (non_blocking_stdout, stdout_guard) = tracing_appender::non_blocking(std::io::stdout());
     let (non_blocking_controller_logs, controller_logs_guard) =
@@ -109,13 +143,13 @@ fn init_controller_logs() -> (
     (stdout_guard, controller_logs_guard)
 }
 
-fn create_log_file(file_name: &str) -> File {
+fn create_log_file(file_name: &Path) -> File {
     OpenOptions::new()
         .create(true)
         .write(true)
         .truncate(true)
         .open(file_name)
-        .expect(&format!("Could not create file {}", file_name))
+        .expect(&format!("Could not create file {}", file_name.display()))
 }
 
 fn construct_bot_command(bot_folder: &str, bot_name: &str) -> Command {
@@ -1,6 +1,9 @@
 use config::{Config, Environment, File, FileFormat};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// The folder of the generated compose files
+pub const COMPOSE_FOLDER: &str = "target";
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ControllerConfig {
@@ -12,6 +15,7 @@ pub struct ControllerConfig {
     pub bots_directory: String,
     pub logs_directory: String,
     pub matches_file: String,
+    pub parallel: usize,
 }
 
 pub fn initialize_config() -> ControllerConfig {
@@ -41,6 +45,11 @@ pub fn initialize_config() -> ControllerConfig {
     config
 }
 
+/// The path of a folder given in a compose file, where relative paths start at the compose folder
+pub fn host_path(folder: &str) -> PathBuf {
+    Path::new(COMPOSE_FOLDER).join(folder)
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Bot {
     pub id: String,
@@ -49,6 +58,22 @@ pub struct Bot {
     pub base: String,
 }
 
+impl Bot {
+    /// Creates the SC2 built-in AI as a player, e.g. from `builtin:VeryHard:Zerg:Macro`
+    fn builtin_ai(slot: &str) -> Self {
+        Self {
+            id: slot.to_string(),
+            name: slot.trim_start_matches("builtin:").replace(':', "_"),
+            runtype: "builtin".to_string(),
+            base: String::new(),
+        }
+    }
+
+    pub fn is_builtin_ai(&self) -> bool {
+        self.runtype == "builtin"
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct MatchRequest {
     pub bot1: Bot,
@@ -60,6 +85,20 @@ impl MatchRequest {
     pub fn from_csv_line(line: &str) -> Self {
         let parts: Vec<&str> = line.split(',').map(|s| s.trim()).collect();
         let (bot1_type, bot1_base) = split(parts[3]);
+
+        // The built-in AI takes a single field and can only be player 2
+        if parts[4].starts_with("builtin:") {
+            return Self {
+                bot1: Bot {
+                    id: parts[0].to_string(),
+                    name: parts[1].to_string(),
+                    runtype: bot1_type,
+                    base: bot1_base,
+                },
+                bot2: Bot::builtin_ai(parts[4]),
+            };
+        }
+
         let (bot2_type, bot2_base) = split(parts[7]);
 
         Self {
@@ -1,14 +1,83 @@
-use crate::config::{Bot, ControllerConfig, MatchRequest};
+use crate::config::{host_path, Bot, ControllerConfig, MatchRequest, COMPOSE_FOLDER};
 use std::env::temp_dir;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-pub fn run_match(run_type: &str, config: &ControllerConfig, request: &MatchRequest) {
-    let match_directory = format!("{}/match", config.logs_directory);
-    let (bot1_controller, bot1_command, bot1_directory) = select_bot_controller(run_type, config, &request.bot1, &request.bot2, "bot1", "10001");
-    let (bot2_controller, bot2_command, bot2_directory) = select_bot_controller(run_type, config, &request.bot2, &request.bot1, "bot2", "10002");
+/// Where a match runs: its compose project, player seats and folders.
+/// Matches that run at the same time need different slots.
+pub struct MatchSlot {
+    /// The compose project, or the default project of the compose folder
+    pub project: Option<String>,
+    pub compose_file: String,
+    /// The logs of the match, as given in the compose file
+    pub logs_directory: String,
+    pub player_1_seat: u16,
+    pub player_2_seat: u16,
+    /// The ids of the matches start above it, so the replays of parallel matches get distinct names
+    pub match_id_offset: usize,
+    /// Where the logs of the containers go instead of the standard output
+    pub compose_log: Option<PathBuf>,
+}
+
+impl MatchSlot {
+    /// The slot of matches that run one after another, sharing the logs directory
+    pub fn sequential(config: &ControllerConfig) -> Self {
+        Self {
+            project: None,
+            compose_file: format!("{COMPOSE_FOLDER}/docker-compose.yaml"),
+            logs_directory: config.logs_directory.clone(),
+            player_1_seat: 10001,
+            player_2_seat: 10002,
+            match_id_offset: 0,
+            compose_log: None,
+        }
+    }
+
+    /// The slot of the given worker running the given match, with its own project, ports and folders
+    pub fn parallel(config: &ControllerConfig, worker: usize, match_number: usize) -> Self {
+        let logs_directory = format!("{}/match-{}", config.logs_directory, match_number);
+        let seat = 10001 + 2 * worker as u16;
+        Self {
+            project: Some(format!("client-controller-{}", worker + 1)),
+            compose_file: format!("{COMPOSE_FOLDER}/docker-compose-{}.yaml", worker + 1),
+            compose_log: Some(host_path(&logs_directory).join("docker-compose.log")),
+            logs_directory,
+            player_1_seat: seat,
+            player_2_seat: seat + 1,
+            // Each match has its own results file, so its id is the match number
+            match_id_offset: match_number - 1,
+        }
+    }
+
+    /// The match folder, as given in the compose file
+    pub fn match_directory(&self) -> String {
+        format!("{}/match", self.logs_directory)
+    }
+
+    fn compose(&self) -> Command {
+        let mut command = Command::new("docker");
+        command.arg("compose").arg("-f").arg(&self.compose_file);
+        if let Some(project) = &self.project {
+            command.arg("-p").arg(project);
+        }
+        command
+    }
+}
+
+/// Runs the match and returns the exit code of the match controller
+pub fn run_match(
+    run_type: &str,
+    config: &ControllerConfig,
+    request: &MatchRequest,
+    slot: &MatchSlot,
+) -> i32 {
+    let match_directory = slot.match_directory();
+    let player_1_seat = slot.player_1_seat.to_string();
+    let player_2_seat = slot.player_2_seat.to_string();
+    let (bot1_controller, bot1_command, bot1_directory) = select_bot_controller(run_type, config, &request.bot1, &request.bot2, "bot1", &player_1_seat);
+    let (bot2_controller, bot2_command, bot2_directory) = select_bot_controller(run_type, config, &request.bot2, &request.bot1, "bot2", &player_2_seat);
 
     // Prepare the template to schedule a match
     let template = include_str!("../templates/docker-compose.yaml");
@@ -27,21 +96,31 @@ pub fn run_match(run_type: &str, config: &ControllerConfig, request: &MatchReque
     let template = template.replace("PLACEHOLDER_BOT2_CONTROLLER", &bot2_controller);
     let template = template.replace("PLACEHOLDER_BOT2_COMMAND", &bot2_command);
     let template = template.replace("PLACEHOLDER_BOT2_DIRECTORY", &bot2_directory);
+    let template = template.replace("PLACEHOLDER_PLAYER1_SEAT", &player_1_seat);
+    let template = template.replace("PLACEHOLDER_PLAYER2_SEAT", &player_2_seat);
     let template = template.replace("PLACEHOLDER_GAMESETS_DIRECTORY", &config.gamesets_directory);
-    let template = template.replace("PLACEHOLDER_LOGS_DIRECTORY", &config.logs_directory);
+    let template = template.replace("PLACEHOLDER_LOGS_DIRECTORY", &slot.logs_directory);
     let template = template.replace("PLACEHOLDER_MATCH_DIRECTORY", &match_directory);
+    let template = template.replace("PLACEHOLDER_MATCH_ID_OFFSET", &slot.match_id_offset.to_string());
+    let template = if request.bot2.is_builtin_ai() {
+        // The built-in AI plays in the game controller and needs no bot controller
+        remove_service(&template, "bot_controller2")
+    } else {
+        template
+    };
 
-    let mut compose_file = File::create("target/docker-compose.yaml")
-        .unwrap_or_else(|e| panic!("Could not create docker-compose.yaml file: {e:?}"));
+    fs::create_dir_all(COMPOSE_FOLDER)
+        .unwrap_or_else(|e| panic!("Could not create {COMPOSE_FOLDER} directory: {e:?}"));
+    let mut compose_file = File::create(&slot.compose_file)
+        .unwrap_or_else(|e| panic!("Could not create {} file: {e:?}", slot.compose_file));
     compose_file.write_all(template.as_bytes())
-        .unwrap_or_else(|e| panic!("Could not write to docker-compose.yaml file: {e:?}"));
+        .unwrap_or_else(|e| panic!("Could not write to {} file: {e:?}", slot.compose_file));
 
-    println!("\nDocker compose:\n{}", template);
+    if slot.compose_log.is_none() {
+        println!("\nDocker compose:\n{}", template);
+    }
 
-    let status = Command::new("docker")
-        .arg("compose")
-        .arg("-f")
-        .arg("target/docker-compose.yaml")
+    let status = slot.compose()
         .arg("up")
         .arg("-d")
         .arg("--force-recreate")
@@ -50,24 +129,26 @@ pub fn run_match(run_type: &str, config: &ControllerConfig, request: &MatchReque
 
     if !status.success() {
         eprintln!("Failed to start docker compose");
-        std::process::exit(1);
+        stop_compose(slot);
+        return 1;
     }
 
-    let mut logs_process = Command::new("docker")
-        .arg("compose")
-        .arg("-f")
-        .arg("target/docker-compose.yaml")
-        .arg("logs")
-        .arg("-f")
+    let mut logs_command = slot.compose();
+    logs_command.arg("logs").arg("-f");
+    if let Some(compose_log) = &slot.compose_log {
+        let log_file = File::create(compose_log)
+            .unwrap_or_else(|e| panic!("Could not create {compose_log:?} file: {e:?}"));
+        logs_command.stdout(log_file);
+    }
+    let mut logs_process = logs_command
         .spawn()
         .expect("Unable to stream docker compose logs");
 
-    println!("Waiting for match_controller to exit...");
+    if slot.compose_log.is_none() {
+        println!("Waiting for match_controller to exit...");
+    }
     loop {
-        let output = Command::new("docker")
-            .arg("compose")
-            .arg("-f")
-            .arg("target/docker-compose.yaml")
+        let output = slot.compose()
             .arg("ps")
             .arg("--format")
             .arg("json")
@@ -87,10 +168,7 @@ pub fn run_match(run_type: &str, config: &ControllerConfig, request: &MatchReque
     }
 
     // Get exit code of match_controller
-    let output = Command::new("docker")
-        .arg("compose")
-        .arg("-f")
-        .arg("target/docker-compose.yaml")
+    let output = slot.compose()
         .arg("ps")
         .arg("--all")
         .arg("--format")
@@ -112,17 +190,24 @@ pub fn run_match(run_type: &str, config: &ControllerConfig, request: &MatchReque
         1
     };
 
-    println!("Match controller exited with code: {}", exit_code);
+    if slot.compose_log.is_none() {
+        println!("Match controller exited with code: {}", exit_code);
+    }
 
     // Stop logs process
     logs_process.kill().ok();
     logs_process.wait().ok();
 
-    // Stop docker compose
-    let status = Command::new("docker")
-        .arg("compose")
-        .arg("-f")
-        .arg("target/docker-compose.yaml")
+    stop_compose(slot);
+
+    if exit_code != 0 {
+        eprintln!("Match controller failed with exit code: {}", exit_code);
+    }
+    exit_code
+}
+
+fn stop_compose(slot: &MatchSlot) {
+    let status = slot.compose()
         .arg("down")
         .arg("--timeout=0")
         .status()
@@ -131,11 +216,26 @@ pub fn run_match(run_type: &str, config: &ControllerConfig, request: &MatchReque
     if !status.success() {
         eprintln!("Failed to stop docker compose");
     }
+}
 
-    if exit_code != 0 {
-        eprintln!("Match controller failed with exit code: {}", exit_code);
-        std::process::exit(exit_code);
+// Removes the service with the given name from the docker compose template
+fn remove_service(template: &str, service: &str) -> String {
+    let header = format!("  {service}:");
+    let mut skip = false;
+    let mut lines = Vec::new();
+
+    for line in template.lines() {
+        // Services are the only entries with an indentation of two spaces
+        let rest = line.strip_prefix("  ").unwrap_or(" ");
+        if !rest.starts_with(' ') && !rest.starts_with('#') {
+            skip = line == header;
+        }
+        if !skip {
+            lines.push(line);
+        }
     }
+
+    lines.join("\n") + "\n"
 }
 
 fn select_bot_controller(run_type: &str, config: &ControllerConfig, bot: &Bot, opponent: &Bot, bot_directory: &str, game_port: &str) -> (String, String, String) {
@@ -154,7 +254,7 @@ fn select_bot_controller(run_type: &str, config: &ControllerConfig, bot: &Bot, o
     } else if Path::new(&path).exists() {
         // This bot uses custom docker image and its code is not included in the image
         controller = bot.base.clone();
-        command = construct_bot_command(&bot.runtype, &bot.name, &game_port, &opponent.id);
+        command = construct_bot_command(&bot.runtype, &bot.name, game_port, &opponent.id);
         directory = format!("{}/{}", config.bots_directory, bot.name);
     } else {
         // This bot uses custom docker image and its code is included in the image
@@ -166,8 +266,8 @@ fn select_bot_controller(run_type: &str, config: &ControllerConfig, bot: &Bot, o
     (controller, command, directory)
 }
 
-fn construct_bot_command(bot_type: &String, bot_name: &String, game_port: &str, opponent_id: &String) -> String {
-    let command = match bot_type.as_str() {
+fn construct_bot_command(bot_type: &str, bot_name: &str, game_port: &str, opponent_id: &str) -> String {
+    let command = match bot_type {
         "cppwin32" => format!("wine {bot_name}.exe"),
         "cpplinux" => format!("./{bot_name}"),
         "dotnetcore" => format!("dotnet {bot_name}.dll"),
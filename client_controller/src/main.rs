@@ -1,16 +1,81 @@
 mod config;
 mod docker;
+mod queue;
+mod tournament;
 
 use crate::config::{initialize_config, MatchRequest};
-use crate::docker::run_match;
-use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Write};
-use std::path::Path;
+use crate::docker::{run_match, MatchSlot};
+use crate::queue::run_matches;
+use crate::tournament::Format;
+use clap::{crate_name, crate_version, value_parser, Arg, ArgAction, Command};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
 
 fn main() {
-    println!("Starting client controller");
+    let args = Command::new(crate_name!())
+        .version(crate_version!())
+        .about("Runs the matches of AI Arena with Docker Compose")
+        .arg(
+            Arg::new("parallel")
+                .long("parallel")
+                .value_name("N")
+                .value_parser(value_parser!(u64).range(1..))
+                .help("Number of matches to run at the same time, instead of PARALLEL of the configuration"),
+        )
+        .subcommand(
+            Command::new("tournament")
+                .about("Writes the matches of a tournament between the bots of BOTS_DIRECTORY")
+                .arg(
+                    Arg::new("format")
+                        .value_name("FORMAT")
+                        .required(true)
+                        .value_parser(["round-robin", "double-round-robin", "swiss"]),
+                )
+                .arg(
+                    Arg::new("maps")
+                        .long("maps")
+                        .value_name("MAPS")
+                        .required(true)
+                        .value_delimiter(',')
+                        .help("Maps of the matches, rotating from round to round"),
+                )
+                .arg(
+                    Arg::new("results")
+                        .long("results")
+                        .value_name("SUMMARY_FILE")
+                        .action(ArgAction::Append)
+                        .help("Summary files of the previous rounds of a Swiss tournament"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .value_name("MATCHES_FILE")
+                        .value_parser(value_parser!(PathBuf))
+                        .help("File to write the matches to, instead of the standard output"),
+                ),
+        )
+        .get_matches();
+
+    let mut config = initialize_config();
+    if let Some(parallel) = args.get_one::<u64>("parallel") {
+        config.parallel = *parallel as usize;
+    }
 
-    let config = initialize_config();
+    if let Some(args) = args.subcommand_matches("tournament") {
+        let format = args.get_one::<String>("format").unwrap().parse::<Format>().unwrap();
+        let maps: Vec<String> = args.get_many::<String>("maps").unwrap().cloned().collect();
+        let results: Vec<String> = args
+            .get_many::<String>("results")
+            .unwrap_or_default()
+            .cloned()
+            .collect();
+        let output = args.get_one::<PathBuf>("output").map(PathBuf::as_path);
+        tournament::generate(format, &config.bots_directory, &maps, &results, output);
+        return;
+    }
+
+    println!("Starting client controller");
 
     if !config.api_url.is_empty() {
         println!("Reading matches from API at: {}", config.api_url);
@@ -18,7 +83,10 @@ fn main() {
         println!("This version of client controller supports only the test-server-api with a single test match");
         let match_request = MatchRequest::from_csv_line("1,basic_bot,T,python,2,loser_bot,T,python,AutomatonLE");
 
-        run_match("aiarena", &config, &match_request);
+        let exit_code = run_match("aiarena", &config, &match_request, &MatchSlot::sequential(&config));
+        if exit_code != 0 {
+            std::process::exit(exit_code);
+        }
     } else if !config.matches_file.is_empty() {
         println!("Reading matches from file: {}", config.matches_file);
 
@@ -26,14 +94,7 @@ fn main() {
             .unwrap_or_else(|e| panic!("Could not open matches file {}: {e:?}", config.matches_file));
         let reader = BufReader::new(file);
 
-        fs::create_dir_all("target/logs/match")
-            .unwrap_or_else(|e| panic!("Could not create target directory: {e:?}"));
-        
-        let path = Path::new("target/logs/match/match_result.json");
-        if path.exists() {
-            let _ = fs::remove_file(path);
-        }
-
+        let mut lines = Vec::new();
         for line in reader.lines() {
             let line = line.unwrap_or_else(|e| panic!("Could not read line from matches file: {e:?}"));
             let line = line.trim();
@@ -42,20 +103,13 @@ fn main() {
             if line.is_empty() || line.starts_with('#') {
                 continue;
             }
-
-            let match_request = MatchRequest::from_csv_line(&line);
-
-            println!("Running match: {:?}", match_request);
-
-            // Store the current line in ./match file (overwrite)
-            let mut match_file = File::create("target/logs/match/match-request.csv")
-                .unwrap_or_else(|e| panic!("Could not create match file: {e:?}"));
-            match_file.write_all(line.as_bytes())
-                .unwrap_or_else(|e| panic!("Could not write to match file: {e:?}"));
-
-            run_match("test", &config, &match_request);
+            lines.push(line.to_string());
         }
 
+        let exit_code = run_matches(&config, &lines);
+        if exit_code != 0 {
+            std::process::exit(exit_code);
+        }
     } else {
         eprintln!("Client controller requires either API_URL or MATCHES_FILE to read matches!");
         std::process::exit(1);
@@ -0,0 +1,217 @@
+use crate::config::{host_path, ControllerConfig, MatchRequest};
+use crate::docker::{run_match, MatchSlot};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// The outcome of a match of the matches file, as written to the summary file
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MatchSummary {
+    pub number: usize,
+    pub line: String,
+    pub exit_code: i32,
+    /// The result submitted by the match controller, if it submitted one
+    pub result: Option<String>,
+    pub game_steps: Option<u32>,
+    pub duration_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Summary {
+    pub matches: usize,
+    pub failed: usize,
+    pub results: Vec<MatchSummary>,
+}
+
+impl Summary {
+    /// Reads a summary file written by a previous run of the client controller
+    pub fn read(summary_file: &str) -> Self {
+        let bytes = fs::read(summary_file)
+            .unwrap_or_else(|e| panic!("Could not read summary file {summary_file}: {e:?}"));
+        serde_json::from_slice(&bytes)
+            .unwrap_or_else(|e| panic!("Could not parse summary file {summary_file}: {e:?}"))
+    }
+}
+
+/// The results file of the match controller in the match folder
+#[derive(Deserialize, Default)]
+struct Results {
+    results: Vec<GameResult>,
+}
+
+#[derive(Deserialize)]
+struct GameResult {
+    #[serde(rename = "type")]
+    result: String,
+    game_steps: u32,
+}
+
+/// Runs the matches, `config.parallel` at a time, writes the summary file
+/// and returns the exit code of the first failed match
+pub fn run_matches(config: &ControllerConfig, lines: &[String]) -> i32 {
+    let summaries = if config.parallel > 1 {
+        run_parallel(config, lines)
+    } else {
+        run_sequential(config, lines)
+    };
+
+    let logs_directory = host_path(&config.logs_directory);
+    fs::create_dir_all(&logs_directory)
+        .unwrap_or_else(|e| panic!("Could not create {logs_directory:?} directory: {e:?}"));
+    let summary_file = logs_directory.join("summary.json");
+    // The summary of each matches file is kept, e.g. for the next round of a Swiss tournament
+    let matches_summary_file = logs_directory.join(matches_summary_name(&config.matches_file));
+    let summary = Summary {
+        matches: summaries.len(),
+        failed: summaries.iter().filter(|s| s.exit_code != 0).count(),
+        results: summaries,
+    };
+    let json = serde_json::to_string_pretty(&summary)
+        .unwrap_or_else(|e| panic!("Could not serialize the summary: {e:?}"));
+    for file in [&summary_file, &matches_summary_file] {
+        fs::write(file, &json).unwrap_or_else(|e| panic!("Could not write {file:?} file: {e:?}"));
+    }
+
+    let failed: Vec<&MatchSummary> = summary
+        .results
+        .iter()
+        .filter(|s| s.exit_code != 0)
+        .collect();
+    for match_summary in &failed {
+        eprintln!(
+            "Match {} failed with exit code {}: {}",
+            match_summary.number, match_summary.exit_code, match_summary.line
+        );
+    }
+    println!(
+        "Finished {} matches, {} failed. Summary is in {:?} and {:?}",
+        summary.matches, summary.failed, summary_file, matches_summary_file
+    );
+
+    failed.first().map_or(0, |s| s.exit_code)
+}
+
+/// The name of the summary file of the matches file, e.g. `round-1.summary.json` for `round-1.csv`
+fn matches_summary_name(matches_file: &str) -> String {
+    let stem = Path::new(matches_file)
+        .file_stem()
+        .map_or("matches".into(), |stem| stem.to_string_lossy());
+    format!("{stem}.summary.json")
+}
+
+/// Runs the matches one after another in the logs directory, like a single match
+fn run_sequential(config: &ControllerConfig, lines: &[String]) -> Vec<MatchSummary> {
+    let slot = MatchSlot::sequential(config);
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            println!("[{}/{}] Running match: {}", index + 1, lines.len(), line);
+            let summary = run_queued_match(config, &slot, index + 1, line);
+            print_progress(&summary, index + 1, lines.len());
+            summary
+        })
+        .collect()
+}
+
+/// Runs the matches on `config.parallel` workers, each with its own slot per match
+fn run_parallel(config: &ControllerConfig, lines: &[String]) -> Vec<MatchSummary> {
+    let next_match = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let summaries = Mutex::new(Vec::with_capacity(lines.len()));
+    let workers = config.parallel.min(lines.len());
+    println!("Running {} matches on {} workers", lines.len(), workers);
+
+    std::thread::scope(|scope| {
+        for worker in 0..workers {
+            let (next_match, finished, summaries) = (&next_match, &finished, &summaries);
+            scope.spawn(move || loop {
+                let index = next_match.fetch_add(1, Ordering::SeqCst);
+                let Some(line) = lines.get(index) else {
+                    break;
+                };
+                let number = index + 1;
+                let slot = MatchSlot::parallel(config, worker, number);
+                let logs_directory = host_path(&slot.logs_directory);
+                if logs_directory.exists() {
+                    // Start over from a previous run of the matches file
+                    let _ = fs::remove_dir_all(&logs_directory);
+                }
+                println!(
+                    "Starting match {} on worker {} with logs in {:?}: {}",
+                    number,
+                    worker + 1,
+                    logs_directory,
+                    line
+                );
+
+                let summary = run_queued_match(config, &slot, number, line);
+                let done = finished.fetch_add(1, Ordering::SeqCst) + 1;
+                print_progress(&summary, done, lines.len());
+                summaries.lock().unwrap().push(summary);
+            });
+        }
+    });
+
+    let mut summaries = summaries.into_inner().unwrap();
+    summaries.sort_by_key(|s| s.number);
+    summaries
+}
+
+fn run_queued_match(
+    config: &ControllerConfig,
+    slot: &MatchSlot,
+    number: usize,
+    line: &str,
+) -> MatchSummary {
+    let match_directory = host_path(&slot.match_directory());
+    fs::create_dir_all(&match_directory)
+        .unwrap_or_else(|e| panic!("Could not create {match_directory:?} directory: {e:?}"));
+    let _ = fs::remove_file(match_directory.join("match_result.json"));
+
+    // Store the line as the only match of the match controller
+    let request_file = match_directory.join("match-request.csv");
+    fs::write(&request_file, line)
+        .unwrap_or_else(|e| panic!("Could not write {request_file:?} file: {e:?}"));
+
+    let results_file = match_directory.join("results.json");
+    let previous_results = read_results(&results_file).results.len();
+    let start_time = Instant::now();
+    let exit_code = run_match("test", config, &MatchRequest::from_csv_line(line), slot);
+
+    // The results file keeps the results of the previous matches of the slot
+    let result = read_results(&results_file)
+        .results
+        .into_iter()
+        .nth(previous_results);
+    MatchSummary {
+        number,
+        line: line.to_string(),
+        exit_code,
+        result: result.as_ref().map(|r| r.result.clone()),
+        game_steps: result.map(|r| r.game_steps),
+        duration_secs: start_time.elapsed().as_secs(),
+    }
+}
+
+fn read_results(results_file: &Path) -> Results {
+    fs::read(results_file)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn print_progress(summary: &MatchSummary, done: usize, total: usize) {
+    let outcome = match (&summary.result, summary.exit_code) {
+        (Some(result), 0) => result.clone(),
+        (Some(result), exit_code) => format!("{result} (exit code {exit_code})"),
+        (None, exit_code) => format!("no result (exit code {exit_code})"),
+    };
+    println!(
+        "[{}/{}] Match {} finished in {}s: {}",
+        done, total, summary.number, summary.duration_secs, outcome
+    );
+}
@@ -0,0 +1,436 @@
+use crate::queue::Summary;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The metadata file of a bot, in the format of the AI Arena ladder
+pub const METADATA_FILE: &str = "ladderbots.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    RoundRobin,
+    DoubleRoundRobin,
+    Swiss,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "round-robin" => Ok(Self::RoundRobin),
+            "double-round-robin" => Ok(Self::DoubleRoundRobin),
+            "swiss" => Ok(Self::Swiss),
+            _ => Err(format!("Unknown tournament format: {input}")),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LadderBots {
+    #[serde(rename = "Bots")]
+    bots: HashMap<String, LadderBot>,
+}
+
+#[derive(Deserialize)]
+struct LadderBot {
+    #[serde(rename = "Race")]
+    race: String,
+    #[serde(rename = "Type")]
+    bot_type: String,
+}
+
+/// A bot of the tournament, with the fields of a player in the matches file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TournamentBot {
+    pub id: String,
+    pub name: String,
+    pub race: String,
+    pub bot_type: String,
+}
+
+impl TournamentBot {
+    fn from_metadata(name: &str, metadata: &LadderBot) -> Self {
+        let race = match metadata.race.to_lowercase().as_str() {
+            "terran" | "t" => "T",
+            "zerg" | "z" => "Z",
+            "protoss" | "p" => "P",
+            _ => "R",
+        };
+        let bot_type = match metadata.bot_type.to_lowercase().as_str() {
+            "binarycpp" => "cpplinux".to_string(),
+            "wine" => "cppwin32".to_string(),
+            bot_type => bot_type.to_string(),
+        };
+        Self {
+            id: name.to_string(),
+            name: name.to_string(),
+            race: race.to_string(),
+            bot_type,
+        }
+    }
+
+    fn csv(&self) -> String {
+        format!("{},{},{},{}", self.id, self.name, self.race, self.bot_type)
+    }
+}
+
+/// A match of the tournament between the bots with the given indices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pairing {
+    pub round: usize,
+    pub player1: usize,
+    pub player2: usize,
+}
+
+/// Reads the bots of the subfolders of the bots directory that have a metadata file
+pub fn read_bots(bots_directory: &str) -> Vec<TournamentBot> {
+    let entries = fs::read_dir(bots_directory)
+        .unwrap_or_else(|e| panic!("Could not read bots directory {bots_directory}: {e:?}"));
+
+    let mut bots = Vec::new();
+    for entry in entries.flatten() {
+        let metadata_file = entry.path().join(METADATA_FILE);
+        let Ok(bytes) = fs::read(&metadata_file) else {
+            continue;
+        };
+        let name = entry.file_name().to_string_lossy().to_string();
+        let ladder_bots = serde_json::from_slice::<LadderBots>(&bytes)
+            .unwrap_or_else(|e| panic!("Could not parse {metadata_file:?}: {e:?}"));
+
+        // The metadata can list several bots, of which the one named like the folder plays
+        let metadata = ladder_bots
+            .bots
+            .get(&name)
+            .or_else(|| ladder_bots.bots.values().next());
+        match metadata {
+            Some(metadata) => bots.push(TournamentBot::from_metadata(&name, metadata)),
+            None => eprintln!("Skipping bot {name} without entries in {METADATA_FILE}"),
+        }
+    }
+
+    bots.sort_by(|a, b| a.name.cmp(&b.name));
+    bots
+}
+
+/// Pairs every bot with every other bot once, or twice with swapped sides.
+/// The bots alternate between player 1 and player 2 from round to round.
+pub fn round_robin(bots: usize, double: bool) -> Vec<Pairing> {
+    // An odd number of bots gets a placeholder, whose opponent sits the round out
+    let slots = bots + bots % 2;
+    let mut circle: Vec<usize> = (0..slots).collect();
+    let rounds = slots.saturating_sub(1);
+    let mut pairings = Vec::new();
+
+    for round in 0..rounds {
+        for table in 0..slots / 2 {
+            let (home, away) = (circle[table], circle[slots - 1 - table]);
+            let (player1, player2) = match (table, round % 2) {
+                (0, 0) => (home, away),
+                (0, _) => (away, home),
+                _ => (home, away),
+            };
+            if player1 < bots && player2 < bots {
+                pairings.push(Pairing {
+                    round: round + 1,
+                    player1,
+                    player2,
+                });
+            }
+        }
+        // Keeps the last slot in place and rotates the others
+        circle[..slots - 1].rotate_right(1);
+    }
+
+    if double {
+        let second_leg: Vec<Pairing> = pairings
+            .iter()
+            .map(|p| Pairing {
+                round: p.round + rounds,
+                player1: p.player2,
+                player2: p.player1,
+            })
+            .collect();
+        pairings.extend(second_leg);
+    }
+
+    pairings
+}
+
+/// The standing of a bot in a Swiss tournament
+#[derive(Debug, Clone, Default)]
+struct Standing {
+    /// Two points for a win and one for a tie or a bye
+    points: u32,
+    games: usize,
+    player1_games: usize,
+    opponents: HashSet<usize>,
+}
+
+/// Pairs the bots for the next round of a Swiss tournament from the results of the previous rounds.
+/// Returns the pairings and the bot that sits the round out, if the number of bots is odd.
+pub fn swiss_round(bots: &[TournamentBot], summaries: &[Summary]) -> (Vec<Pairing>, Option<usize>) {
+    let index: HashMap<&str, usize> = bots
+        .iter()
+        .enumerate()
+        .map(|(i, bot)| (bot.id.as_str(), i))
+        .collect();
+    let mut standings = vec![Standing::default(); bots.len()];
+
+    for result in summaries.iter().flat_map(|s| &s.results) {
+        let fields: Vec<&str> = result.line.split(',').map(str::trim).collect();
+        let (Some(player1), Some(player2)) = (
+            fields.first().and_then(|id| index.get(id)),
+            fields.get(4).and_then(|id| index.get(id)),
+        ) else {
+            continue;
+        };
+        let (points1, points2) = points(result.result.as_deref());
+        for (bot, opponent, points) in
+            [(*player1, *player2, points1), (*player2, *player1, points2)]
+        {
+            let standing = &mut standings[bot];
+            standing.points += points;
+            standing.games += 1;
+            standing.opponents.insert(opponent);
+        }
+        standings[*player1].player1_games += 1;
+    }
+
+    // Bots that played fewer rounds than others sat those rounds out
+    let played_rounds = standings.iter().map(|s| s.games).max().unwrap_or(0);
+    let byes: Vec<usize> = standings.iter().map(|s| played_rounds - s.games).collect();
+    for (standing, byes) in standings.iter_mut().zip(&byes) {
+        standing.points += *byes as u32;
+    }
+    let round = played_rounds + 1;
+
+    let mut ranking: Vec<usize> = (0..bots.len()).collect();
+    ranking.sort_by(|a, b| {
+        standings[*b]
+            .points
+            .cmp(&standings[*a].points)
+            .then_with(|| bots[*a].name.cmp(&bots[*b].name))
+    });
+
+    // The lowest ranked bot with the fewest byes sits out
+    let bye = if ranking.len() % 2 == 1 {
+        let position = (0..ranking.len())
+            .rev()
+            .min_by_key(|position| byes[ranking[*position]])
+            .unwrap_or(0);
+        Some(ranking.remove(position))
+    } else {
+        None
+    };
+
+    let mut pairings = Vec::new();
+    while let Some(bot) = ranking.first().copied() {
+        ranking.remove(0);
+        // The next ranked bot it has not played yet, or the next ranked bot if it played all
+        let position = ranking
+            .iter()
+            .position(|opponent| !standings[bot].opponents.contains(opponent))
+            .unwrap_or(0);
+        let opponent = ranking.remove(position);
+
+        let (bot_p1, opponent_p1) = (
+            standings[bot].player1_games,
+            standings[opponent].player1_games,
+        );
+        let bot_is_player1 = bot_p1 < opponent_p1 || (bot_p1 == opponent_p1 && round % 2 == 1);
+        let (player1, player2) = if bot_is_player1 {
+            (bot, opponent)
+        } else {
+            (opponent, bot)
+        };
+        pairings.push(Pairing {
+            round,
+            player1,
+            player2,
+        });
+    }
+
+    (pairings, bye)
+}
+
+/// The points of player 1 and player 2 for the result of a match
+fn points(result: Option<&str>) -> (u32, u32) {
+    match result {
+        Some("Player1Win" | "Player2Crash" | "Player2TimeOut") => (2, 0),
+        Some("Player2Win" | "Player1Crash" | "Player1TimeOut") => (0, 2),
+        Some("Tie") => (1, 1),
+        _ => (0, 0),
+    }
+}
+
+/// Writes the pairings as lines of the matches file. The maps rotate from round to round.
+pub fn matches_file(bots: &[TournamentBot], pairings: &[Pairing], maps: &[String]) -> String {
+    let mut lines = String::new();
+    let mut round = 0;
+    for pairing in pairings {
+        if pairing.round != round {
+            round = pairing.round;
+            lines.push_str(&format!("# Round {round}\n"));
+        }
+        let map = &maps[(pairing.round - 1) % maps.len()];
+        lines.push_str(&format!(
+            "{},{},{}\n",
+            bots[pairing.player1].csv(),
+            bots[pairing.player2].csv(),
+            map
+        ));
+    }
+    lines
+}
+
+/// Generates the matches of the tournament, or of its next round for a Swiss tournament
+pub fn generate(
+    format: Format,
+    bots_directory: &str,
+    maps: &[String],
+    results: &[String],
+    output: Option<&Path>,
+) {
+    let bots = read_bots(bots_directory);
+    if bots.len() < 2 {
+        eprintln!(
+            "A tournament requires at least two bots with {METADATA_FILE} in {bots_directory}"
+        );
+        std::process::exit(1);
+    }
+    println!(
+        "Generating {:?} tournament for {} bots: {}",
+        format,
+        bots.len(),
+        bots.iter()
+            .map(|b| b.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let pairings = match format {
+        Format::RoundRobin => round_robin(bots.len(), false),
+        Format::DoubleRoundRobin => round_robin(bots.len(), true),
+        Format::Swiss => {
+            let summaries: Vec<Summary> = results.iter().map(|file| Summary::read(file)).collect();
+            let (pairings, bye) = swiss_round(&bots, &summaries);
+            if let Some(bye) = bye {
+                println!(
+                    "Bot {} sits out round {}",
+                    bots[bye].name, pairings[0].round
+                );
+            }
+            pairings
+        }
+    };
+
+    let lines = matches_file(&bots, &pairings, maps);
+    match output {
+        Some(output) => {
+            fs::write(output, lines)
+                .unwrap_or_else(|e| panic!("Could not write matches file {output:?}: {e:?}"));
+            println!("Wrote {} matches to {:?}", pairings.len(), output);
+        }
+        None => print!("{lines}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queue::MatchSummary;
+
+    fn bots(count: usize) -> Vec<TournamentBot> {
+        (0..count)
+            .map(|i| TournamentBot {
+                id: format!("{i}"),
+                name: format!("bot_{i}"),
+                race: "T".to_string(),
+                bot_type: "python".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_robin_pairs_all_bots_once() {
+        for count in 2..=9 {
+            let pairings = round_robin(count, false);
+            let pairs: HashSet<(usize, usize)> = pairings
+                .iter()
+                .map(|p| (p.player1.min(p.player2), p.player1.max(p.player2)))
+                .collect();
+            assert_eq!(pairings.len(), count * (count - 1) / 2);
+            assert_eq!(pairs.len(), pairings.len());
+
+            for bot in 0..count {
+                let player1 = pairings.iter().filter(|p| p.player1 == bot).count();
+                let player2 = pairings.iter().filter(|p| p.player2 == bot).count();
+                assert!(
+                    player1.abs_diff(player2) <= 1,
+                    "{count} bots: {bot} has {player1}/{player2}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_double_round_robin_swaps_sides() {
+        let pairings = round_robin(4, true);
+        assert_eq!(pairings.len(), 12);
+        for bot in 0..4 {
+            assert_eq!(pairings.iter().filter(|p| p.player1 == bot).count(), 3);
+        }
+        assert_eq!(pairings.last().unwrap().round, 6);
+    }
+
+    #[test]
+    fn test_swiss_round_pairs_by_score_without_rematch() {
+        let bots = bots(5);
+        let summary = |line: &str, result: &str| MatchSummary {
+            number: 1,
+            line: line.to_string(),
+            exit_code: 0,
+            result: Some(result.to_string()),
+            game_steps: None,
+            duration_secs: 0,
+        };
+        let round_1 = Summary {
+            matches: 2,
+            failed: 0,
+            results: vec![
+                summary("0,bot_0,T,python,1,bot_1,T,python,Map", "Player1Win"),
+                summary("2,bot_2,T,python,3,bot_3,T,python,Map", "Player2Win"),
+            ],
+        };
+
+        let (pairings, bye) = swiss_round(&bots, &[round_1]);
+        // bot_4 sat out round 1 and scores like a tie, so bot_1 or bot_2 sits out round 2
+        assert_eq!(bye, Some(2));
+        assert_eq!(pairings.len(), 2);
+        assert!(pairings.iter().all(|p| p.round == 2));
+        let pair = |a, b| {
+            pairings
+                .iter()
+                .any(|p| (p.player1, p.player2) == (a, b) || (p.player1, p.player2) == (b, a))
+        };
+        assert!(pair(0, 3));
+        assert!(pair(4, 1));
+        // bot_0 played player 1 in round 1, so it plays player 2 now
+        assert!(pairings.iter().any(|p| p.player1 == 3 && p.player2 == 0));
+    }
+
+    #[test]
+    fn test_matches_file_rotates_maps() {
+        let bots = bots(2);
+        let pairings = round_robin(2, true);
+        let maps = vec!["MapA".to_string(), "MapB".to_string()];
+        assert_eq!(
+            matches_file(&bots, &pairings, &maps),
+            "# Round 1\n0,bot_0,T,python,1,bot_1,T,python,MapA\n\
+             # Round 2\n1,bot_1,T,python,0,bot_0,T,python,MapB\n"
+        );
+    }
+}
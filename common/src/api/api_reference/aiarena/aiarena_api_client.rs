@@ -1,4 +1,6 @@
+use crate::api::api_reference::aiarena::create_part_from_path;
 use crate::api::api_reference::aiarena::errors::AiArenaApiError;
+use std::path::Path;
 use std::time::Duration;
 
 use crate::api::api_reference::{ApiError, ControllerApi, ResponseContent};
@@ -235,7 +237,7 @@ impl AiArenaApiClient {
         &self,
         url: &str,
         unique_key: String,
-        file: &[u8],
+        file: &Path,
     ) -> Result<(), ApiError<String>> {
         let mut local_var_resp_result;
         let mut counter = 0;
@@ -243,7 +245,10 @@ impl AiArenaApiClient {
             let mut request_builder = self.client.request(reqwest::Method::POST, url);
             request_builder = request_builder.query(&[("uniqueKey", &unique_key.to_string())]);
             let mut local_var_form = Form::new();
-            let part = reqwest::multipart::Part::bytes(file.to_vec()).file_name(unique_key.clone());
+            // The file is streamed again on every attempt
+            let part = create_part_from_path(file)
+                .await?
+                .file_name(unique_key.clone());
             local_var_form = local_var_form.part("file", part);
 
             request_builder = request_builder.multipart(local_var_form);
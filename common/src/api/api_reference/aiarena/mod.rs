@@ -131,16 +131,15 @@ impl From<&AiArenaGameResult> for AiArenaResultForm {
     }
 }
 
+/// A multipart file part that streams the file from disk while it is sent
 pub async fn create_part_from_path(
     path: &Path,
 ) -> Result<reqwest::multipart::Part, std::io::Error> {
     let file_name = String::from(path.file_name().and_then(|p| p.to_str()).unwrap());
-    let file = tokio::fs::read(path).await?;
+    let file = tokio::fs::File::open(path).await?;
+    let length = file.metadata().await?.len();
+    let body = reqwest::Body::wrap_stream(tokio_util::io::ReaderStream::new(file));
 
-    let file_part = reqwest::multipart::Part::bytes(file).file_name(file_name);
+    let file_part = reqwest::multipart::Part::stream_with_length(body, length).file_name(file_name);
     Ok(file_part)
 }
-
-pub fn create_part_from_bytes(bytes: Vec<u8>, filename: String) -> reqwest::multipart::Part {
-    reqwest::multipart::Part::bytes(bytes).file_name(filename)
-}
@@ -0,0 +1,85 @@
+use crate::api::errors::app_error::ApiErrorMessage;
+use crate::models::match_controller::ControllerEvent;
+use crate::models::Status;
+use async_trait::async_trait;
+use reqwest::{Client, ClientBuilder, Url};
+use std::time::Duration;
+
+use crate::api::api_reference::{ApiError, ControllerApi};
+
+/// Environment variable with the URL of the match controller, e.g. `http://match_controller:8080`
+pub const MATCH_CONTROLLER_URL_ENV: &str = "MATCH_CONTROLLER_URL";
+
+#[derive(Debug, Clone)]
+pub struct MatchController {
+    client: Client,
+    url: Url,
+}
+
+impl MatchController {
+    pub fn new(url: &str) -> Result<Self, url::ParseError> {
+        let url = Url::parse(url)?;
+
+        Ok(Self {
+            url,
+            client: ClientBuilder::new()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap(),
+        })
+    }
+
+    /// The match controller at the URL in `MATCH_CONTROLLER_URL`, if it is set
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var(MATCH_CONTROLLER_URL_ENV).ok()?;
+        match Self::new(&url) {
+            Ok(controller) => Some(controller),
+            Err(e) => {
+                tracing::error!("Invalid {}: {:?}", MATCH_CONTROLLER_URL_ENV, e);
+                None
+            }
+        }
+    }
+
+    pub async fn send_event(
+        &self,
+        event: &ControllerEvent,
+    ) -> Result<Status, ApiError<ApiErrorMessage>> {
+        let events_url = self.url.join("/events").unwrap(); // static string, so the constructor should catch any parse
+                                                            // errors
+
+        let request = self
+            .client
+            .request(reqwest::Method::POST, events_url)
+            .json(event)
+            .build()?;
+
+        self.execute_request(request).await
+    }
+
+    /// Sends the event, retrying every second while the match controller is not reachable
+    pub async fn send_event_with_retry(&self, event: &ControllerEvent, max_retries: u32) -> bool {
+        for _ in 0..=max_retries {
+            match self.send_event(event).await {
+                Ok(_) => return true,
+                Err(e) => tracing::debug!("Could not send event {:?}: {}", event, e),
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        tracing::error!("Could not send event {:?} to the match controller", event);
+        false
+    }
+}
+
+#[async_trait]
+impl ControllerApi for MatchController {
+    const API_TYPE: &'static str = "MatchController";
+
+    fn url(&self) -> &Url {
+        &self.url
+    }
+
+    fn client(&self) -> &Client {
+        &self.client
+    }
+}
@@ -14,6 +14,7 @@ use std::{error, fmt};
 
 pub mod aiarena;
 pub mod bot_controller_client;
+pub mod match_controller_client;
 pub mod sc2_controller_client;
 
 #[async_trait]
@@ -1,4 +1,5 @@
 use crate::api::errors::app_error::ApiErrorMessage;
+use crate::models::sc2_controller::ControllerStatus;
 use crate::models::Status;
 use crate::portpicker::Port;
 use async_trait::async_trait;
@@ -34,6 +35,18 @@ impl SC2Controller {
 
         self.execute_request(request).await
     }
+
+    pub async fn controller_status(&self) -> Result<ControllerStatus, ApiError<ApiErrorMessage>> {
+        let status_url = self.url.join("/status").unwrap(); // static string, so the constructor should catch any parse
+                                                            // errors
+
+        let request = self
+            .client
+            .request(reqwest::Method::GET, status_url)
+            .build()?;
+
+        self.execute_request(request).await
+    }
 }
 
 #[async_trait]
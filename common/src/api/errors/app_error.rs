@@ -97,6 +97,14 @@ impl IntoResponse for AppError {
             }
             Self::Download(DownloadError::Other(e)) => (StatusCode::INTERNAL_SERVER_ERROR, e),
             Self::Download(DownloadError::NotAvailable(e)) => (StatusCode::NOT_IMPLEMENTED, e),
+            Self::Download(DownloadError::HashMismatch {
+                asset,
+                expected,
+                actual,
+            }) => (
+                StatusCode::BAD_GATEWAY,
+                format!("{asset} has md5 hash {actual} instead of {expected}"),
+            ),
         };
 
         let body = Json(ApiErrorMessage {
@@ -11,6 +11,12 @@ pub enum DownloadError {
     Io(Error),
     ZipError(anyhow::Error),
     NotAvailable(String),
+    /// The downloaded file of the asset does not match the md5 hash of the match
+    HashMismatch {
+        asset: String,
+        expected: String,
+        actual: String,
+    },
     Other(String),
 }
 
@@ -7,7 +7,6 @@ use std::sync::Arc;
 
 use crate::api::errors::app_error::AppError;
 use crate::api::errors::process_error::ProcessError;
-use crate::api::state::AppState;
 use crate::models::stats::{HostStats, ProcessStats};
 use crate::models::ProcessStatusResponse;
 use crate::utilities::portpicker::Port;
@@ -35,7 +34,7 @@ pub async fn stats_host() -> Result<Json<HostStats>, AppError> {
     Ok(Json(host_stats))
 }
 
-#[tracing::instrument(skip(state))]
+#[tracing::instrument(skip(process_map))]
 #[cfg_attr(feature = "swagger", utoipa::path(
     get,
     path = "/stats/{process_key}",
@@ -48,24 +47,26 @@ pub async fn stats_host() -> Result<Json<HostStats>, AppError> {
 ))]
 pub async fn stats(
     Path(port): Path<Port>,
-    State(state): State<AppState>,
+    State(process_map): State<ProcessMap>,
 ) -> Result<Json<ProcessStats>, AppError> {
     let sys = sysinfo::System::new_all();
-    if let Some(child) = state.process_map.read().get(&port) {
-        let pid = Pid::from_u32(child.id());
+    // The read lock has to be released before the process is removed
+    let child_id = process_map.read().get(&port).map(|child| child.id());
+    if let Some(child_id) = child_id {
+        let pid = Pid::from_u32(child_id);
         if let Some(process) = sys.process(pid) {
             let process_stats = ProcessStats::new(process);
             Ok(Json(process_stats))
         } else {
-            state.process_map.write().remove(&port);
-            Err(ProcessError::NotFound(child.id()).into())
+            process_map.write().remove(&port);
+            Err(ProcessError::NotFound(child_id).into())
         }
     } else {
         Err(ProcessError::NotInProcessMap(port).into())
     }
 }
 
-#[tracing::instrument(skip(state))]
+#[tracing::instrument(skip(process_map))]
 #[cfg_attr(feature = "swagger", utoipa::path(
     get,
     path = "/status/{process_key}",
@@ -78,10 +79,10 @@ pub async fn stats(
 ))]
 pub async fn status(
     Path(port): Path<Port>,
-    State(state): State<AppState>,
+    State(process_map): State<ProcessMap>,
 ) -> Result<Json<ProcessStatusResponse>, AppError> {
     let sys = sysinfo::System::new_all();
-    if let Some(child) = state.process_map.read().get(&port) {
+    if let Some(child) = process_map.read().get(&port) {
         let pid = Pid::from_u32(child.id());
         if let Some(process) = sys.process(pid) {
             let status = ProcStatus::from(process.status());
@@ -101,7 +102,7 @@ pub async fn status(
     }
 }
 
-#[tracing::instrument(skip(state))]
+#[tracing::instrument(skip(process_map))]
 #[cfg_attr(feature = "swagger", utoipa::path(
     get,
     path = "/stats_all",
@@ -109,10 +110,11 @@ pub async fn status(
         (status = 200, description = "Get procs stats for all processes ")
     )
 ))]
-pub async fn stats_all(State(state): State<AppState>) -> Result<Json<Vec<ProcessStats>>, AppError> {
+pub async fn stats_all(
+    State(process_map): State<ProcessMap>,
+) -> Result<Json<Vec<ProcessStats>>, AppError> {
     let sys = sysinfo::System::new_all();
-    let process_stats: Vec<ProcessStats> = state
-        .process_map
+    let process_stats: Vec<ProcessStats> = process_map
         .read()
         .iter()
         .filter_map(|(_, child)| {
@@ -1,5 +1,6 @@
 use crate::api::process::ProcessMap;
 use crate::configuration::ac_config::ACConfig;
+use axum::extract::FromRef;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -12,3 +13,9 @@ pub struct AppState {
     pub extra_info: Arc<RwLock<HashMap<String, HashMap<String, String>>>>,
     pub ws_shutdown_senders: Arc<RwLock<Vec<tokio::sync::oneshot::Sender<()>>>>,
 }
+
+impl FromRef<AppState> for ProcessMap {
+    fn from_ref(state: &AppState) -> Self {
+        state.process_map.clone()
+    }
+}
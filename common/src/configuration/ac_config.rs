@@ -1,4 +1,6 @@
+use crate::paths::MatchLayout;
 use crate::utilities::portpicker::Port;
+use crate::PlayerNum;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -6,34 +8,109 @@ pub struct ACConfig {
     #[serde(skip_serializing)] // Don't expose via config endpoint
     pub api_token: Option<String>,
     pub arena_client_id: String,
+    /// Folder of the cached bots, bot data and maps. Empty disables the cache
+    #[serde(default)]
+    pub asset_cache_directory: String,
+    #[serde(default = "default_asset_cache_max_bytes")]
+    pub asset_cache_max_bytes: u64,
     pub base_website_url: String,
     pub bot_directory: String,
     pub bot_cont_1_host: String,
     pub bot_cont_1_port: Port,
     pub bot_cont_2_host: String,
     pub bot_cont_2_port: Port,
+    /// Bot data folders over this size are not uploaded. Zero disables the quota
+    #[serde(default)]
+    pub data_quota_bytes: u64,
     pub debug_mode: bool,
     pub disable_debug: bool,
     pub game_directory: String,
     pub hash_check: bool,
     pub logging_level: String,
     pub log_root: String,
+    /// Log files over this size are truncated in the uploaded logs. Zero disables the truncation
+    #[serde(default)]
+    pub log_file_max_bytes: u64,
+    #[serde(default)]
+    pub log_truncation: LogTruncation,
+    #[serde(default = "default_match_directory")]
+    pub match_directory: String,
+    /// The matches of the matches file get ids above this offset, so parallel runs don't share ids
+    #[serde(default)]
+    pub match_id_offset: u32,
     pub matches_file: String,
     pub max_frame_time: i32,
+    #[serde(default)]
+    pub enforce_frame_time: bool,
+    #[serde(default)]
+    pub frame_time_bank: u64,
+    #[serde(default)]
+    pub max_frame_time_strikes: u32,
+    #[serde(default)]
+    pub hard_frame_time: u64,
     pub max_game_time: u32,
     pub max_real_time: i64,
+    #[serde(default)]
+    pub real_time_limit_result: RealTimeLimitResult,
     pub timeout_secs: u64,
+    /// Archives and replays over this size are not uploaded. Zero disables the limit
+    #[serde(default)]
+    pub upload_max_bytes: u64,
+    #[serde(default = "default_join_timeout_secs")]
+    pub join_timeout_secs: u64,
+    #[serde(default)]
+    pub max_actions_per_step: u32,
+    #[serde(default)]
+    pub max_queries_per_step: u32,
+    #[serde(default)]
+    pub max_apm: u32,
+    #[serde(default)]
+    pub rate_limit_mode: RateLimitMode,
+    #[serde(default = "default_allowed_requests")]
+    pub allowed_requests: Vec<String>,
+    #[serde(default = "default_allowed_debug_commands")]
+    pub allowed_debug_commands: Vec<String>,
     pub python: String,
     pub realtime: bool,
+    #[serde(default)]
+    pub record_protocol: bool,
+    #[serde(default)]
+    pub protocol_record_max_bytes: u64,
+    #[serde(default)]
+    pub protocol_record_observation_interval: u32,
+    #[serde(default)]
+    pub restart_sc2: bool,
     pub results_file: String,
     pub run_type: RunType,
     pub sc2_cont_host: String,
     pub sc2_cont_port: Port,
+    /// Folder of the results that wait to be submitted to AI Arena. Empty disables the outbox
+    #[serde(default)]
+    pub submission_outbox_directory: String,
     pub validate_race: bool,
     pub visualize: bool,
     pub aws: bool,
     pub caching_server_url: String,
     pub keep_alive: bool,
+    #[serde(default)]
+    pub event_port: Port,
+}
+
+impl ACConfig {
+    /// The folders of the match controller, with the match files in `match_directory`
+    /// and its logs in `log_root`
+    pub fn match_layout(&self) -> MatchLayout {
+        MatchLayout {
+            match_folder: self.match_directory.clone().into(),
+            log_folder: self.log_root.clone().into(),
+            ..MatchLayout::default()
+        }
+    }
+
+    /// The folders of the bot controller of the player, with its logs in `log_root`
+    pub fn bot_controller_layout(&self, player_num: PlayerNum) -> MatchLayout {
+        self.match_layout().bot_controller_layout(player_num)
+    }
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -44,42 +121,159 @@ pub enum RunType {
     Submit,
 }
 
+/// The result recorded for a match that exceeds the real time limit
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub enum RealTimeLimitResult {
+    #[default]
+    Tie,
+    Error,
+}
+
+/// The part of a log file that is uploaded when it exceeds `log_file_max_bytes`
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub enum LogTruncation {
+    /// Keep the start and the end of the log, with the setup and the last messages of a bot
+    #[default]
+    HeadAndTail,
+    Head,
+    Tail,
+}
+
+/// The handling of bot requests that exceed the rate limits
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub enum RateLimitMode {
+    /// Forward the request and only count the violation
+    #[default]
+    Tag,
+    /// Drop the actions or queries over the limit
+    Truncate,
+    /// Answer the request with an error response
+    Reject,
+}
+
+/// The folder of the match files shared with the game controller
+pub fn default_match_directory() -> String {
+    MatchLayout::default()
+        .match_folder
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Size of the asset cache before the least recently used files are evicted
+pub fn default_asset_cache_max_bytes() -> u64 {
+    10 * 1024 * 1024 * 1024
+}
+
+/// Seconds the bots have to join the game before the match fails to start
+pub fn default_join_timeout_secs() -> u64 {
+    60
+}
+
+/// The requests a bot can send after joining the game, by their field name in `sc2api.Request`
+pub fn default_allowed_requests() -> Vec<String> {
+    [
+        "leave_game",
+        "quit",
+        "game_info",
+        "observation",
+        "action",
+        "obs_action",
+        "step",
+        "data",
+        "query",
+        "save_replay",
+        "available_maps",
+        "ping",
+        "debug",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+/// The debug commands a bot can send when debug is enabled, by their field name in `sc2api.DebugCommand`
+pub fn default_allowed_debug_commands() -> Vec<String> {
+    [
+        "draw",
+        "game_state",
+        "create_unit",
+        "kill_unit",
+        "test_process",
+        "score",
+        "end_game",
+        "unit_value",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::configuration::ac_config::{ACConfig, RunType};
+    use crate::configuration::ac_config::{
+        default_allowed_debug_commands, default_allowed_requests, default_asset_cache_max_bytes,
+        default_match_directory, ACConfig, LogTruncation, RateLimitMode, RealTimeLimitResult,
+        RunType,
+    };
 
     fn ac_config() -> ACConfig {
         ACConfig {
             api_token: Some("123".to_string()),
             arena_client_id: "123".to_string(),
+            asset_cache_directory: "".to_string(),
+            asset_cache_max_bytes: default_asset_cache_max_bytes(),
             base_website_url: "123".to_string(),
             bot_directory: "123".to_string(),
             bot_cont_1_host: "123".to_string(),
             bot_cont_1_port: 0,
             bot_cont_2_host: "123".to_string(),
             bot_cont_2_port: 0,
+            data_quota_bytes: 0,
             debug_mode: false,
             disable_debug: false,
             game_directory: "123".to_string(),
             hash_check: false,
             logging_level: "123".to_string(),
             log_root: "123".to_string(),
+            log_file_max_bytes: 0,
+            log_truncation: LogTruncation::HeadAndTail,
+            match_directory: default_match_directory(),
+            match_id_offset: 0,
             matches_file: "123".to_string(),
             max_frame_time: 0,
+            enforce_frame_time: false,
+            frame_time_bank: 0,
+            max_frame_time_strikes: 0,
+            hard_frame_time: 0,
             max_game_time: 0,
             max_real_time: 0,
+            real_time_limit_result: RealTimeLimitResult::Tie,
             timeout_secs: 0,
+            upload_max_bytes: 0,
+            join_timeout_secs: 0,
+            max_actions_per_step: 0,
+            max_queries_per_step: 0,
+            max_apm: 0,
+            rate_limit_mode: RateLimitMode::Tag,
+            allowed_requests: default_allowed_requests(),
+            allowed_debug_commands: default_allowed_debug_commands(),
             python: "123".to_string(),
             realtime: false,
+            record_protocol: false,
+            protocol_record_max_bytes: 0,
+            protocol_record_observation_interval: 0,
+            restart_sc2: false,
             results_file: "123".to_string(),
             run_type: RunType::Prepare,
             sc2_cont_host: "123".to_string(),
             sc2_cont_port: 0,
+            submission_outbox_directory: "".to_string(),
             validate_race: false,
             visualize: false,
             aws: false,
             caching_server_url: "".to_string(),
             keep_alive: false,
+            event_port: 0,
         }
     }
 
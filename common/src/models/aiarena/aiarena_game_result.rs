@@ -1,4 +1,6 @@
 use crate::models::aiarena::aiarena_result::AiArenaResult;
+use crate::paths::MatchLayout;
+use crate::PlayerNum;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -13,15 +15,29 @@ pub struct AiArenaGameResult {
     pub bot2_avg_step_time: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bot2_tags: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot1_step_time_stats: Option<StepTimeStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot2_step_time_stats: Option<StepTimeStats>,
     #[serde(rename = "type")]
     pub result: AiArenaResult,
     pub game_steps: u32,
 }
 
+/// Distribution of the time a bot took for its game steps, in seconds
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct StepTimeStats {
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+    pub max: f32,
+    /// Number of steps that took longer than the frame time budget
+    pub steps_over_budget: u32,
+}
+
 use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
-use std::path::Path;
 
 impl AiArenaGameResult {
     // Create an instance of InitializationError
@@ -32,26 +48,36 @@ impl AiArenaGameResult {
             bot1_tags: None,
             bot2_avg_step_time: None,
             bot2_tags: None,
+            bot1_step_time_stats: None,
+            bot2_step_time_stats: None,
             result: AiArenaResult::InitializationError,
             game_steps: 0,
         }
     }
 
+    // Create an instance of Error
+    pub fn new_error(match_id: u32) -> Self {
+        AiArenaGameResult {
+            result: AiArenaResult::Error,
+            ..Self::new_initialization_error(match_id)
+        }
+    }
+
     // Reads AiArenaGameResult from disk.
-    pub fn from_json_file() -> Result<Self, Box<dyn Error>> {
-        let file = File::open("/match/match_result.json")?;
+    pub fn from_json_file(layout: &MatchLayout) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(layout.match_result_file())?;
         let reader = BufReader::new(file);
         let result = serde_json::from_reader(reader)?;
         Ok(result)
     }
 
     // Writes the AiArenaGameResult instance to disk.
-    pub fn to_json_file(&self) -> Result<(), Box<dyn Error>> {
-        let path = Path::new("/match/match_result.json");
+    pub fn to_json_file(&self, layout: &MatchLayout) -> Result<(), Box<dyn Error>> {
+        let path = layout.match_result_file();
 
         // If a valid match result is already stored, keep it
         if path.exists() {
-            let record = Self::from_json_file()?;
+            let record = Self::from_json_file(layout)?;
             println!("Match result already stored: {:?}", record);
             println!("Ignoring new match result: {:?}", self);
             return Ok(());
@@ -63,9 +89,20 @@ impl AiArenaGameResult {
         Ok(())
     }
 
+    // Adds a tag to the result of the player, once.
+    pub fn add_tag(&mut self, player_num: PlayerNum, tag: &str) {
+        let tags = match player_num {
+            PlayerNum::One => self.bot1_tags.get_or_insert_with(Vec::new),
+            PlayerNum::Two => self.bot2_tags.get_or_insert_with(Vec::new),
+        };
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+
     // Deletes the match result file from disk.
-    pub fn delete_json_file() -> Result<(), Box<dyn Error>> {
-        let path = Path::new("/match/match_result.json");
+    pub fn delete_json_file(layout: &MatchLayout) -> Result<(), Box<dyn Error>> {
+        let path = layout.match_result_file();
         if path.exists() {
             std::fs::remove_file(path)?;
         }
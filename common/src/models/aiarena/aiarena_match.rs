@@ -1,6 +1,12 @@
+use crate::configuration::ac_config::{
+    default_allowed_debug_commands, default_allowed_requests, ACConfig, RateLimitMode,
+    RealTimeLimitResult,
+};
 use crate::models::aiarena::aiarena_bot::AiArenaBot;
 use crate::models::aiarena::aiarena_map::AiArenaMap;
 use crate::models::aiarena::bot_race::BotRace;
+use crate::models::aiarena::builtin_ai::BuiltinAi;
+use crate::paths::MatchLayout;
 use crate::PlayerNum;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -13,6 +19,34 @@ pub struct AiArenaMatch {
     pub map: AiArenaMap,
     #[serde(default)]
     pub game_base: Option<String>,
+    #[serde(default)]
+    pub settings: MatchSettings,
+}
+
+/// Game limits that can be overridden for a single match.
+/// Limits that are not set fall back to the values in the arena client configuration.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MatchSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_game_time: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_frame_time: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_real_time: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_debug: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validate_race: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub realtime: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_protocol: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_requests: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_debug_commands: Option<Vec<String>>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -22,6 +56,8 @@ pub struct MatchPlayer {
     pub race: BotRace,
     pub bot_type: String,
     pub bot_base: String,
+    /// Set if the SC2 built-in AI plays in place of a bot
+    pub builtin_ai: Option<BuiltinAi>,
 }
 
 impl MatchPlayer {
@@ -33,6 +69,7 @@ impl MatchPlayer {
                 race: BotRace::from_str(&ai_match.bot1.plays_race),
                 bot_type: ai_match.bot1._type.clone(),
                 bot_base: ai_match.bot1.bot_base.clone().unwrap_or_default(),
+                builtin_ai: None,
             },
             PlayerNum::Two => Self {
                 id: ai_match.bot2.game_display_id.clone(),
@@ -40,11 +77,32 @@ impl MatchPlayer {
                 race: BotRace::from_str(&ai_match.bot2.plays_race),
                 bot_type: ai_match.bot2._type.clone(),
                 bot_base: ai_match.bot2.bot_base.clone().unwrap_or_default(),
+                builtin_ai: None,
             },
         }
     }
 
+    pub fn from_builtin_ai(builtin_ai: BuiltinAi) -> Self {
+        Self {
+            id: builtin_ai.id(),
+            name: builtin_ai.name(),
+            race: builtin_ai.race,
+            bot_type: "builtin".to_string(),
+            bot_base: String::new(),
+            builtin_ai: Some(builtin_ai),
+        }
+    }
+
+    /// Creates the player from its slot in a matches file.
+    /// The slot is either the four fields of a bot or a single built-in AI field.
     pub fn from_file_source(bot_line: &[String]) -> Result<Self, SerializationError> {
+        if let [builtin_ai] = bot_line {
+            return builtin_ai
+                .parse::<BuiltinAi>()
+                .map(Self::from_builtin_ai)
+                .map_err(|_| SerializationError::ParsingError);
+        }
+
         let raw_bot_type = bot_line
             .get(3)
             .ok_or_else(|| SerializationError::ParsingError)?;
@@ -74,8 +132,13 @@ impl MatchPlayer {
             ),
             bot_type,
             bot_base,
+            builtin_ai: None,
         })
     }
+
+    pub const fn is_builtin_ai(&self) -> bool {
+        self.builtin_ai.is_some()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -83,6 +146,7 @@ pub struct Match {
     pub match_id: u32,
     pub players: HashMap<PlayerNum, MatchPlayer>,
     pub map_name: String,
+    pub settings: MatchSettings,
     pub aiarena_match: Option<AiArenaMatch>,
 }
 
@@ -103,6 +167,7 @@ impl From<AiArenaMatch> for Match {
             match_id: ai_match.id,
             players,
             map_name: ai_match.map.name.clone(),
+            settings: ai_match.settings.clone(),
             aiarena_match: Some(ai_match),
         }
     }
@@ -137,10 +202,89 @@ pub struct MatchRequest {
     pub map_name: String,
     pub player_1_race: u8,
     pub player_2_race: u8,
+
+    // Game limits. The defaults apply to match requests written by older match controllers.
+    #[serde(default = "default_max_game_time")]
+    pub max_game_time: u32,
+    #[serde(default = "default_max_frame_time")]
+    pub max_frame_time: i32,
+    #[serde(default)]
+    pub enforce_frame_time: bool,
+    #[serde(default)]
+    pub frame_time_bank: u64,
+    #[serde(default)]
+    pub max_frame_time_strikes: u32,
+    #[serde(default)]
+    pub hard_frame_time: u64,
+    #[serde(default = "default_max_real_time")]
+    pub max_real_time: i64,
+    #[serde(default)]
+    pub real_time_limit_result: RealTimeLimitResult,
+    // Rate limits of the requests of the bots. Zero disables a limit.
+    #[serde(default)]
+    pub max_actions_per_step: u32,
+    #[serde(default)]
+    pub max_queries_per_step: u32,
+    #[serde(default)]
+    pub max_apm: u32,
+    #[serde(default)]
+    pub rate_limit_mode: RateLimitMode,
+    // Requests a bot can send after joining the game
+    #[serde(default = "default_allowed_requests")]
+    pub allowed_requests: Vec<String>,
+    #[serde(default = "default_allowed_debug_commands")]
+    pub allowed_debug_commands: Vec<String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_true")]
+    pub disable_debug: bool,
+    #[serde(default = "default_true")]
+    pub validate_race: bool,
+    #[serde(default)]
+    pub realtime: bool,
+
+    // Recording of the requests and responses of the bots
+    #[serde(default)]
+    pub record_protocol: bool,
+    #[serde(default)]
+    pub protocol_record_max_bytes: u64,
+    #[serde(default)]
+    pub protocol_record_observation_interval: u32,
+
+    // Restart of a crashed SC2 process before the bots joined
+    #[serde(default)]
+    pub restart_sc2: bool,
+
+    // Player 2 is the SC2 built-in AI instead of a bot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_2_builtin_ai: Option<BuiltinAi>,
 }
 
-impl From<Match> for MatchRequest {
-    fn from(a_match: Match) -> Self {
+const fn default_max_game_time() -> u32 {
+    80640
+}
+
+const fn default_max_frame_time() -> i32 {
+    40
+}
+
+const fn default_max_real_time() -> i64 {
+    7200
+}
+
+const fn default_timeout_secs() -> u64 {
+    30
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+impl MatchRequest {
+    /// Creates the match request for the given match.
+    /// Game limits are taken from the match settings, falling back to the arena client configuration.
+    pub fn new(a_match: &Match, settings: &ACConfig) -> Self {
+        let overrides = &a_match.settings;
         Self {
             match_id: a_match.match_id,
             player_1_id: a_match.players[&PlayerNum::One].id.clone(),
@@ -150,16 +294,64 @@ impl From<Match> for MatchRequest {
             map_name: a_match.map_name.clone(),
             player_1_race: a_match.players[&PlayerNum::One].race as u8,
             player_2_race: a_match.players[&PlayerNum::Two].race as u8,
+            max_game_time: overrides.max_game_time.unwrap_or(settings.max_game_time),
+            max_frame_time: overrides.max_frame_time.unwrap_or(settings.max_frame_time),
+            enforce_frame_time: settings.enforce_frame_time,
+            frame_time_bank: settings.frame_time_bank,
+            max_frame_time_strikes: settings.max_frame_time_strikes,
+            hard_frame_time: settings.hard_frame_time,
+            max_actions_per_step: settings.max_actions_per_step,
+            max_queries_per_step: settings.max_queries_per_step,
+            max_apm: settings.max_apm,
+            rate_limit_mode: settings.rate_limit_mode,
+            allowed_requests: overrides
+                .allowed_requests
+                .clone()
+                .unwrap_or_else(|| settings.allowed_requests.clone()),
+            allowed_debug_commands: overrides
+                .allowed_debug_commands
+                .clone()
+                .unwrap_or_else(|| settings.allowed_debug_commands.clone()),
+            max_real_time: overrides.max_real_time.unwrap_or(settings.max_real_time),
+            real_time_limit_result: settings.real_time_limit_result,
+            timeout_secs: overrides.timeout_secs.unwrap_or(settings.timeout_secs),
+            disable_debug: overrides.disable_debug.unwrap_or(settings.disable_debug),
+            validate_race: overrides.validate_race.unwrap_or(settings.validate_race),
+            realtime: overrides.realtime.unwrap_or(settings.realtime),
+            record_protocol: overrides
+                .record_protocol
+                .unwrap_or(settings.record_protocol),
+            protocol_record_max_bytes: settings.protocol_record_max_bytes,
+            protocol_record_observation_interval: settings.protocol_record_observation_interval,
+            restart_sc2: settings.restart_sc2,
+            player_2_builtin_ai: a_match.players[&PlayerNum::Two].builtin_ai,
         }
     }
-}
 
-impl MatchRequest {
-    pub fn read() -> Self {
+    /// File name of the protocol recording of a player in the game controller logs
+    pub fn protocol_recording_file(player_num: PlayerNum) -> String {
+        match player_num {
+            PlayerNum::One => "protocol-player1.sc2proto".to_string(),
+            PlayerNum::Two => "protocol-player2.sc2proto".to_string(),
+        }
+    }
+
+    /// Reads the match request, if the match controller has written it already.
+    pub fn try_read(layout: &MatchLayout) -> Option<Self> {
+        if !layout.match_request_file().exists() {
+            return None;
+        }
+        Some(Self::read(layout))
+    }
+
+    pub fn read(layout: &MatchLayout) -> Self {
         config::Config::builder()
             .add_source(
-                config::File::new("/match/match-request.toml", config::FileFormat::Toml)
-                    .required(false),
+                config::File::new(
+                    &layout.match_request_file().to_string_lossy(),
+                    config::FileFormat::Toml,
+                )
+                .required(false),
             )
             .add_source(config::Environment::default())
             .build()
@@ -168,7 +360,7 @@ impl MatchRequest {
             .expect("Could not parse match request data")
     }
 
-    pub fn write(&self) -> Result<(), std::io::Error> {
+    pub fn write(&self, layout: &MatchLayout) -> Result<(), std::io::Error> {
         let toml_str = toml::to_string(self).map_err(|_| {
             std::io::Error::new(
                 std::io::ErrorKind::Other,
@@ -177,8 +369,8 @@ impl MatchRequest {
         })?;
         tracing::debug!("Writing match request to file: {}", toml_str);
 
-        std::fs::create_dir_all("/match")?;
-        std::fs::write("/match/match-request.toml", toml_str)
+        std::fs::create_dir_all(&layout.match_folder)?;
+        std::fs::write(layout.match_request_file(), toml_str)
     }
 }
 
@@ -194,17 +386,20 @@ pub struct PlayerInfo {
 
 impl PlayerInfo {
     /// Reads player information for the player with the given client port.
-    pub fn read(port: u16) -> Option<Self> {
-        let file_path = format!("/match/player-{}.toml", port);
+    pub fn read(layout: &MatchLayout, port: u16) -> Option<Self> {
+        let file_path = layout.player_info_file(port);
 
         // If file does not exist, return None
-        if !std::path::Path::new(&file_path).exists() {
+        if !file_path.exists() {
             return None;
         }
 
         Some(
             config::Config::builder()
-                .add_source(config::File::new(&file_path, config::FileFormat::Toml).required(false))
+                .add_source(
+                    config::File::new(&file_path.to_string_lossy(), config::FileFormat::Toml)
+                        .required(false),
+                )
                 .add_source(config::Environment::default())
                 .build()
                 .expect("Could not read player information")
@@ -214,21 +409,75 @@ impl PlayerInfo {
     }
 
     /// Writes player information for the player with the given port.
-    pub fn write(&self, port: u16) -> Result<(), std::io::Error> {
-        let dir_path = "/match";
+    pub fn write(&self, layout: &MatchLayout, port: u16) -> Result<(), std::io::Error> {
         let toml_str = toml::to_string(self).map_err(|_| {
             std::io::Error::new(
                 std::io::ErrorKind::Other,
                 "Could not serialize player information",
             )
         })?;
+        let file_path = layout.player_info_file(port);
         tracing::debug!(
             "Writing player information to {}:\n{}",
-            format!("{}/player-{}.toml", dir_path, port),
+            file_path.display(),
             &toml_str
         );
 
-        std::fs::create_dir_all(dir_path)?;
-        std::fs::write(format!("{}/player-{}.toml", dir_path, port), toml_str)
+        std::fs::create_dir_all(&layout.match_folder)?;
+        std::fs::write(file_path, toml_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_request_without_limits_uses_defaults() {
+        let toml_str = r#"
+            match_id = 1
+            player_1_id = "1"
+            player_1_name = "basic_bot"
+            player_2_id = "2"
+            player_2_name = "loser_bot"
+            map_name = "AutomatonLE.SC2Map"
+            player_1_race = 1
+            player_2_race = 3
+        "#;
+        let match_request: MatchRequest =
+            toml::from_str(toml_str).expect("Could not parse match request");
+        assert_eq!(match_request.max_game_time, 80640);
+        assert_eq!(match_request.max_frame_time, 40);
+        assert!(!match_request.enforce_frame_time);
+        assert_eq!(match_request.max_real_time, 7200);
+        assert_eq!(
+            match_request.real_time_limit_result,
+            RealTimeLimitResult::Tie
+        );
+        assert_eq!(match_request.max_actions_per_step, 0);
+        assert_eq!(match_request.rate_limit_mode, RateLimitMode::Tag);
+        assert_eq!(match_request.allowed_requests, default_allowed_requests());
+        assert_eq!(match_request.timeout_secs, 30);
+        assert!(match_request.disable_debug);
+        assert!(match_request.validate_race);
+        assert!(!match_request.realtime);
+        assert!(match_request.player_2_builtin_ai.is_none());
+    }
+
+    #[test]
+    fn test_builtin_ai_player_from_file_source() {
+        let player = MatchPlayer::from_file_source(&["builtin:VeryHard:Zerg:Macro".to_string()])
+            .expect("Could not parse built-in AI");
+        assert!(player.is_builtin_ai());
+        assert_eq!(player.race, BotRace::Zerg);
+        assert!(MatchPlayer::from_file_source(&["builtin:Unknown".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_match_settings_are_optional() {
+        let settings: MatchSettings =
+            serde_json::from_str(r#"{"max_game_time": 100}"#).expect("Could not parse settings");
+        assert_eq!(settings.max_game_time, Some(100));
+        assert_eq!(settings.timeout_secs, None);
     }
 }
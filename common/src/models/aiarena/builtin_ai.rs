@@ -0,0 +1,144 @@
+use crate::models::aiarena::bot_race::BotRace;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Prefix of a player slot that plays against the SC2 built-in AI
+pub const BUILTIN_AI_PREFIX: &str = "builtin:";
+
+/// The SC2 built-in computer AI as an opponent, e.g. `builtin:VeryHard:Zerg:Macro`
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BuiltinAi {
+    pub difficulty: AiDifficulty,
+    pub race: BotRace,
+    pub build: AiBuild,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum AiDifficulty {
+    VeryEasy,
+    Easy,
+    Medium,
+    MediumHard,
+    Hard,
+    Harder,
+    VeryHard,
+    CheatVision,
+    CheatMoney,
+    CheatInsane,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum AiBuild {
+    RandomBuild,
+    Rush,
+    Timing,
+    Power,
+    Macro,
+    Air,
+}
+
+impl FromStr for BuiltinAi {
+    type Err = ();
+
+    /// Parses `builtin:<difficulty>:<race>[:<build>]`. The build defaults to a random build.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut parts = input.strip_prefix(BUILTIN_AI_PREFIX).ok_or(())?.split(':');
+        let difficulty = parts.next().ok_or(())?.parse()?;
+        let race = match BotRace::from_str(parts.next().ok_or(())?) {
+            BotRace::NoRace => return Err(()),
+            race => race,
+        };
+        let build = match parts.next() {
+            Some(build) => build.parse()?,
+            None => AiBuild::RandomBuild,
+        };
+        if parts.next().is_some() {
+            return Err(());
+        }
+        Ok(Self {
+            difficulty,
+            race,
+            build,
+        })
+    }
+}
+
+impl BuiltinAi {
+    pub fn id(&self) -> String {
+        format!(
+            "{}{:?}:{:?}:{:?}",
+            BUILTIN_AI_PREFIX, self.difficulty, self.race, self.build
+        )
+    }
+
+    pub fn name(&self) -> String {
+        format!("{:?}_{:?}_{:?}", self.difficulty, self.race, self.build)
+    }
+}
+
+impl FromStr for AiDifficulty {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match &input.to_lowercase()[..] {
+            "veryeasy" => Ok(Self::VeryEasy),
+            "easy" => Ok(Self::Easy),
+            "medium" => Ok(Self::Medium),
+            "mediumhard" => Ok(Self::MediumHard),
+            "hard" => Ok(Self::Hard),
+            "harder" => Ok(Self::Harder),
+            "veryhard" => Ok(Self::VeryHard),
+            "cheatvision" => Ok(Self::CheatVision),
+            "cheatmoney" => Ok(Self::CheatMoney),
+            "cheatinsane" => Ok(Self::CheatInsane),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromStr for AiBuild {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match &input.to_lowercase()[..] {
+            "random" | "randombuild" => Ok(Self::RandomBuild),
+            "rush" => Ok(Self::Rush),
+            "timing" => Ok(Self::Timing),
+            "power" => Ok(Self::Power),
+            "macro" => Ok(Self::Macro),
+            "air" => Ok(Self::Air),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let ai = "builtin:VeryHard:Zerg:Macro".parse::<BuiltinAi>().unwrap();
+        assert_eq!(ai.difficulty, AiDifficulty::VeryHard);
+        assert_eq!(ai.race, BotRace::Zerg);
+        assert_eq!(ai.build, AiBuild::Macro);
+        assert_eq!(ai.id(), "builtin:VeryHard:Zerg:Macro");
+    }
+
+    #[test]
+    fn test_from_str_default_build() {
+        let ai = "builtin:easy:T".parse::<BuiltinAi>().unwrap();
+        assert_eq!(ai.race, BotRace::Terran);
+        assert_eq!(ai.build, AiBuild::RandomBuild);
+    }
+
+    #[test]
+    fn test_from_str_invalid() {
+        assert!("VeryHard:Zerg:Macro".parse::<BuiltinAi>().is_err());
+        assert!("builtin:Impossible:Zerg".parse::<BuiltinAi>().is_err());
+        assert!("builtin:VeryHard:Elf".parse::<BuiltinAi>().is_err());
+        assert!("builtin:VeryHard:Zerg:Macro:Extra"
+            .parse::<BuiltinAi>()
+            .is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::models::aiarena::aiarena_game_result::AiArenaGameResult;
+use crate::models::aiarena::aiarena_match::{Match, MatchPlayer};
+use crate::models::aiarena::bot_race::BotRace;
+use crate::PlayerNum;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The results file of local runs of the match controller
+#[derive(Deserialize, Serialize, Default, Debug)]
+pub struct LocalResults {
+    pub results: Vec<LocalGameResult>,
+}
+
+/// A result in the results file, with the players and the map of its match.
+/// Results written before the players were recorded only have the game result.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LocalGameResult {
+    #[serde(flatten)]
+    pub game_result: AiArenaGameResult,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot1: Option<ResultPlayer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot2: Option<ResultPlayer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub map: Option<String>,
+    /// Seconds since the Unix epoch when the result was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ResultPlayer {
+    pub id: String,
+    pub name: String,
+    pub race: BotRace,
+}
+
+impl From<&MatchPlayer> for ResultPlayer {
+    fn from(player: &MatchPlayer) -> Self {
+        Self {
+            id: player.id.clone(),
+            name: player.name.clone(),
+            race: player.race,
+        }
+    }
+}
+
+impl LocalGameResult {
+    /// Records the result of the match, if known, at the current time
+    pub fn new(game_result: &AiArenaGameResult, ai_match: Option<&Match>) -> Self {
+        let player = |player_num| {
+            ai_match
+                .and_then(|m| m.players.get(&player_num))
+                .map(ResultPlayer::from)
+        };
+        Self {
+            game_result: game_result.clone(),
+            bot1: player(PlayerNum::One),
+            bot2: player(PlayerNum::Two),
+            map: ai_match.map(|m| m.map_name.clone()),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::aiarena::aiarena_result::AiArenaResult;
+
+    #[test]
+    fn test_reads_results_without_players() {
+        let results: LocalResults = serde_json::from_str(
+            r#"{"results": [{"match": 3, "type": "Player2Win", "game_steps": 1200}]}"#,
+        )
+        .unwrap();
+        let result = &results.results[0];
+        assert_eq!(result.game_result.match_id, 3);
+        assert_eq!(result.game_result.result, AiArenaResult::Player2Win);
+        assert_eq!(result.bot1, None);
+        assert_eq!(result.map, None);
+    }
+
+    #[test]
+    fn test_writes_players_next_to_the_game_result() {
+        let result = LocalGameResult {
+            game_result: AiArenaGameResult::new_error(5),
+            bot1: Some(ResultPlayer {
+                id: "1".to_string(),
+                name: "basic_bot".to_string(),
+                race: BotRace::Terran,
+            }),
+            bot2: None,
+            map: Some("AutomatonLE".to_string()),
+            timestamp: Some(1_700_000_000),
+        };
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["match"], 5);
+        assert_eq!(json["type"], "Error");
+        assert_eq!(json["bot1"]["name"], "basic_bot");
+        assert_eq!(json["bot1"]["race"], "Terran");
+        assert_eq!(json["map"], "AutomatonLE");
+        assert!(json.get("bot2").is_none());
+    }
+}
@@ -4,3 +4,5 @@ pub mod aiarena_map;
 pub mod aiarena_match;
 pub mod aiarena_result;
 pub mod bot_race;
+pub mod builtin_ai;
+pub mod local_game_result;
@@ -1 +1,107 @@
+use crate::models::aiarena::aiarena_game_result::AiArenaGameResult;
+use crate::paths::MatchLayout;
+use crate::PlayerNum;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
 
+/// Progress of the match, reported by the game and bot controllers to the match controller
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "event")]
+pub enum ControllerEvent {
+    PlayerJoined {
+        player_num: PlayerNum,
+    },
+    GameStarted,
+    GameEnded {
+        result: AiArenaGameResult,
+    },
+    BotExited {
+        player_num: PlayerNum,
+        exit_code: i32,
+    },
+}
+
+/// The players that joined the game, published by the game controller
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct JoinState {
+    pub match_id: u32,
+    pub joined_players: Vec<PlayerNum>,
+}
+
+impl JoinState {
+    pub fn new(match_id: u32) -> Self {
+        Self {
+            match_id,
+            joined_players: Vec::new(),
+        }
+    }
+
+    pub fn has_joined(&self, player_num: PlayerNum) -> bool {
+        self.joined_players.contains(&player_num)
+    }
+
+    pub fn join(&mut self, player_num: PlayerNum) {
+        if !self.has_joined(player_num) {
+            self.joined_players.push(player_num);
+        }
+    }
+
+    // Reads the JoinState from disk.
+    pub fn from_json_file(layout: &MatchLayout) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(layout.join_state_file())?;
+        let reader = BufReader::new(file);
+        let join_state = serde_json::from_reader(reader)?;
+        Ok(join_state)
+    }
+
+    // Writes the JoinState to disk, replacing the previous state at once.
+    pub fn to_json_file(&self, layout: &MatchLayout) -> Result<(), Box<dyn Error>> {
+        let path = layout.join_state_file();
+        let temp_path = path.with_extension("json.tmp");
+        let file = File::create(&temp_path)?;
+        serde_json::to_writer_pretty(file, &self)?;
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    // Deletes the JoinState file from disk.
+    pub fn delete_json_file(layout: &MatchLayout) -> Result<(), Box<dyn Error>> {
+        let path = layout.join_state_file();
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bot_exited_format() {
+        // The wire format of the exit reported by the bot controllers
+        let event: ControllerEvent =
+            serde_json::from_str(r#"{"event": "BotExited", "player_num": "Two", "exit_code": 1}"#)
+                .expect("Could not parse event");
+        assert!(matches!(
+            event,
+            ControllerEvent::BotExited {
+                player_num: PlayerNum::Two,
+                exit_code: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn test_join_state() {
+        let mut join_state = JoinState::new(1);
+        join_state.join(PlayerNum::Two);
+        join_state.join(PlayerNum::Two);
+        assert_eq!(join_state.joined_players, vec![PlayerNum::Two]);
+        assert!(join_state.has_joined(PlayerNum::Two));
+        assert!(!join_state.has_joined(PlayerNum::One));
+    }
+}
@@ -1 +1,36 @@
+use crate::portpicker::Port;
+use crate::PlayerNum;
+use serde::{Deserialize, Serialize};
 
+/// Status of the game controller, served at `/status` of its control port
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ControllerStatus {
+    pub match_id: Option<u32>,
+    pub seats: Vec<SeatStatus>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SeatStatus {
+    /// The player of the seat, none for the observer seat
+    pub player_num: Option<PlayerNum>,
+    pub port: Port,
+    pub state: SeatState,
+    /// PID of the SC2 process of the seat
+    pub pid: Option<u32>,
+    /// The last game loop observed by the player
+    pub game_loop: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeatState {
+    /// Waiting for the bot to connect
+    Open,
+    /// Not used, e.g. the seat of player 2 when playing against the built-in AI
+    Unused,
+    /// The bot connected and is joining the game
+    Connected,
+    /// The bot joined the game
+    Joined,
+    /// The bot left the game, or the game ended
+    Finished,
+}
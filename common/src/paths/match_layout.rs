@@ -0,0 +1,149 @@
+//! The folders of a match, shared by the controllers.
+//!
+//! The containers mount them at fixed paths. Runs without containers, like the native controller
+//! or tests, set other folders with environment variables or the configuration of a controller.
+
+use crate::PlayerNum;
+use serde::{Deserialize, Serialize};
+use std::env::var_os;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const MATCH_FOLDER: &str = "MATCH_FOLDER";
+pub const LOG_FOLDER: &str = "LOG_FOLDER";
+pub const BOT_FOLDER: &str = "BOT_FOLDER";
+pub const REPLAY_FOLDER: &str = "REPLAY_FOLDER";
+
+static MATCH_LAYOUT: OnceLock<MatchLayout> = OnceLock::new();
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MatchLayout {
+    /// The match request, player information, result and join state
+    pub match_folder: PathBuf,
+    /// The logs of the controller
+    pub log_folder: PathBuf,
+    /// The bot of a bot controller
+    pub bot_folder: PathBuf,
+    /// The replays of the game controller
+    pub replay_folder: PathBuf,
+}
+
+impl Default for MatchLayout {
+    /// The paths mounted in the containers
+    fn default() -> Self {
+        Self {
+            match_folder: PathBuf::from("/match"),
+            log_folder: PathBuf::from("/logs"),
+            bot_folder: PathBuf::from("/bot"),
+            replay_folder: PathBuf::from("/root/StarCraftII/maps"),
+        }
+    }
+}
+
+impl MatchLayout {
+    /// The default layout with the folders set in the environment
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let folder = |name: &str, default: PathBuf| var_os(name).map_or(default, PathBuf::from);
+        Self {
+            match_folder: folder(MATCH_FOLDER, default.match_folder),
+            log_folder: folder(LOG_FOLDER, default.log_folder),
+            bot_folder: folder(BOT_FOLDER, default.bot_folder),
+            replay_folder: folder(REPLAY_FOLDER, default.replay_folder),
+        }
+    }
+
+    /// The environment variables that give this layout to a controller
+    pub fn envs(&self) -> [(&'static str, &Path); 4] {
+        [
+            (MATCH_FOLDER, self.match_folder.as_path()),
+            (LOG_FOLDER, self.log_folder.as_path()),
+            (BOT_FOLDER, self.bot_folder.as_path()),
+            (REPLAY_FOLDER, self.replay_folder.as_path()),
+        ]
+    }
+
+    pub fn match_request_file(&self) -> PathBuf {
+        self.match_folder.join("match-request.toml")
+    }
+
+    pub fn match_result_file(&self) -> PathBuf {
+        self.match_folder.join("match_result.json")
+    }
+
+    pub fn join_state_file(&self) -> PathBuf {
+        self.match_folder.join("join_state.json")
+    }
+
+    /// Player information for the player with the given client port
+    pub fn player_info_file(&self, port: u16) -> PathBuf {
+        self.match_folder.join(format!("player-{}.toml", port))
+    }
+
+    pub fn log_file(&self, file_name: &str) -> PathBuf {
+        self.log_folder.join(file_name)
+    }
+
+    /// The exit code of the bot, written by the bot controller when the bot exits
+    pub fn signal_exit_file(&self) -> PathBuf {
+        self.log_file("signal.exit")
+    }
+
+    pub fn bot_logs_folder(&self) -> PathBuf {
+        self.bot_folder.join("logs")
+    }
+
+    /// The layout of the bot controller of the player, with its logs in a folder of these logs
+    pub fn bot_controller_layout(&self, player_num: PlayerNum) -> MatchLayout {
+        let folder = match player_num {
+            PlayerNum::One => "bot-controller-1",
+            PlayerNum::Two => "bot-controller-2",
+        };
+        MatchLayout {
+            log_folder: self.log_folder.join(folder),
+            ..self.clone()
+        }
+    }
+}
+
+/// The layout of this process, resolved from the environment once
+pub fn match_layout() -> &'static MatchLayout {
+    MATCH_LAYOUT.get_or_init(MatchLayout::from_env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_layout_files() {
+        let layout = MatchLayout {
+            match_folder: PathBuf::from("/tmp/match-1/match"),
+            log_folder: PathBuf::from("/tmp/match-1/logs"),
+            ..MatchLayout::default()
+        };
+        assert_eq!(
+            layout.match_result_file(),
+            Path::new("/tmp/match-1/match/match_result.json")
+        );
+        assert_eq!(
+            layout.player_info_file(10001),
+            Path::new("/tmp/match-1/match/player-10001.toml")
+        );
+        assert_eq!(
+            layout.signal_exit_file(),
+            Path::new("/tmp/match-1/logs/signal.exit")
+        );
+        assert_eq!(layout.bot_logs_folder(), Path::new("/bot/logs"));
+        assert_eq!(
+            layout
+                .bot_controller_layout(PlayerNum::Two)
+                .signal_exit_file(),
+            Path::new("/tmp/match-1/logs/bot-controller-2/signal.exit")
+        );
+        assert_eq!(
+            layout.envs()[0],
+            (MATCH_FOLDER, layout.match_folder.as_path())
+        );
+    }
+}
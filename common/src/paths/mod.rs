@@ -1,3 +1,7 @@
+mod match_layout;
+
+pub use match_layout::{match_layout, MatchLayout};
+
 use std::env::var_os;
 use std::io::prelude::*;
 use std::io::ErrorKind;
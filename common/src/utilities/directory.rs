@@ -12,3 +12,19 @@ pub async fn ensure_directory_structure(base: &str, folder: &str) -> std::io::Re
     }
     Ok(())
 }
+
+/// Total size of the files in the directory and its subdirectories, without following symlinks
+pub fn directory_size(directory: &std::path::Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        // The metadata of a directory entry does not follow symlinks
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            size += directory_size(&entry.path())?;
+        } else if metadata.is_file() {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
@@ -1,3 +1,4 @@
 pub mod directory;
+pub mod files;
 pub mod portpicker;
 pub mod zip_utils;
@@ -1,10 +1,22 @@
-use anyhow::anyhow;
+use crate::configuration::ac_config::LogTruncation;
+use anyhow::{anyhow, bail, Context};
 use bytes::Bytes;
-use std::io::Write;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 use std::path::PathBuf;
-use std::process::Command;
 use tracing::{debug, trace};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Uncompressed bytes an archive may extract to by default
+pub const DEFAULT_MAX_UNCOMPRESSED_BYTES: u64 = 8 * 1024 * 1024 * 1024;
+/// Files and folders an archive may contain by default
+pub const DEFAULT_MAX_FILES: usize = 100_000;
+
+/// File type bits of a unix mode
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
 
 #[derive(Debug, Clone)]
 pub struct ZipStruct {
@@ -14,6 +26,22 @@ pub struct ZipStruct {
     pub path: PathBuf,
 }
 
+/// Limits of an extraction, against archives that expand to fill the disk
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractLimits {
+    pub max_uncompressed_bytes: u64,
+    pub max_files: usize,
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_uncompressed_bytes: DEFAULT_MAX_UNCOMPRESSED_BYTES,
+            max_files: DEFAULT_MAX_FILES,
+        }
+    }
+}
+
 pub fn zip_directory_to_path(archive_file_path: &Path, directory: &Path) -> anyhow::Result<()> {
     trace!(
         "Zipping directory {:?} to file {:?}",
@@ -23,80 +51,280 @@ pub fn zip_directory_to_path(archive_file_path: &Path, directory: &Path) -> anyh
     zip_directory(archive_file_path, directory)
 }
 
+/// Files of the zipped directory that are left out or shortened
+#[derive(Debug, Default)]
+struct ZipFilter {
+    /// The archive that is written, when it is inside the directory
+    archive: Option<PathBuf>,
+    /// Size over which files are truncated, and the part that is kept
+    truncation: Option<(u64, LogTruncation)>,
+}
+
+/// Zips the contents of the directory, without the directory itself, into the file.
+/// No file is left behind if the directory cannot be zipped.
 pub fn zip_directory(file: &Path, directory: &Path) -> anyhow::Result<()> {
-    let file = file.to_string_lossy().to_string();
-    let dir = directory.join("*").to_string_lossy().to_string();
-    let mut command = Command::new("7z");
-    command.arg("a").arg(file).arg(dir).arg("-y");
-    trace!("Zipping archive with args: {:?}", command.get_args());
-    trace!("{:?}", command);
-    let process = command.output()?;
-    // let mut writer = BufWriter::new(file);
-    // writer.write_all(&process.stdout)?;
+    zip_directory_to_file(file, directory, None)
+}
 
-    if process.status.success() {
-        Ok(())
-    } else {
-        let str = String::from_utf8(process.stderr);
+/// Zips the log files of the directory into the file, truncating the files over `max_file_bytes`
+pub fn zip_log_directory(
+    file: &Path,
+    directory: &Path,
+    max_file_bytes: u64,
+    truncation: LogTruncation,
+) -> anyhow::Result<()> {
+    let truncation = (max_file_bytes > 0).then_some((max_file_bytes, truncation));
+    zip_directory_to_file(file, directory, truncation)
+}
+
+fn zip_directory_to_file(
+    file: &Path,
+    directory: &Path,
+    truncation: Option<(u64, LogTruncation)>,
+) -> anyhow::Result<()> {
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let archive = File::create(file).with_context(|| format!("Could not create {file:?}"))?;
+    let filter = ZipFilter {
+        archive: fs::canonicalize(file).ok(),
+        truncation,
+    };
+    let mut zip = ZipWriter::new(BufWriter::new(archive));
+    let result = add_directory_entries(&mut zip, directory, directory, &filter)
+        .and_then(|_| Ok(zip.finish()?.flush()?));
+    if result.is_err() {
+        let _ = fs::remove_file(file);
+    }
+    result
+}
 
-        trace!("{:?}", str);
-        Err(anyhow!("Error while zipping archive: {:?}", str))
+/// Zips the contents of the directory into the writer, streaming the files one by one.
+/// Symlinks are skipped.
+pub fn zip_directory_to_writer<W: Write + Seek>(writer: W, directory: &Path) -> anyhow::Result<W> {
+    let mut zip = ZipWriter::new(writer);
+    add_directory_entries(&mut zip, directory, directory, &ZipFilter::default())?;
+    Ok(zip.finish()?)
+}
+
+fn add_directory_entries<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    root: &Path,
+    directory: &Path,
+    filter: &ZipFilter,
+) -> anyhow::Result<()> {
+    let mut entries = fs::read_dir(directory)
+        .with_context(|| format!("Could not read directory {directory:?}"))?
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let metadata = fs::symlink_metadata(&path)?;
+        let name = path
+            .strip_prefix(root)?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(metadata.len() >= u32::MAX as u64);
+        let options = match unix_permissions(&metadata) {
+            Some(mode) => options.unix_permissions(mode),
+            None => options,
+        };
+
+        if metadata.file_type().is_symlink() {
+            debug!("Skipping symlink {:?}", path);
+        } else if metadata.is_dir() {
+            zip.add_directory(name, options)?;
+            add_directory_entries(zip, root, &path, filter)?;
+        } else if filter.archive.is_some() && fs::canonicalize(&path).ok() == filter.archive {
+            continue;
+        } else {
+            zip.start_file(name, options)?;
+            let mut file = File::open(&path).with_context(|| format!("Could not open {path:?}"))?;
+            match filter.truncation {
+                Some((max_bytes, truncation)) if metadata.len() > max_bytes => {
+                    debug!("Truncating {:?} to {} bytes", path, max_bytes);
+                    copy_truncated(&mut file, metadata.len(), max_bytes, truncation, zip)?;
+                }
+                _ => {
+                    io::copy(&mut file, zip)?;
+                }
+            }
+        }
     }
+    Ok(())
+}
+
+/// Copies the part of the file that the truncation keeps, with a line in place of the rest
+fn copy_truncated<R: Read + Seek, W: Write>(
+    file: &mut R,
+    len: u64,
+    max_bytes: u64,
+    truncation: LogTruncation,
+    writer: &mut W,
+) -> io::Result<()> {
+    let (head_bytes, tail_bytes) = match truncation {
+        LogTruncation::HeadAndTail => (max_bytes / 2, max_bytes - max_bytes / 2),
+        LogTruncation::Head => (max_bytes, 0),
+        LogTruncation::Tail => (0, max_bytes),
+    };
+    io::copy(&mut file.by_ref().take(head_bytes), writer)?;
+    write!(
+        writer,
+        "\n... {} bytes truncated ...\n",
+        len - head_bytes - tail_bytes
+    )?;
+    file.seek(SeekFrom::Start(len - tail_bytes))?;
+    io::copy(&mut file.by_ref().take(tail_bytes), writer)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn unix_permissions(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn unix_permissions(_metadata: &fs::Metadata) -> Option<u32> {
+    None
 }
 
 /// Extracts a ZIP file from memory to the given directory.
 pub fn zip_extract_from_bytes(archive_file: &Bytes, target_dir: &Path) -> anyhow::Result<()> {
-    let mut tmp_file = tempfile::NamedTempFile::new().unwrap();
-    tmp_file.write_all(archive_file).unwrap();
-
-    let new_file = tmp_file.into_temp_path();
-    let path = new_file.to_string_lossy().to_string();
-
-    let mut command = Command::new("7z");
-
-    command
-        .arg("x")
-        .arg(&path)
-        .arg(format!("-o{}", target_dir.to_string_lossy()))
-        .arg("-r")
-        .arg("-tzip")
-        .arg("-y");
-    debug!("{:?}", command);
-    let process = command.output()?;
-
-    let exit_status = process.status;
-
-    if exit_status.success() {
-        Ok(())
-    } else {
-        let msg = format!(
-            "{exit_status:?}-Err:{}\nOut:{}",
-            String::from_utf8(process.stderr)?,
-            String::from_utf8(process.stdout)?
+    zip_extract(
+        Cursor::new(archive_file.as_ref()),
+        target_dir,
+        ExtractLimits::default(),
+    )
+}
+
+/// Extracts a ZIP file to the given directory, reading it from disk as it is extracted.
+pub fn zip_extract_from_file(archive_file: &Path, target_dir: &Path) -> anyhow::Result<()> {
+    let file =
+        File::open(archive_file).with_context(|| format!("Could not open {archive_file:?}"))?;
+    zip_extract(BufReader::new(file), target_dir, ExtractLimits::default())
+}
+
+/// Extracts a ZIP archive to the given directory, keeping the unix permissions of its files.
+/// Entries outside the directory, symlinks and archives over the limits are rejected, which
+/// can leave the directory partially extracted.
+pub fn zip_extract<R: Read + Seek>(
+    reader: R,
+    target_dir: &Path,
+    limits: ExtractLimits,
+) -> anyhow::Result<()> {
+    let mut archive = ZipArchive::new(reader).context("Could not read archive")?;
+    if archive.len() > limits.max_files {
+        bail!(
+            "Archive contains {} files, more than the limit of {}",
+            archive.len(),
+            limits.max_files
         );
-        Err(anyhow::Error::msg(msg))
     }
+    fs::create_dir_all(target_dir)
+        .with_context(|| format!("Could not create directory {target_dir:?}"))?;
+
+    let mut remaining_bytes = limits.max_uncompressed_bytes;
+    for index in 0..archive.len() {
+        // Bot archives can use any compression method, so an unsupported one is named in the error
+        let method = archive.by_index_raw(index)?.compression();
+        let mut entry = archive
+            .by_index(index)
+            .with_context(|| format!("Could not read archive entry compressed with {method}"))?;
+        let relative_path = entry
+            .enclosed_name()
+            .ok_or_else(|| anyhow!("Archive entry {:?} is outside the target", entry.name()))?
+            .to_path_buf();
+        let path = target_dir.join(&relative_path);
+        let mode = entry.unix_mode();
+        if mode.is_some_and(|mode| mode & S_IFMT == S_IFLNK) {
+            bail!("Archive entry {:?} is a symlink", entry.name());
+        }
+        trace!("Extracting {:?}", relative_path);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else {
+            if entry.size() > remaining_bytes {
+                bail!(
+                    "Archive expands to more than {} bytes",
+                    limits.max_uncompressed_bytes
+                );
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut file = BufWriter::new(
+                File::create(&path).with_context(|| format!("Could not create {path:?}"))?,
+            );
+            // The declared size of an entry can be forged, so the written bytes are counted too
+            let written = io::copy(&mut (&mut entry).take(remaining_bytes + 1), &mut file)?;
+            if written > remaining_bytes {
+                bail!(
+                    "Archive expands to more than {} bytes",
+                    limits.max_uncompressed_bytes
+                );
+            }
+            remaining_bytes -= written;
+            file.flush()?;
+        }
+        if let Some(mode) = mode {
+            set_permissions(&path, mode, entry.is_dir())?;
+        }
+    }
+    Ok(())
 }
 
-pub fn test_archive(path: &Path) -> anyhow::Result<()> {
-    let process = Command::new("7z")
-        .arg("t")
-        .arg(&path.to_string_lossy().to_string())
-        .arg("-r")
-        .output()?;
+#[cfg(unix)]
+fn set_permissions(path: &Path, mode: u32, is_dir: bool) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    // Folders stay writable, so the rest of the archive can be extracted into them
+    let mode = if is_dir { mode | 0o700 } else { mode };
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn set_permissions(_path: &Path, _mode: u32, _is_dir: bool) -> io::Result<()> {
+    Ok(())
+}
 
-    if process.status.success() {
-        Ok(())
-    } else {
-        let msg = String::from_utf8(process.stderr);
-        Err(anyhow::Error::msg(msg?))
+/// Reads every file of the archive, which checks their checksums
+pub fn test_archive(path: &Path) -> anyhow::Result<()> {
+    let file = File::open(path).with_context(|| format!("Could not open {path:?}"))?;
+    let mut archive = ZipArchive::new(BufReader::new(file))?;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        io::copy(&mut entry, &mut io::sink())
+            .with_context(|| format!("Archive entry {:?} is corrupted", entry.name()))?;
     }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{zip_directory, zip_extract_from_bytes};
-    use std::fs::File;
+    use super::{
+        copy_truncated, test_archive, zip_directory, zip_extract, zip_extract_from_bytes,
+        zip_log_directory, ExtractLimits,
+    };
+    use crate::configuration::ac_config::LogTruncation;
+    use std::fs::{self, File};
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    fn archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
 
     #[test]
     fn test_zip_file_size_is_smaller() {
@@ -112,6 +340,7 @@ mod tests {
 
         let path = tmp_dir.path().join("test.zip");
         zip_directory(&path, &temp_dir).expect("Could not zip file");
+        test_archive(&path).expect("Archive is corrupted");
         let file = File::open(path).expect("Could not open file");
         let zipped_archive_size = file
             .metadata()
@@ -119,4 +348,149 @@ mod tests {
             .len();
         assert!(zipped_archive_size < dir_size)
     }
+
+    #[test]
+    fn test_zip_roundtrip_keeps_folders() {
+        let source = tempfile::tempdir().unwrap();
+        fs::create_dir_all(source.path().join("data/nested")).unwrap();
+        fs::write(source.path().join("run.py"), b"print()").unwrap();
+        fs::write(source.path().join("data/nested/model.bin"), b"weights").unwrap();
+        let archive_dir = tempfile::tempdir().unwrap();
+        let archive_path = archive_dir.path().join("bot.zip");
+        zip_directory(&archive_path, source.path()).unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        let bytes = bytes::Bytes::from(fs::read(&archive_path).unwrap());
+        zip_extract_from_bytes(&bytes, target.path()).unwrap();
+
+        assert_eq!(fs::read(target.path().join("run.py")).unwrap(), b"print()");
+        assert_eq!(
+            fs::read(target.path().join("data/nested/model.bin")).unwrap(),
+            b"weights"
+        );
+    }
+
+    #[test]
+    fn test_copy_truncated_keeps_head_and_tail() {
+        let log = b"0123456789";
+        let truncated = |truncation| {
+            let mut output = Vec::new();
+            copy_truncated(&mut Cursor::new(log), 10, 4, truncation, &mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        assert_eq!(
+            truncated(LogTruncation::HeadAndTail),
+            "01\n... 6 bytes truncated ...\n89"
+        );
+        assert_eq!(
+            truncated(LogTruncation::Head),
+            "0123\n... 6 bytes truncated ...\n"
+        );
+        assert_eq!(
+            truncated(LogTruncation::Tail),
+            "\n... 6 bytes truncated ...\n6789"
+        );
+    }
+
+    #[test]
+    fn test_zip_logs_truncates_large_files_and_skips_the_archive() {
+        let logs = tempfile::tempdir().unwrap();
+        fs::write(logs.path().join("small.log"), b"ok").unwrap();
+        fs::write(logs.path().join("large.log"), vec![b'x'; 1000]).unwrap();
+        fs::create_dir(logs.path().join("zips")).unwrap();
+        let archive_path = logs.path().join("zips").join("logs.zip");
+        zip_log_directory(&archive_path, logs.path(), 100, LogTruncation::Head).unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        let bytes = bytes::Bytes::from(fs::read(&archive_path).unwrap());
+        zip_extract_from_bytes(&bytes, target.path()).unwrap();
+
+        assert_eq!(fs::read(target.path().join("small.log")).unwrap(), b"ok");
+        let large = fs::read(target.path().join("large.log")).unwrap();
+        assert!(large.len() < 200);
+        assert!(!target.path().join("zips").join("logs.zip").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_zip_keeps_executable_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let source = tempfile::tempdir().unwrap();
+        let bot = source.path().join("bot");
+        fs::write(&bot, b"#!/bin/sh").unwrap();
+        fs::set_permissions(&bot, fs::Permissions::from_mode(0o755)).unwrap();
+        let archive_dir = tempfile::tempdir().unwrap();
+        let archive_path = archive_dir.path().join("bot.zip");
+        zip_directory(&archive_path, source.path()).unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        let bytes = bytes::Bytes::from(fs::read(&archive_path).unwrap());
+        zip_extract_from_bytes(&bytes, target.path()).unwrap();
+
+        let mode = fs::metadata(target.path().join("bot"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+
+    #[test]
+    fn test_extract_rejects_entries_outside_the_target() {
+        let parent = tempfile::tempdir().unwrap();
+        let target = parent.path().join("bot");
+        let bytes = archive(&[("../escaped.txt", b"evil")]);
+
+        assert!(zip_extract(Cursor::new(bytes), &target, ExtractLimits::default()).is_err());
+        assert!(!parent.path().join("escaped.txt").exists());
+    }
+
+    #[test]
+    fn test_extract_rejects_symlinks() {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.add_symlink("link", "/etc/passwd", FileOptions::default())
+            .unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+        let target = tempfile::tempdir().unwrap();
+
+        assert!(zip_extract(Cursor::new(bytes), target.path(), ExtractLimits::default()).is_err());
+        assert!(fs::symlink_metadata(target.path().join("link")).is_err());
+    }
+
+    #[test]
+    fn test_extract_names_unsupported_compression() {
+        let mut bytes = archive(&[("a.txt", b"a")]);
+        // Deflate64 in the local and the central header of the entry
+        bytes[8..10].copy_from_slice(&9u16.to_le_bytes());
+        let central = bytes.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        bytes[central + 10..central + 12].copy_from_slice(&9u16.to_le_bytes());
+
+        let target = tempfile::tempdir().unwrap();
+        let error =
+            zip_extract(Cursor::new(bytes), target.path(), ExtractLimits::default()).unwrap_err();
+        assert!(format!("{error:#}").contains("Unsupported(9)"), "{error:#}");
+    }
+
+    #[test]
+    fn test_extract_enforces_the_limits() {
+        let bytes = archive(&[("a.txt", &[0; 100]), ("b.txt", &[0; 100])]);
+        let target = tempfile::tempdir().unwrap();
+
+        let too_many_files = ExtractLimits {
+            max_uncompressed_bytes: 1000,
+            max_files: 1,
+        };
+        assert!(zip_extract(Cursor::new(&bytes), target.path(), too_many_files).is_err());
+        let too_large = ExtractLimits {
+            max_uncompressed_bytes: 150,
+            max_files: 10,
+        };
+        assert!(zip_extract(Cursor::new(&bytes), target.path(), too_large).is_err());
+        let enough = ExtractLimits {
+            max_uncompressed_bytes: 200,
+            max_files: 2,
+        };
+        zip_extract(Cursor::new(&bytes), target.path(), enough).unwrap();
+    }
 }
@@ -2,7 +2,7 @@ use anyhow::{anyhow, Context};
 use base64::{engine::general_purpose::STANDARD, Engine};
 use common::models::aiarena::aiarena_bot::AiArenaBot;
 use common::models::aiarena::aiarena_map::AiArenaMap;
-use common::models::aiarena::aiarena_match::AiArenaMatch;
+use common::models::aiarena::aiarena_match::{AiArenaMatch, MatchSettings};
 use reqwest::Client;
 use serde::Deserialize;
 
@@ -124,6 +124,7 @@ fn convert_to_aiarena_match(m: MatchInfo) -> AiArenaMatch {
             file_hash: None,
         },
         game_base: None,
+        settings: MatchSettings::default(),
     }
 }
 
@@ -20,6 +20,7 @@ use axum::{error_handling::HandleErrorLayer, http::StatusCode};
 use common::api::health;
 use common::configuration::get_host_url;
 use common::logging::init_logging;
+use common::paths::match_layout;
 use config::{Config, FileFormat};
 use parking_lot::RwLock;
 use state::AppState;
@@ -45,7 +46,7 @@ async fn main() {
 
     let env_log = std::env::var("RUST_LOG")
         .unwrap_or_else(|_| format!("info,common={},k8s_controller={}", "debug", "debug"));
-    let log_path = "/logs/k8s_controller".to_string();
+    let log_path = match_layout().log_file("k8s_controller");
     let log_file = "k8s_controller.log";
     let full_path = Path::new(&log_path).join(log_file);
     if full_path.exists() {
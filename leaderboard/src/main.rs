@@ -0,0 +1,83 @@
+mod ratings;
+mod report;
+
+use crate::ratings::RatingSystem;
+use crate::report::build_report;
+use anyhow::{Context, Result};
+use clap::{crate_name, crate_version, value_parser, Arg, ArgAction, Command};
+use common::models::aiarena::local_game_result::{LocalGameResult, LocalResults};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn main() -> Result<()> {
+    let args = Command::new(crate_name!())
+        .version(crate_version!())
+        .about("Rates the bots of the results files of local matches and writes a leaderboard")
+        .arg(
+            Arg::new("results_files")
+                .value_name("RESULTS_FILE")
+                .required(true)
+                .action(ArgAction::Append)
+                .value_parser(value_parser!(PathBuf))
+                .help("Results files of the match controller, e.g. results.json of the native controller"),
+        )
+        .arg(
+            Arg::new("rating")
+                .long("rating")
+                .value_name("SYSTEM")
+                .default_value("elo")
+                .value_parser(["elo", "glicko2"])
+                .help("Rating system of the leaderboard"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("DIRECTORY")
+                .default_value(".")
+                .value_parser(value_parser!(PathBuf))
+                .help("Folder for leaderboard.json, leaderboard.md and leaderboard.csv"),
+        )
+        .get_matches();
+
+    let system = args
+        .get_one::<String>("rating")
+        .unwrap()
+        .parse::<RatingSystem>()
+        .unwrap();
+    let output = args.get_one::<PathBuf>("output").unwrap();
+
+    let mut results = Vec::new();
+    for results_file in args.get_many::<PathBuf>("results_files").unwrap() {
+        results.extend(read_results(results_file)?);
+    }
+    // Results of several files are rated in the order they were recorded
+    results.sort_by_key(|result| result.timestamp);
+
+    let report = build_report(system, &results);
+    fs::create_dir_all(output).with_context(|| format!("Could not create {output:?}"))?;
+    for (file_name, contents) in [
+        ("leaderboard.json", serde_json::to_string_pretty(&report)?),
+        ("leaderboard.md", report.to_markdown()),
+        ("leaderboard.csv", report.to_csv()),
+    ] {
+        let path = output.join(file_name);
+        fs::write(&path, contents).with_context(|| format!("Could not write {path:?}"))?;
+    }
+
+    println!(
+        "Rated {} bots in {} games, skipped {} results. Leaderboard is in {:?}",
+        report.leaderboard.len(),
+        report.games,
+        report.skipped,
+        output
+    );
+    Ok(())
+}
+
+fn read_results(results_file: &Path) -> Result<Vec<LocalGameResult>> {
+    let bytes =
+        fs::read(results_file).with_context(|| format!("Could not read {results_file:?}"))?;
+    let results: LocalResults = serde_json::from_slice(&bytes)
+        .with_context(|| format!("Could not parse {results_file:?}"))?;
+    Ok(results.results)
+}
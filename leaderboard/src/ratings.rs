@@ -0,0 +1,228 @@
+//! Rating systems for the bots, updated game by game.
+
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// Rating points at stake in an Elo game
+const ELO_K_FACTOR: f64 = 32.0;
+
+const GLICKO2_INITIAL_DEVIATION: f64 = 350.0;
+const GLICKO2_INITIAL_VOLATILITY: f64 = 0.06;
+/// Constrains the change of the volatility, between 0.3 and 1.2
+const GLICKO2_TAU: f64 = 0.5;
+/// Converts between the Glicko and the Glicko-2 scale
+const GLICKO2_SCALE: f64 = 173.7178;
+const CONVERGENCE_TOLERANCE: f64 = 0.000_001;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RatingSystem {
+    Elo,
+    Glicko2,
+}
+
+impl FromStr for RatingSystem {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "elo" => Ok(Self::Elo),
+            "glicko2" => Ok(Self::Glicko2),
+            _ => Err(format!("Unknown rating system: {input}")),
+        }
+    }
+}
+
+/// The rating of a bot in either system. Elo ratings have no deviation or volatility.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: Option<f64>,
+    pub volatility: Option<f64>,
+}
+
+impl Rating {
+    pub fn initial(system: RatingSystem) -> Self {
+        match system {
+            RatingSystem::Elo => Self {
+                rating: INITIAL_RATING,
+                deviation: None,
+                volatility: None,
+            },
+            RatingSystem::Glicko2 => Self {
+                rating: INITIAL_RATING,
+                deviation: Some(GLICKO2_INITIAL_DEVIATION),
+                volatility: Some(GLICKO2_INITIAL_VOLATILITY),
+            },
+        }
+    }
+}
+
+/// Rates a game between two bots, with the score of the first bot (1 win, 0.5 tie, 0 loss).
+/// Both ratings change with the ratings before the game.
+pub fn rate_game(
+    system: RatingSystem,
+    first: Rating,
+    second: Rating,
+    score: f64,
+) -> (Rating, Rating) {
+    match system {
+        RatingSystem::Elo => {
+            let (first_rating, second_rating) = elo(first.rating, second.rating, score);
+            (
+                Rating {
+                    rating: first_rating,
+                    ..first
+                },
+                Rating {
+                    rating: second_rating,
+                    ..second
+                },
+            )
+        }
+        RatingSystem::Glicko2 => (
+            glicko2(first, &[(second, score)]),
+            glicko2(second, &[(first, 1.0 - score)]),
+        ),
+    }
+}
+
+fn elo(first: f64, second: f64, score: f64) -> (f64, f64) {
+    let expected = 1.0 / (1.0 + 10f64.powf((second - first) / 400.0));
+    let change = ELO_K_FACTOR * (score - expected);
+    (first + change, second - change)
+}
+
+/// Updates a Glicko-2 rating with the games of a rating period, given as the ratings
+/// of the opponents before the period and the scores against them
+fn glicko2(player: Rating, games: &[(Rating, f64)]) -> Rating {
+    let deviation = player.deviation.unwrap_or(GLICKO2_INITIAL_DEVIATION);
+    let volatility = player.volatility.unwrap_or(GLICKO2_INITIAL_VOLATILITY);
+    let mu = (player.rating - INITIAL_RATING) / GLICKO2_SCALE;
+    let phi = deviation / GLICKO2_SCALE;
+
+    if games.is_empty() {
+        return Rating {
+            deviation: Some((phi.powi(2) + volatility.powi(2)).sqrt() * GLICKO2_SCALE),
+            ..player
+        };
+    }
+
+    let g = |phi: f64| 1.0 / (1.0 + 3.0 * phi.powi(2) / PI.powi(2)).sqrt();
+    let mut variance_inverse = 0.0;
+    let mut improvement_sum = 0.0;
+    for (opponent, score) in games {
+        let opponent_mu = (opponent.rating - INITIAL_RATING) / GLICKO2_SCALE;
+        let opponent_phi = opponent.deviation.unwrap_or(GLICKO2_INITIAL_DEVIATION) / GLICKO2_SCALE;
+        let g_phi = g(opponent_phi);
+        let expected = 1.0 / (1.0 + (-g_phi * (mu - opponent_mu)).exp());
+        variance_inverse += g_phi.powi(2) * expected * (1.0 - expected);
+        improvement_sum += g_phi * (score - expected);
+    }
+    let variance = 1.0 / variance_inverse;
+    let delta = variance * improvement_sum;
+
+    let new_volatility = glicko2_volatility(phi, volatility, variance, delta);
+    let pre_period_phi = (phi.powi(2) + new_volatility.powi(2)).sqrt();
+    let new_phi = 1.0 / (1.0 / pre_period_phi.powi(2) + 1.0 / variance).sqrt();
+    let new_mu = mu + new_phi.powi(2) * improvement_sum;
+
+    Rating {
+        rating: new_mu * GLICKO2_SCALE + INITIAL_RATING,
+        deviation: Some(new_phi * GLICKO2_SCALE),
+        volatility: Some(new_volatility),
+    }
+}
+
+/// The new volatility, found with the Illinois algorithm of the Glicko-2 paper
+fn glicko2_volatility(phi: f64, volatility: f64, variance: f64, delta: f64) -> f64 {
+    let a = volatility.powi(2).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        ex * (delta.powi(2) - phi.powi(2) - variance - ex)
+            / (2.0 * (phi.powi(2) + variance + ex).powi(2))
+            - (x - a) / GLICKO2_TAU.powi(2)
+    };
+
+    let mut lower = a;
+    let mut upper = if delta.powi(2) > phi.powi(2) + variance {
+        (delta.powi(2) - phi.powi(2) - variance).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * GLICKO2_TAU) < 0.0 {
+            k += 1.0;
+        }
+        a - k * GLICKO2_TAU
+    };
+
+    let (mut f_lower, mut f_upper) = (f(lower), f(upper));
+    while (upper - lower).abs() > CONVERGENCE_TOLERANCE {
+        let next = lower + (lower - upper) * f_lower / (f_upper - f_lower);
+        let f_next = f(next);
+        if f_next * f_upper <= 0.0 {
+            lower = upper;
+            f_lower = f_upper;
+        } else {
+            f_lower /= 2.0;
+        }
+        upper = next;
+        f_upper = f_next;
+    }
+
+    (lower / 2.0).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glicko2_rating(rating: f64, deviation: f64) -> Rating {
+        Rating {
+            rating,
+            deviation: Some(deviation),
+            volatility: Some(0.06),
+        }
+    }
+
+    #[test]
+    fn test_elo_exchanges_points() {
+        let initial = Rating::initial(RatingSystem::Elo);
+        let (winner, loser) = rate_game(RatingSystem::Elo, initial, initial, 1.0);
+        assert_eq!(winner.rating, 1516.0);
+        assert_eq!(loser.rating, 1484.0);
+
+        let (first, second) = rate_game(RatingSystem::Elo, winner, loser, 0.5);
+        assert!(first.rating < winner.rating);
+        assert!((first.rating + second.rating - 3000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_glicko2_example_of_the_paper() {
+        let player = glicko2_rating(1500.0, 200.0);
+        let games = [
+            (glicko2_rating(1400.0, 30.0), 1.0),
+            (glicko2_rating(1550.0, 100.0), 0.0),
+            (glicko2_rating(1700.0, 300.0), 0.0),
+        ];
+        let rating = glicko2(player, &games);
+        assert!((rating.rating - 1464.06).abs() < 0.01, "{rating:?}");
+        assert!(
+            (rating.deviation.unwrap() - 151.52).abs() < 0.01,
+            "{rating:?}"
+        );
+        assert!(
+            (rating.volatility.unwrap() - 0.05999).abs() < 0.00001,
+            "{rating:?}"
+        );
+    }
+
+    #[test]
+    fn test_glicko2_game_moves_both_ratings() {
+        let initial = Rating::initial(RatingSystem::Glicko2);
+        let (winner, loser) = rate_game(RatingSystem::Glicko2, initial, initial, 1.0);
+        assert!(winner.rating > INITIAL_RATING);
+        assert!((winner.rating - INITIAL_RATING + loser.rating - INITIAL_RATING).abs() < 1e-9);
+        assert!(winner.deviation.unwrap() < GLICKO2_INITIAL_DEVIATION);
+    }
+}
@@ -0,0 +1,338 @@
+use crate::ratings::{rate_game, Rating, RatingSystem};
+use common::models::aiarena::aiarena_result::AiArenaResult;
+use common::models::aiarena::local_game_result::{LocalGameResult, ResultPlayer};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// The leaderboard and the win rates of the bots
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub rating_system: String,
+    /// Games with a winner or a tie, which count for the ratings
+    pub games: usize,
+    /// Results without players, which were recorded before the players were, or without an outcome
+    pub skipped: usize,
+    pub leaderboard: Vec<Standing>,
+    pub matchups: Vec<WinRate>,
+    pub maps: Vec<WinRate>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Standing {
+    pub rank: usize,
+    pub id: String,
+    pub name: String,
+    pub race: String,
+    pub rating: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deviation: Option<f64>,
+    #[serde(flatten)]
+    pub record: Record,
+}
+
+/// The games of a bot against an opponent or on a map
+#[derive(Serialize, Debug)]
+pub struct WinRate {
+    pub bot: String,
+    /// The opponent or the map
+    pub against: String,
+    #[serde(flatten)]
+    pub record: Record,
+}
+
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Record {
+    pub games: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub ties: usize,
+    /// Wins and half of the ties per game
+    pub win_rate: f64,
+}
+
+impl Record {
+    fn add(&mut self, score: f64) {
+        self.games += 1;
+        match score {
+            s if s > 0.5 => self.wins += 1,
+            s if s < 0.5 => self.losses += 1,
+            _ => self.ties += 1,
+        }
+        self.win_rate = (self.wins as f64 + self.ties as f64 / 2.0) / self.games as f64;
+    }
+}
+
+/// The score of player 1, or None if the game has no winner, like a game that failed to start
+fn player1_score(result: AiArenaResult) -> Option<f64> {
+    match result {
+        AiArenaResult::Player1Win | AiArenaResult::Player2Crash | AiArenaResult::Player2TimeOut => {
+            Some(1.0)
+        }
+        AiArenaResult::Player2Win | AiArenaResult::Player1Crash | AiArenaResult::Player1TimeOut => {
+            Some(0.0)
+        }
+        AiArenaResult::Tie => Some(0.5),
+        AiArenaResult::InitializationError | AiArenaResult::Error | AiArenaResult::Placeholder => {
+            None
+        }
+    }
+}
+
+struct Bot {
+    player: ResultPlayer,
+    rating: Rating,
+    record: Record,
+}
+
+/// Rates the bots game by game, in the order of the results, and counts their win rates
+pub fn build_report(system: RatingSystem, results: &[LocalGameResult]) -> Report {
+    let mut bots: HashMap<String, Bot> = HashMap::new();
+    let mut matchups: BTreeMap<(String, String), Record> = BTreeMap::new();
+    let mut maps: BTreeMap<(String, String), Record> = BTreeMap::new();
+    let mut games = 0;
+
+    for result in results {
+        let (Some(bot1), Some(bot2), Some(score)) = (
+            result.bot1.as_ref(),
+            result.bot2.as_ref(),
+            player1_score(result.game_result.result),
+        ) else {
+            continue;
+        };
+        games += 1;
+
+        for player in [bot1, bot2] {
+            bots.entry(player.id.clone()).or_insert_with(|| Bot {
+                player: player.clone(),
+                rating: Rating::initial(system),
+                record: Record::default(),
+            });
+        }
+        let (rating1, rating2) =
+            rate_game(system, bots[&bot1.id].rating, bots[&bot2.id].rating, score);
+
+        for (player, opponent, rating, score) in [
+            (bot1, bot2, rating1, score),
+            (bot2, bot1, rating2, 1.0 - score),
+        ] {
+            let bot = bots.get_mut(&player.id).unwrap();
+            bot.rating = rating;
+            bot.record.add(score);
+            matchups
+                .entry((player.name.clone(), opponent.name.clone()))
+                .or_default()
+                .add(score);
+            if let Some(map) = &result.map {
+                maps.entry((player.name.clone(), map.clone()))
+                    .or_default()
+                    .add(score);
+            }
+        }
+    }
+
+    let mut bots: Vec<Bot> = bots.into_values().collect();
+    bots.sort_by(|a, b| {
+        b.rating
+            .rating
+            .total_cmp(&a.rating.rating)
+            .then_with(|| a.player.name.cmp(&b.player.name))
+    });
+    let leaderboard = bots
+        .into_iter()
+        .enumerate()
+        .map(|(index, bot)| Standing {
+            rank: index + 1,
+            id: bot.player.id,
+            name: bot.player.name,
+            race: format!("{:?}", bot.player.race),
+            rating: bot.rating.rating,
+            deviation: bot.rating.deviation,
+            record: bot.record,
+        })
+        .collect();
+    let win_rates = |records: BTreeMap<(String, String), Record>| {
+        records
+            .into_iter()
+            .map(|((bot, against), record)| WinRate {
+                bot,
+                against,
+                record,
+            })
+            .collect()
+    };
+
+    Report {
+        rating_system: format!("{system:?}"),
+        games,
+        skipped: results.len() - games,
+        leaderboard,
+        matchups: win_rates(matchups),
+        maps: win_rates(maps),
+    }
+}
+
+impl Report {
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!(
+            "# Leaderboard\n\n{} rating of {} games",
+            self.rating_system, self.games
+        );
+        if self.skipped > 0 {
+            let _ = write!(
+                markdown,
+                ", skipping {} results without players or outcome",
+                self.skipped
+            );
+        }
+        markdown.push_str(
+            "\n\n| Rank | Bot | Race | Rating | Games | Wins | Losses | Ties | Win rate |\n",
+        );
+        markdown
+            .push_str("|------|-----|------|--------|-------|------|--------|------|----------|\n");
+        for standing in &self.leaderboard {
+            let rating = match standing.deviation {
+                Some(deviation) => format!("{:.0} ± {:.0}", standing.rating, deviation),
+                None => format!("{:.0}", standing.rating),
+            };
+            let record = &standing.record;
+            let _ = writeln!(
+                markdown,
+                "| {} | {} | {} | {} | {} | {} | {} | {} | {:.1}% |",
+                standing.rank,
+                standing.name,
+                standing.race,
+                rating,
+                record.games,
+                record.wins,
+                record.losses,
+                record.ties,
+                record.win_rate * 100.0
+            );
+        }
+
+        for (title, column, win_rates) in [
+            ("Matchups", "Opponent", &self.matchups),
+            ("Maps", "Map", &self.maps),
+        ] {
+            let _ = write!(
+                markdown,
+                "\n## {title}\n\n| Bot | {column} | Games | Wins | Losses | Ties | Win rate |\n\
+                 |-----|------|-------|------|--------|------|----------|\n"
+            );
+            for win_rate in win_rates {
+                let record = &win_rate.record;
+                let _ = writeln!(
+                    markdown,
+                    "| {} | {} | {} | {} | {} | {} | {:.1}% |",
+                    win_rate.bot,
+                    win_rate.against,
+                    record.games,
+                    record.wins,
+                    record.losses,
+                    record.ties,
+                    record.win_rate * 100.0
+                );
+            }
+        }
+        markdown
+    }
+
+    /// The leaderboard as CSV
+    pub fn to_csv(&self) -> String {
+        let mut csv =
+            "rank,id,name,race,rating,deviation,games,wins,losses,ties,win_rate\n".to_string();
+        for standing in &self.leaderboard {
+            let record = &standing.record;
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{:.1},{},{},{},{},{},{:.3}",
+                standing.rank,
+                standing.id,
+                standing.name,
+                standing.race,
+                standing.rating,
+                standing
+                    .deviation
+                    .map(|d| format!("{d:.1}"))
+                    .unwrap_or_default(),
+                record.games,
+                record.wins,
+                record.losses,
+                record.ties,
+                record.win_rate
+            );
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
+    use common::models::aiarena::bot_race::BotRace;
+
+    fn result(bot1: &str, bot2: &str, map: &str, result: AiArenaResult) -> LocalGameResult {
+        let player = |name: &str| ResultPlayer {
+            id: format!("{name}-id"),
+            name: name.to_string(),
+            race: BotRace::Terran,
+        };
+        LocalGameResult {
+            game_result: AiArenaGameResult {
+                result,
+                ..AiArenaGameResult::new_error(1)
+            },
+            bot1: Some(player(bot1)),
+            bot2: Some(player(bot2)),
+            map: Some(map.to_string()),
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn test_report_ranks_bots_and_counts_win_rates() {
+        let mut old_result = result("a", "b", "MapA", AiArenaResult::Player1Win);
+        old_result.bot1 = None;
+        let results = vec![
+            result("a", "b", "MapA", AiArenaResult::Player1Win),
+            result("b", "a", "MapB", AiArenaResult::Player1Crash),
+            result("a", "c", "MapA", AiArenaResult::Tie),
+            result("c", "b", "MapB", AiArenaResult::Player2Win),
+            result("c", "b", "MapB", AiArenaResult::InitializationError),
+            old_result,
+        ];
+        let report = build_report(RatingSystem::Elo, &results);
+
+        assert_eq!(report.games, 4);
+        assert_eq!(report.skipped, 2);
+        let names: Vec<&str> = report.leaderboard.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert_eq!(report.leaderboard[0].record.wins, 2);
+        assert_eq!(report.leaderboard[0].record.ties, 1);
+        assert_eq!(report.leaderboard[0].race, "Terran");
+
+        let a_vs_b = report
+            .matchups
+            .iter()
+            .find(|w| w.bot == "a" && w.against == "b")
+            .unwrap();
+        assert_eq!(a_vs_b.record.games, 2);
+        assert_eq!(a_vs_b.record.win_rate, 1.0);
+        let c_on_map_a = report
+            .maps
+            .iter()
+            .find(|w| w.bot == "c" && w.against == "MapA")
+            .unwrap();
+        assert_eq!(c_on_map_a.record.win_rate, 0.5);
+
+        assert!(report.to_markdown().contains("| 1 | a | Terran | "));
+        assert!(report
+            .to_csv()
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("1,a-id,a,Terran,"));
+    }
+}
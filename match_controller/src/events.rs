@@ -0,0 +1,150 @@
+use axum::extract::State;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use common::api::health;
+use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
+use common::models::match_controller::ControllerEvent;
+use common::models::Status;
+use common::portpicker::Port;
+use common::PlayerNum;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{error, info};
+
+/// The events reported by the game and bot controllers during the match
+#[derive(Default)]
+pub struct MatchEvents {
+    state: Mutex<MatchEventState>,
+    notify: Notify,
+}
+
+#[derive(Default)]
+struct MatchEventState {
+    joined_players: HashSet<PlayerNum>,
+    game_started: bool,
+    game_result: Option<AiArenaGameResult>,
+    bot_exit_codes: HashMap<PlayerNum, i32>,
+}
+
+impl MatchEvents {
+    pub fn record(&self, event: ControllerEvent) {
+        info!("Event: {:?}", event);
+        {
+            let mut state = self.state.lock();
+            match event {
+                ControllerEvent::PlayerJoined { player_num } => {
+                    state.joined_players.insert(player_num);
+                }
+                ControllerEvent::GameStarted => state.game_started = true,
+                ControllerEvent::GameEnded { result } => {
+                    // Like the result file, the first result is kept
+                    state.game_result.get_or_insert(result);
+                }
+                ControllerEvent::BotExited {
+                    player_num,
+                    exit_code,
+                } => {
+                    state.bot_exit_codes.insert(player_num, exit_code);
+                }
+            }
+        }
+        self.notify.notify_waiters();
+    }
+
+    pub fn player_joined(&self, player_num: PlayerNum) -> bool {
+        self.state.lock().joined_players.contains(&player_num)
+    }
+
+    pub fn game_started(&self) -> bool {
+        self.state.lock().game_started
+    }
+
+    pub fn game_result(&self) -> Option<AiArenaGameResult> {
+        self.state.lock().game_result.clone()
+    }
+
+    pub fn bot_exited(&self, player_num: PlayerNum) -> bool {
+        self.state.lock().bot_exit_codes.contains_key(&player_num)
+    }
+
+    /// Waits for the next event, at most for the given duration
+    pub async fn wait(&self, duration: Duration) {
+        let _ = tokio::time::timeout(duration, self.notify.notified()).await;
+    }
+}
+
+/// Serves the event endpoint on the given port.
+/// Returns None if the port is zero or can't be bound, so the controllers fall back to files.
+pub async fn serve_events(port: Port) -> Option<Arc<MatchEvents>> {
+    if port == 0 {
+        return None;
+    }
+    let events = Arc::new(MatchEvents::default());
+    let app = Router::new()
+        .route("/health", get(health))
+        .route("/events", post(post_event))
+        .with_state(events.clone());
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let server = match axum::Server::try_bind(&addr) {
+        Ok(builder) => builder.serve(app.into_make_service()),
+        Err(e) => {
+            error!("Could not serve events on {}: {:?}", addr, e);
+            return None;
+        }
+    };
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            error!("Event server failed: {:?}", e);
+        }
+    });
+
+    info!("Serving events on {}", addr);
+    Some(events)
+}
+
+async fn post_event(
+    State(events): State<Arc<MatchEvents>>,
+    Json(event): Json<ControllerEvent>,
+) -> Json<Status> {
+    events.record(event);
+    Json(Status::Success)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_events() {
+        let events = MatchEvents::default();
+        events.record(ControllerEvent::PlayerJoined {
+            player_num: PlayerNum::One,
+        });
+        events.record(ControllerEvent::BotExited {
+            player_num: PlayerNum::Two,
+            exit_code: 1,
+        });
+        assert!(events.player_joined(PlayerNum::One));
+        assert!(!events.player_joined(PlayerNum::Two));
+        assert!(events.bot_exited(PlayerNum::Two));
+        assert!(!events.game_started());
+        assert!(events.game_result().is_none());
+    }
+
+    #[test]
+    fn test_first_result_is_kept() {
+        let events = MatchEvents::default();
+        events.record(ControllerEvent::GameEnded {
+            result: AiArenaGameResult::new_error(1),
+        });
+        events.record(ControllerEvent::GameEnded {
+            result: AiArenaGameResult::new_initialization_error(2),
+        });
+        assert_eq!(events.game_result().map(|r| r.match_id), Some(1));
+    }
+}
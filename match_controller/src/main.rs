@@ -1,17 +1,21 @@
 #![allow(dead_code)]
+mod events;
 mod match_scheduler;
 pub mod matches;
 mod routes;
 mod state;
 
 use crate::match_scheduler::match_scheduler;
+use crate::matches::outbox::Outbox;
 use crate::matches::sources::aiarena_api::HttpApiSource;
 use crate::matches::sources::test_source::TestSource;
 use crate::matches::sources::MatchSource;
+use clap::{crate_name, crate_version, ArgMatches, Command};
 use common::configuration::ac_config::{ACConfig, RunType};
 use common::logging::init_logging;
 use config::{Config, FileFormat};
 use std::path::Path;
+use tracing_appender::non_blocking::WorkerGuard;
 
 static PREFIX: &str = "acmatch";
 
@@ -19,27 +23,40 @@ static PREFIX: &str = "acmatch";
 async fn main() {
     let settings = setup_controller_config();
 
-    let log_level = &settings.logging_level;
-    let env_log = std::env::var("RUST_LOG")
-        .unwrap_or_else(|_| format!("info,common={log_level},match_controller={log_level}"));
-    let log_path = format!("{}/match_controller", &settings.log_root);
+    let matches = Command::new(crate_name!())
+        .version(crate_version!())
+        .subcommand(
+            Command::new("outbox")
+                .about("Manages the results that wait to be submitted to AI Arena")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("Lists the pending submissions"))
+                .subcommand(
+                    Command::new("flush")
+                        .about("Submits all pending submissions, ignoring their backoff"),
+                ),
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        Some(("outbox", args)) => outbox(&settings, args).await,
+        _ => run(settings).await,
+    }
+}
+
+async fn run(settings: ACConfig) {
     let log_file = match settings.run_type {
         RunType::Prepare => "prepare_match.log",
         RunType::Submit => "submit_result.log",
     };
-    let full_path = Path::new(&log_path).join(log_file);
+    let full_path = Path::new(&settings.log_root)
+        .join("match_controller")
+        .join(log_file);
     if full_path.exists() {
         tokio::fs::remove_file(full_path).await.unwrap();
     }
-    let (non_blocking_stdout, _guard) = tracing_appender::non_blocking(std::io::stdout());
-    let non_blocking_file = tracing_appender::rolling::never(&log_path, log_file);
-    init_logging(&env_log, non_blocking_stdout, non_blocking_file);
+    let _guard = init_logs(&settings, log_file);
 
-    let match_source: Box<dyn MatchSource> = if settings.base_website_url.is_empty() {
-        Box::new(TestSource::new(settings.clone()))
-    } else {
-        Box::new(HttpApiSource::new(settings.clone()).unwrap())
-    };
+    let match_source = match_source(&settings);
 
     match_scheduler(&settings, match_source).await;
 
@@ -63,6 +80,71 @@ async fn main() {
     println!("Match controller exits");
 }
 
+async fn outbox(settings: &ACConfig, args: &ArgMatches) {
+    let _guard = init_logs(settings, "outbox.log");
+    let Some(outbox) = Outbox::from_settings(settings) else {
+        eprintln!(
+            "The submission outbox is disabled, set SUBMISSION_OUTBOX_DIRECTORY to enable it"
+        );
+        std::process::exit(1);
+    };
+
+    match args.subcommand() {
+        Some(("list", _)) => match outbox.pending() {
+            Ok(pending) => {
+                for submission in &pending {
+                    println!(
+                        "Match {}: {} attempts, next attempt at {}{}",
+                        submission.match_id(),
+                        submission.attempts,
+                        submission.next_attempt_at,
+                        submission
+                            .last_error
+                            .as_ref()
+                            .map(|e| format!(", last error: {e}"))
+                            .unwrap_or_default()
+                    );
+                }
+                println!("{} pending submissions", pending.len());
+            }
+            Err(e) => {
+                eprintln!("Could not read the submission outbox: {e}");
+                std::process::exit(1);
+            }
+        },
+        Some(("flush", _)) => {
+            let summary = outbox.flush(&match_source(settings), true).await;
+            println!(
+                "{} submitted, {} failed, {} rejected",
+                summary.submitted, summary.failed, summary.rejected
+            );
+            if summary.failed > 0 || summary.rejected > 0 {
+                std::process::exit(1);
+            }
+        }
+        _ => unreachable!("The outbox subcommand is required"),
+    }
+}
+
+fn init_logs(settings: &ACConfig, log_file: &str) -> WorkerGuard {
+    let log_level = &settings.logging_level;
+    let env_log = std::env::var("RUST_LOG")
+        .unwrap_or_else(|_| format!("info,common={log_level},match_controller={log_level}"));
+    let log_path = format!("{}/match_controller", &settings.log_root);
+    let (non_blocking_stdout, guard) = tracing_appender::non_blocking(std::io::stdout());
+    let non_blocking_file = tracing_appender::rolling::never(&log_path, log_file);
+    init_logging(&env_log, non_blocking_stdout, non_blocking_file);
+    guard
+}
+
+fn match_source(settings: &ACConfig) -> Box<dyn MatchSource> {
+    if settings.base_website_url.is_empty() {
+        Box::new(TestSource::new(settings.clone()))
+    } else {
+        Box::new(HttpApiSource::new(settings.clone()).unwrap())
+    }
+}
+
 fn setup_controller_config() -> ACConfig {
     let default_config = include_str!("../config.toml");
     Config::builder()
@@ -0,0 +1,189 @@
+//! Local cache of the downloaded bots, bot data and maps, keyed by their md5 hash.
+
+use bytes::Bytes;
+use common::configuration::ac_config::ACConfig;
+use common::utilities::files::{md5_hex, partial_path, remove_dir_if_exists, write_atomically};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{debug, warn};
+
+/// Cached files, stored as `<directory>/<md5 hash>`. The least recently used files are
+/// evicted once the cache holds more than `max_bytes`.
+pub struct AssetCache {
+    directory: PathBuf,
+    max_bytes: u64,
+    verify: bool,
+}
+
+impl AssetCache {
+    /// The cache of the settings, or None if `asset_cache_directory` is not set
+    pub fn from_settings(settings: &ACConfig) -> Option<Self> {
+        if settings.asset_cache_directory.is_empty() {
+            return None;
+        }
+        Some(Self::new(
+            &settings.asset_cache_directory,
+            settings.asset_cache_max_bytes,
+            settings.hash_check,
+        ))
+    }
+
+    pub fn new(directory: impl Into<PathBuf>, max_bytes: u64, verify: bool) -> Self {
+        Self {
+            directory: directory.into(),
+            max_bytes,
+            verify,
+        }
+    }
+
+    /// The cached file of the hash. A file that does not match its hash is removed
+    /// when the hashes are verified.
+    pub fn get(&self, hash: &str) -> Option<Bytes> {
+        let path = self.entry_path(hash)?;
+        let bytes = fs::read(&path).ok()?;
+        if self.verify && md5_hex(&bytes) != hash.to_lowercase() {
+            warn!(
+                "Removing cached file {:?} that does not match its hash",
+                path
+            );
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        // The modification time orders the files for the eviction
+        if let Err(e) = File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            debug!("Could not update the access time of {:?}: {}", path, e);
+        }
+        Some(Bytes::from(bytes))
+    }
+
+    /// Stores the file under its hash and evicts the least recently used files over the size limit.
+    /// Files that do not match the hash are not stored when the hashes are verified.
+    pub fn insert(&self, hash: &str, bytes: &[u8]) -> io::Result<()> {
+        let path = self.entry_path(hash).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid hash {hash:?}"),
+            )
+        })?;
+        if self.verify {
+            let actual = md5_hex(bytes);
+            if actual != hash.to_lowercase() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Expected md5 hash {hash}, got {actual}"),
+                ));
+            }
+        }
+        fs::create_dir_all(&self.directory)?;
+        write_atomically(&path, bytes)?;
+        self.evict(&path)
+    }
+
+    /// Removes the least recently used files until the cache fits its size limit,
+    /// keeping the file that was just stored
+    fn evict(&self, keep: &Path) -> io::Result<()> {
+        let mut entries = Vec::new();
+        let mut total_bytes = 0;
+        for entry in fs::read_dir(&self.directory)? {
+            let entry = entry?;
+            if !is_md5_hash(&entry.file_name().to_string_lossy()) {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+            total_bytes += metadata.len();
+            entries.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+        entries.sort();
+
+        for (_, size, path) in entries {
+            if total_bytes <= self.max_bytes {
+                break;
+            }
+            if path == keep {
+                continue;
+            }
+            debug!("Evicting {:?} from the asset cache", path);
+            fs::remove_file(&path)?;
+            total_bytes -= size;
+        }
+        Ok(())
+    }
+
+    fn entry_path(&self, hash: &str) -> Option<PathBuf> {
+        is_md5_hash(hash).then(|| self.directory.join(hash.to_lowercase()))
+    }
+}
+
+fn is_md5_hash(hash: &str) -> bool {
+    hash.len() == 32 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Extracts the ZIP file next to the folder and renames it, replacing the previous contents
+/// of the folder only once the extraction succeeded
+pub fn extract_atomically(archive_file: &Bytes, folder: &Path) -> anyhow::Result<()> {
+    let partial = partial_path(folder);
+    remove_dir_if_exists(&partial)?;
+    if let Err(e) = common::utilities::zip_utils::zip_extract_from_bytes(archive_file, &partial) {
+        let _ = fs::remove_dir_all(&partial);
+        return Err(e);
+    }
+    remove_dir_if_exists(folder)?;
+    fs::rename(&partial, folder)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cache_verifies_the_hash() {
+        let directory = tempfile::tempdir().unwrap();
+        let cache = AssetCache::new(directory.path(), 1024, true);
+        let hash = md5_hex(b"bot");
+
+        assert!(cache.insert(&hash, b"not the bot").is_err());
+        assert!(cache.get(&hash).is_none());
+
+        cache.insert(&hash, b"bot").unwrap();
+        assert_eq!(cache.get(&hash.to_uppercase()).unwrap(), Bytes::from("bot"));
+
+        fs::write(directory.path().join(&hash), b"corrupted").unwrap();
+        assert!(cache.get(&hash).is_none());
+        assert!(!directory.path().join(&hash).exists());
+        assert!(cache.insert("../escape", b"bot").is_err());
+    }
+
+    #[test]
+    fn test_cache_evicts_the_least_recently_used_files() {
+        let directory = tempfile::tempdir().unwrap();
+        let cache = AssetCache::new(directory.path(), 10, false);
+        let hashes: Vec<String> = ["a", "b", "c"]
+            .iter()
+            .map(|s| md5_hex(s.as_bytes()))
+            .collect();
+
+        cache.insert(&hashes[0], b"12345").unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        cache.insert(&hashes[1], b"12345").unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        // Using the first file makes the second one the least recently used
+        cache.get(&hashes[0]).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        cache.insert(&hashes[2], b"12345").unwrap();
+
+        assert!(cache.get(&hashes[0]).is_some());
+        assert!(cache.get(&hashes[1]).is_none());
+        assert!(cache.get(&hashes[2]).is_some());
+    }
+}
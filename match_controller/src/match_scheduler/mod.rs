@@ -1,18 +1,36 @@
+mod asset_cache;
+
+use crate::events::{serve_events, MatchEvents};
+use crate::match_scheduler::asset_cache::{extract_atomically, AssetCache};
+use crate::matches::outbox::Outbox;
 use crate::matches::sources::{LogsAndReplays, MatchSource};
 use crate::routes::{download_bot, download_bot_data, download_map};
+use bytes::Bytes;
+use common::api::errors::app_error::AppError;
 use common::configuration::ac_config::{ACConfig, RunType};
 use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
 use common::models::aiarena::aiarena_match::{Match, MatchPlayer, MatchRequest};
-use common::utilities::zip_utils::zip_directory_to_path;
+use common::models::aiarena::aiarena_result::AiArenaResult;
+use common::models::match_controller::JoinState;
+use common::paths::MatchLayout;
+use common::utilities::directory::directory_size;
+use common::utilities::files::write_atomically;
+use common::utilities::zip_utils::{zip_directory_to_path, zip_log_directory};
 use common::PlayerNum;
 use std::collections::HashMap;
+use std::future::Future;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
-use tokio::io::AsyncWriteExt;
 use tokio::time::sleep;
 use tracing::{error, info};
 
+// Time given to the game controller to store the result after the real time limit of the match
+const RESULT_GRACE_PERIOD: Duration = Duration::from_secs(120);
+
+// Tag of a bot whose data folder exceeds the data quota and is not uploaded
+const DATA_QUOTA_TAG: &str = "Data quota exceeded";
+
 pub async fn match_scheduler<M: MatchSource>(settings: &ACConfig, match_source: M) {
     let new_match = match_source.next_match().await.unwrap();
 
@@ -32,22 +50,25 @@ async fn prepare_match(settings: &ACConfig, new_match: Match, map_name: String)
         &new_match.players[&PlayerNum::Two].name
     );
 
-    let mut match_request: MatchRequest = new_match.clone().into();
+    let layout = settings.match_layout();
+    let mut match_request = MatchRequest::new(&new_match, settings);
     match_request.map_name = map_name.clone();
 
-    delete_all_signals(&settings).await;
+    delete_all_signals(&settings, &layout).await;
 
     if !settings.base_website_url.is_empty() {
         if let Err(e) = download_assets(&settings, &new_match, map_name.clone()).await {
-            info!("Match could not be prepared: {:?}", e);
-            let _ = AiArenaGameResult::new_initialization_error(new_match.match_id).to_json_file();
+            error!("Match could not be prepared: {}", e);
+            let _ = AiArenaGameResult::new_initialization_error(new_match.match_id)
+                .to_json_file(&layout);
             return;
         }
     }
 
-    if let Err(e) = match_request.write() {
+    if let Err(e) = match_request.write(&layout) {
         info!("Match request could not be written: {:?}", e);
-        let _ = AiArenaGameResult::new_initialization_error(new_match.match_id).to_json_file();
+        let _ =
+            AiArenaGameResult::new_initialization_error(new_match.match_id).to_json_file(&layout);
     } else {
         info!("Match prepared successfully");
     }
@@ -60,23 +81,54 @@ async fn submit_result<M: MatchSource>(settings: &ACConfig, match_source: M, new
         &new_match.players[&PlayerNum::Two].name
     );
 
-    let aiarena_game_result;
+    let layout = settings.match_layout();
+    let mut aiarena_game_result;
     let start_time = std::time::Instant::now();
-
-    if check_bots_started(settings).await {
+    let events = serve_events(settings.event_port).await;
+    let events = events.as_deref();
+
+    if let Some(result) = check_players_joined(settings, &layout, &new_match, events).await {
+        aiarena_game_result = AiArenaGameResult {
+            result,
+            ..AiArenaGameResult::new_initialization_error(new_match.match_id)
+        };
+    } else {
         info!("Match is running...");
 
         // Wait for the game result as signal for completion of the match
+        let max_real_time = new_match
+            .settings
+            .max_real_time
+            .unwrap_or(settings.max_real_time);
+        let deadline = u64::try_from(max_real_time)
+            .ok()
+            .filter(|secs| *secs > 0)
+            .map(|secs| Duration::from_secs(secs) + RESULT_GRACE_PERIOD);
         loop {
-            if let Ok(result) = AiArenaGameResult::from_json_file() {
+            if let Some(result) = AiArenaGameResult::from_json_file(&layout)
+                .ok()
+                .or_else(|| events.and_then(MatchEvents::game_result))
+            {
                 aiarena_game_result = result;
                 break;
             }
 
-            sleep(Duration::from_secs(3)).await;
+            if deadline.map_or(false, |deadline| start_time.elapsed() > deadline) {
+                error!(
+                    "No match result within the real time limit of {} seconds",
+                    max_real_time
+                );
+                // The result file was checked at the start of this iteration, so there is no
+                // result of the game controller to keep
+                aiarena_game_result = AiArenaGameResult::new_error(new_match.match_id);
+                if let Err(e) = aiarena_game_result.to_json_file(&layout) {
+                    error!("Failed to store match result: {:?}", e);
+                }
+                break;
+            }
+
+            wait_for_event(events, Duration::from_secs(3)).await;
         }
-    } else {
-        aiarena_game_result = AiArenaGameResult::new_initialization_error(new_match.match_id);
     }
 
     info!("Match result: {:?}", &aiarena_game_result);
@@ -87,50 +139,69 @@ async fn submit_result<M: MatchSource>(settings: &ACConfig, match_source: M, new
     if !settings.base_website_url.is_empty() {
         tracing::debug!("Submitting result via AI Arena API");
 
-        check_bots_terminated(&settings).await;
+        check_bots_terminated(&settings, &new_match, events).await;
 
-        logs_and_replays =
-            match build_logs_and_replays_object(&new_match, &new_match.players, &settings).await {
-                Ok(l) => Some(l),
-                Err(err) => {
-                    error!("{:?}", err);
-                    None
-                }
-            };
-    }
-
-    if let Err(e) = match_source
-        .submit_result(&aiarena_game_result, logs_and_replays)
+        logs_and_replays = match build_logs_and_replays_object(
+            &new_match,
+            &new_match.players,
+            &settings,
+            &mut aiarena_game_result,
+        )
         .await
-    {
-        error!("{:?}", e);
+        {
+            Ok(l) => Some(l),
+            Err(err) => {
+                error!("{:?}", err);
+                None
+            }
+        };
     }
 
-    info!("Match result submitted");
-}
+    // Only results for AI Arena are kept in the outbox, local sources record them directly
+    let outbox = Outbox::from_settings(settings)
+        .filter(|_| !settings.base_website_url.is_empty())
+        .filter(
+            |outbox| match outbox.store(&aiarena_game_result, logs_and_replays.as_ref()) {
+                Ok(_) => true,
+                Err(e) => {
+                    error!("Could not store the result in the outbox: {:?}", e);
+                    false
+                }
+            },
+        );
 
-async fn delete_all_signals(settings: &ACConfig) {
-    // Delete any previous match_result.json file
-    AiArenaGameResult::delete_json_file().expect("Failed to delete previous match result");
-
-    // Delete bot 1 signal.exit file if it exists
-    let bot1_signal_exit_path = PathBuf::from(&settings.log_root)
-        .join("bot-controller-1")
-        .join("signal.exit");
-    match tokio::fs::remove_file(&bot1_signal_exit_path).await {
-        Ok(_) => {}
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
-        Err(e) => panic!("Failed to clear signal {:?}: {}", bot1_signal_exit_path, e),
+    if let Some(outbox) = outbox {
+        // Submits the result of this match and the earlier ones that are due for a retry
+        let summary = outbox.flush(&match_source, false).await;
+        info!("Submission outbox flushed: {:?}", summary);
+    } else {
+        if let Err(e) = match_source
+            .submit_result(&aiarena_game_result, logs_and_replays)
+            .await
+        {
+            error!("{:?}", e);
+        }
+        info!("Match result submitted");
     }
+}
 
-    // Delete bot 2 signal.exit file if it exists
-    let bot2_signal_exit_path = PathBuf::from(&settings.log_root)
-        .join("bot-controller-2")
-        .join("signal.exit");
-    match tokio::fs::remove_file(&bot2_signal_exit_path).await {
-        Ok(_) => {}
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
-        Err(e) => panic!("Failed to clear signal {:?}: {}", bot2_signal_exit_path, e),
+async fn delete_all_signals(settings: &ACConfig, layout: &MatchLayout) {
+    // Delete any previous match_result.json file
+    AiArenaGameResult::delete_json_file(layout).expect("Failed to delete previous match result");
+
+    // Delete any previous join_state.json file
+    JoinState::delete_json_file(layout).expect("Failed to delete previous join state");
+
+    // Delete the signal.exit files of the bots if they exist
+    for player_num in [PlayerNum::One, PlayerNum::Two] {
+        let signal_exit_path = settings
+            .bot_controller_layout(player_num)
+            .signal_exit_file();
+        match tokio::fs::remove_file(&signal_exit_path).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => panic!("Failed to clear signal {:?}: {}", signal_exit_path, e),
+        }
     }
 }
 
@@ -140,111 +211,142 @@ async fn download_assets(
     map_name: String,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let arena_match = new_match.aiarena_match.as_ref().unwrap();
+    let cache = AssetCache::from_settings(settings);
     let map_path = PathBuf::from(&settings.game_directory).join(&map_name);
 
     tracing::debug!("Downloading map {:?} to {:?}", map_name, map_path);
 
-    let bytes = download_map(&settings, &arena_match)
-        .await
-        .map_err(|e| format!("{:?}", e))?;
-    let mut file = tokio::fs::File::create(map_path).await?;
-    file.write_all(&bytes).await?;
+    let bytes = fetch_asset(
+        cache.as_ref(),
+        arena_match.map.file_hash.as_deref(),
+        &map_name,
+        download_map(settings, arena_match),
+    )
+    .await?;
+    write_atomically(&map_path, &bytes)?;
 
     tracing::debug!("Downloading bots and bot data");
 
-    let bot1_data = arena_match.bot1.bot_data.as_ref();
-    let bot2_data = arena_match.bot2.bot_data.as_ref();
-
-    let bytes = download_bot(&settings, &arena_match, PlayerNum::One)
-        .await
-        .map_err(|e| format!("{:?}", e))?;
-    let bot_name = &new_match.players[&PlayerNum::One].name;
-    let bot_path = PathBuf::from(&settings.bot_directory)
-        .join("bot1")
-        .join(bot_name);
-    let bot_folder = bot_path.as_path();
-    common::utilities::zip_utils::zip_extract_from_bytes(&bytes, bot_folder)?;
-
-    if bot1_data.map_or(false, |s| !s.is_empty()) {
-        let bytes = download_bot_data(&settings, &arena_match, PlayerNum::One)
-            .await
-            .map_err(|e| format!("{:?}", e))?;
-        let bot_name = &new_match.players[&PlayerNum::One].name;
-        let bot_path = PathBuf::from(&settings.bot_directory)
-            .join("bot1")
-            .join(bot_name)
-            .join("data");
-        let bot_folder = bot_path.as_path();
-        common::utilities::zip_utils::zip_extract_from_bytes(&bytes, bot_folder)?;
-    }
-
-    let bytes = download_bot(&settings, &arena_match, PlayerNum::Two)
-        .await
-        .map_err(|e| format!("{:?}", e))?;
-    let bot_name = &new_match.players[&PlayerNum::Two].name;
-    let bot_path = PathBuf::from(&settings.bot_directory)
-        .join("bot2")
-        .join(bot_name);
-    let bot_folder = bot_path.as_path();
-    common::utilities::zip_utils::zip_extract_from_bytes(&bytes, bot_folder)?;
-
-    if bot2_data.map_or(false, |s| !s.is_empty()) {
-        let bytes = download_bot_data(&settings, &arena_match, PlayerNum::Two)
-            .await
-            .map_err(|e| format!("{:?}", e))?;
-        let bot_name = &new_match.players[&PlayerNum::Two].name;
+    for (player_num, bot, folder) in [
+        (PlayerNum::One, &arena_match.bot1, "bot1"),
+        (PlayerNum::Two, &arena_match.bot2, "bot2"),
+    ] {
+        let bot_name = &new_match.players[&player_num].name;
         let bot_path = PathBuf::from(&settings.bot_directory)
-            .join("bot2")
-            .join(bot_name)
-            .join("data");
-        let bot_folder = bot_path.as_path();
-        common::utilities::zip_utils::zip_extract_from_bytes(&bytes, bot_folder)?;
+            .join(folder)
+            .join(bot_name);
+
+        let bytes = fetch_asset(
+            cache.as_ref(),
+            Some(bot.bot_zip_md5hash.as_str()),
+            bot_name,
+            download_bot(settings, arena_match, player_num),
+        )
+        .await?;
+        extract_atomically(&bytes, &bot_path)?;
+
+        if bot.bot_data.as_ref().is_some_and(|s| !s.is_empty()) {
+            let bytes = fetch_asset(
+                cache.as_ref(),
+                bot.bot_data_md5hash.as_deref(),
+                &format!("{bot_name} data"),
+                download_bot_data(settings, arena_match, player_num),
+            )
+            .await?;
+            extract_atomically(&bytes, &bot_path.join("data"))?;
+        }
     }
 
     Ok(())
 }
 
-async fn check_bots_started(settings: &ACConfig) -> bool {
-    // Check if both bots managed to start
-    // Notice: The 2 seconds sleep is not introduced now. It was previously in bot controller.
-    // In the next iteration, this will be improved by:
-    // - waiting for a signal from game controller that both players joined
-    // - removing the 2 seconds sleep, since the signal from game controller will cover for it
-    // - result in initialization error only when both bots didn't start to prevent cheating
-    sleep(Duration::from_secs(2)).await;
-
-    // Check if bot 1 exited early
-    let bot1_signal_exit_path = PathBuf::from(&settings.log_root)
-        .join("bot-controller-1")
-        .join("signal.exit");
-    if bot1_signal_exit_path.exists() {
-        return false;
+/// Returns the cached asset of the hash, or downloads it and stores it in the cache
+async fn fetch_asset(
+    cache: Option<&AssetCache>,
+    hash: Option<&str>,
+    asset: &str,
+    download: impl Future<Output = Result<Bytes, AppError>>,
+) -> Result<Bytes, Box<dyn std::error::Error>> {
+    let cached = cache.zip(hash.filter(|hash| !hash.is_empty()));
+    if let Some((cache, hash)) = cached {
+        if let Some(bytes) = cache.get(hash) {
+            tracing::debug!("Using cached {}", asset);
+            return Ok(bytes);
+        }
     }
 
-    // Check if bot 2 exited early
-    let bot2_signal_exit_path = PathBuf::from(&settings.log_root)
-        .join("bot-controller-2")
-        .join("signal.exit");
-    if bot2_signal_exit_path.exists() {
-        return false;
+    let bytes = download
+        .await
+        .map_err(|e| format!("Could not download {}: {:?}", asset, e))?;
+    if let Some((cache, hash)) = cached {
+        if let Err(e) = cache.insert(hash, &bytes) {
+            error!("Could not cache {}: {}", asset, e);
+        }
     }
-
-    // No bot exited in 2 seconds. We assume they can join the match.
-    true
+    Ok(bytes)
 }
 
-async fn check_bots_terminated(settings: &ACConfig) {
-    let bot1_signal_exit_path = PathBuf::from(&settings.log_root)
-        .join("bot-controller-1")
-        .join("signal.exit");
-    let bot2_signal_exit_path = PathBuf::from(&settings.log_root)
-        .join("bot-controller-2")
-        .join("signal.exit");
+/// Waits until all players joined the game, a bot exited without joining, or the join timeout
+/// passed. Returns the result of the match if a player did not join.
+async fn check_players_joined(
+    settings: &ACConfig,
+    layout: &MatchLayout,
+    new_match: &Match,
+    events: Option<&MatchEvents>,
+) -> Option<AiArenaResult> {
+    // The built-in AI joins together with player 1
+    let builtin_ai = new_match.players[&PlayerNum::Two].is_builtin_ai();
+    let join_timeout = Duration::from_secs(settings.join_timeout_secs);
+    let start_time = std::time::Instant::now();
+
+    loop {
+        let join_state = JoinState::from_json_file(layout)
+            .ok()
+            .filter(|join_state| join_state.match_id == new_match.match_id);
+        let joined = |player_num| {
+            events.is_some_and(|events| events.player_joined(player_num))
+                || join_state
+                    .as_ref()
+                    .is_some_and(|join_state| join_state.has_joined(player_num))
+        };
+        let bot1_joined = joined(PlayerNum::One);
+        let bot2_joined = builtin_ai || joined(PlayerNum::Two);
+        if bot1_joined && bot2_joined {
+            return None;
+        }
+
+        // A bot that exited before joining won't join anymore
+        let bot1_done = bot1_joined || bot_exited(settings, events, PlayerNum::One);
+        let bot2_done = bot2_joined || bot_exited(settings, events, PlayerNum::Two);
+        let timed_out = start_time.elapsed() >= join_timeout;
+        if timed_out || (bot1_done && bot2_done) {
+            if timed_out {
+                info!(
+                    "Not all players joined within {} seconds",
+                    settings.join_timeout_secs
+                );
+            }
+            return Some(match (bot1_joined, bot2_joined) {
+                (true, false) => AiArenaResult::Player2Crash,
+                (false, true) => AiArenaResult::Player1Crash,
+                _ => AiArenaResult::InitializationError,
+            });
+        }
 
+        wait_for_event(events, Duration::from_secs(1)).await;
+    }
+}
+
+async fn check_bots_terminated(
+    settings: &ACConfig,
+    new_match: &Match,
+    events: Option<&MatchEvents>,
+) {
     let start_time = std::time::Instant::now();
     loop {
-        let bot1_exited = bot1_signal_exit_path.exists();
-        let bot2_exited = bot2_signal_exit_path.exists();
+        let bot1_exited = bot_exited(settings, events, PlayerNum::One);
+        let bot2_exited = new_match.players[&PlayerNum::Two].is_builtin_ai()
+            || bot_exited(settings, events, PlayerNum::Two);
 
         if bot1_exited && bot2_exited {
             return;
@@ -260,7 +362,26 @@ async fn check_bots_terminated(settings: &ACConfig) {
             return;
         }
 
-        sleep(Duration::from_secs(1)).await;
+        wait_for_event(events, Duration::from_secs(1)).await;
+    }
+}
+
+// Checks the exit event of the bot, falling back to the signal file of its bot controller
+fn bot_exited(settings: &ACConfig, events: Option<&MatchEvents>, player_num: PlayerNum) -> bool {
+    if events.is_some_and(|events| events.bot_exited(player_num)) {
+        return true;
+    }
+    settings
+        .bot_controller_layout(player_num)
+        .signal_exit_file()
+        .exists()
+}
+
+// Waits for the next event, or sleeps if there is no event server
+async fn wait_for_event(events: Option<&MatchEvents>, duration: Duration) {
+    match events {
+        Some(events) => events.wait(duration).await,
+        None => sleep(duration).await,
     }
 }
 
@@ -268,6 +389,7 @@ async fn build_logs_and_replays_object(
     the_match: &Match,
     players: &HashMap<PlayerNum, MatchPlayer>,
     settings: &ACConfig,
+    game_result: &mut AiArenaGameResult,
 ) -> io::Result<LogsAndReplays> {
     let bot1_name = players[&PlayerNum::One].name.clone();
     let bot2_name = players[&PlayerNum::Two].name.clone();
@@ -282,44 +404,48 @@ async fn build_logs_and_replays_object(
 
     let _ = tokio::fs::remove_dir_all(&zips_folder).await;
 
+    // Move the protocol recordings into the bot logs, so they are not part of the AC log
+    for (player_num, name) in [(PlayerNum::One, &bot1_name), (PlayerNum::Two, &bot2_name)] {
+        move_protocol_recording(logs_folder, bots_folder, player_num, name).await;
+    }
+
     // Zip the log files of all controllers
-    zip_directory_for_submit("AC", ac_zip_path.to_path_buf(), logs_folder.to_path_buf());
-
-    // Zip the logs and data of bot 1
-    zip_directory_for_submit(
-        "bot1 logs",
-        bot1_zip_dir.join("logs.zip"),
-        bots_folder
-            .join("bot1")
-            .join(bot1_name.clone())
-            .join("logs"),
-    );
-    zip_directory_for_submit(
-        "bot1 data",
-        bot1_zip_dir.join("data.zip"),
-        bots_folder
-            .join("bot1")
-            .join(bot1_name.clone())
-            .join("data"),
+    zip_logs_for_submit(
+        settings,
+        "AC",
+        ac_zip_path.to_path_buf(),
+        logs_folder.to_path_buf(),
     );
 
-    // Zip the logs and data of bot 2
-    zip_directory_for_submit(
-        "bot2 logs",
-        bot2_zip_dir.join("logs.zip"),
-        bots_folder
-            .join("bot2")
-            .join(bot2_name.clone())
-            .join("logs"),
-    );
-    zip_directory_for_submit(
-        "bot2 data",
-        bot2_zip_dir.join("data.zip"),
-        bots_folder
-            .join("bot2")
-            .join(bot2_name.clone())
-            .join("data"),
-    );
+    // Zip the logs and data of the bots
+    for (player_num, folder, name, zip_dir) in [
+        (PlayerNum::One, "bot1", &bot1_name, &bot1_zip_dir),
+        (PlayerNum::Two, "bot2", &bot2_name, &bot2_zip_dir),
+    ] {
+        let bot_folder = bots_folder.join(folder).join(name);
+        zip_logs_for_submit(
+            settings,
+            &format!("{folder} logs"),
+            zip_dir.join("logs.zip"),
+            bot_folder.join("logs"),
+        );
+
+        let data_folder = bot_folder.join("data");
+        let data_bytes = directory_size(&data_folder).unwrap_or(0);
+        if settings.data_quota_bytes > 0 && data_bytes > settings.data_quota_bytes {
+            error!(
+                "Rejecting upload of {} data: {} bytes exceed the quota of {} bytes",
+                folder, data_bytes, settings.data_quota_bytes
+            );
+            game_result.add_tag(player_num, DATA_QUOTA_TAG);
+        } else {
+            zip_directory_for_submit(
+                &format!("{folder} data"),
+                zip_dir.join("data.zip"),
+                data_folder,
+            );
+        }
+    }
 
     let replay_file = Path::new(&settings.game_directory).join(format!(
         "{}_{}_vs_{}.SC2Replay",
@@ -339,15 +465,51 @@ async fn build_logs_and_replays_object(
     })
 }
 
+// Moves the protocol recording of a player from the game controller logs to the bot logs
+async fn move_protocol_recording(
+    logs_folder: &Path,
+    bots_folder: &Path,
+    player_num: PlayerNum,
+    bot_name: &str,
+) {
+    let file_name = MatchRequest::protocol_recording_file(player_num);
+    let recording = logs_folder.join("game_controller").join(&file_name);
+    if !recording.exists() {
+        return;
+    }
+    let bot_folder = match player_num {
+        PlayerNum::One => "bot1",
+        PlayerNum::Two => "bot2",
+    };
+    let bot_logs_folder = bots_folder.join(bot_folder).join(bot_name).join("logs");
+    // The folders can be on different mounts, so the file is copied instead of renamed
+    let result = async {
+        tokio::fs::create_dir_all(&bot_logs_folder).await?;
+        tokio::fs::copy(&recording, bot_logs_folder.join(&file_name)).await?;
+        tokio::fs::remove_file(&recording).await
+    };
+    if let Err(e) = result.await {
+        error!("Failed to move protocol recording {:?}: {:?}", recording, e);
+    }
+}
+
+// Zips the log files of the given directory, truncating the files over the size limit
+fn zip_logs_for_submit(settings: &ACConfig, label: &str, zip_path: PathBuf, directory: PathBuf) {
+    tracing::debug!("Zipping {} logs: {:?} -> {:?}", label, directory, zip_path);
+    if let Err(e) = zip_log_directory(
+        &zip_path,
+        &directory,
+        settings.log_file_max_bytes,
+        settings.log_truncation,
+    ) {
+        error!("Failed to zip {} logs: {:?}", label, e)
+    }
+}
+
 // Zips the contents of the given directory into a zip file with the given zip path
 fn zip_directory_for_submit(label: &str, zip_path: PathBuf, directory: PathBuf) {
-    println!("ZIP {:?}: {:?} -> {:?}", label, directory, zip_path);
-    let result = zip_directory_to_path(&zip_path, &directory);
-
-    match result {
-        Ok(_) => {}
-        Err(e) => {
-            error!("Failed to zip {} logs: {:?}", label, e)
-        }
+    tracing::debug!("Zipping {}: {:?} -> {:?}", label, directory, zip_path);
+    if let Err(e) = zip_directory_to_path(&zip_path, &directory) {
+        error!("Failed to zip {}: {:?}", label, e)
     }
 }
@@ -0,0 +1,425 @@
+//! Results that wait on disk until they are submitted, so they survive restarts of the
+//! match controller. Each match has one entry, with copies of its logs and replay.
+
+use crate::matches::sources::file_source::errors::SubmissionError;
+use crate::matches::sources::{LogsAndReplays, MatchSource};
+use common::configuration::ac_config::ACConfig;
+use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
+use common::utilities::files::{remove_dir_if_exists, write_atomically};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info};
+
+/// The file of an entry with the result and the paths of its artifacts
+pub const SUBMISSION_FILE: &str = "submission.json";
+
+/// The folder of the submissions that the API rejected, kept for inspection
+pub const REJECTED_DIRECTORY: &str = "rejected";
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(10);
+const MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingSubmission {
+    pub game_result: AiArenaGameResult,
+    /// The artifacts, copied into the entry
+    pub logs_and_replays: Option<LogsAndReplays>,
+    pub attempts: u32,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+    /// Seconds since the Unix epoch before which the submission is not retried
+    pub next_attempt_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+impl PendingSubmission {
+    pub fn match_id(&self) -> u32 {
+        self.game_result.match_id
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.next_attempt_at <= now_secs()
+    }
+}
+
+/// Outcome of flushing the outbox
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FlushSummary {
+    pub submitted: usize,
+    pub failed: usize,
+    /// Submissions moved to the rejected folder
+    pub rejected: usize,
+    /// Submissions still waiting for their next attempt
+    pub waiting: usize,
+}
+
+pub struct Outbox {
+    directory: PathBuf,
+}
+
+impl Outbox {
+    /// The outbox of the settings, or None if `submission_outbox_directory` is not set
+    pub fn from_settings(settings: &ACConfig) -> Option<Self> {
+        if settings.submission_outbox_directory.is_empty() {
+            return None;
+        }
+        Some(Self::new(&settings.submission_outbox_directory))
+    }
+
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// Stores the result and copies of its artifacts, replacing a previous entry of the match.
+    /// The entry is due immediately.
+    pub fn store(
+        &self,
+        game_result: &AiArenaGameResult,
+        logs_and_replays: Option<&LogsAndReplays>,
+    ) -> io::Result<PendingSubmission> {
+        let entry_dir = self.entry_dir(game_result.match_id);
+        // The entry is built next to its final folder, so a crash never leaves half an entry
+        let partial_dir = self
+            .directory
+            .join(format!(".{}.partial", game_result.match_id));
+        remove_dir_if_exists(&partial_dir)?;
+        fs::create_dir_all(&partial_dir)?;
+
+        let now = now_secs();
+        let submission = PendingSubmission {
+            game_result: game_result.clone(),
+            logs_and_replays: logs_and_replays
+                .map(|l| spool_artifacts(l, &partial_dir, &entry_dir))
+                .transpose()?,
+            attempts: 0,
+            created_at: now,
+            next_attempt_at: now,
+            last_error: None,
+        };
+        write_submission(&partial_dir, &submission)?;
+
+        remove_dir_if_exists(&entry_dir)?;
+        fs::rename(&partial_dir, &entry_dir)?;
+        Ok(submission)
+    }
+
+    /// The pending submissions, oldest first
+    pub fn pending(&self) -> io::Result<Vec<PendingSubmission>> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut submissions = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            // Entries that are still being stored start with a dot
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path().join(SUBMISSION_FILE);
+            if !path.exists() {
+                continue;
+            }
+            match fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|e| e.to_string()))
+            {
+                Ok(submission) => submissions.push(submission),
+                Err(e) => error!("Skipping unreadable submission {:?}: {}", path, e),
+            }
+        }
+        submissions.sort_by_key(|s: &PendingSubmission| (s.created_at, s.match_id()));
+        Ok(submissions)
+    }
+
+    pub fn remove(&self, match_id: u32) -> io::Result<()> {
+        remove_dir_if_exists(&self.entry_dir(match_id))
+    }
+
+    /// Moves the entry to the rejected folder, replacing an earlier rejected entry of the match
+    pub fn reject(&self, match_id: u32) -> io::Result<()> {
+        let rejected_dir = self.directory.join(REJECTED_DIRECTORY);
+        fs::create_dir_all(&rejected_dir)?;
+        let target = rejected_dir.join(match_id.to_string());
+        remove_dir_if_exists(&target)?;
+        fs::rename(self.entry_dir(match_id), target)
+    }
+
+    /// Counts the failed attempt and schedules the next one with an exponential backoff
+    pub fn record_failure(
+        &self,
+        submission: &mut PendingSubmission,
+        error: String,
+    ) -> io::Result<()> {
+        submission.attempts += 1;
+        submission.next_attempt_at = now_secs() + backoff(submission.attempts).as_secs();
+        submission.last_error = Some(error);
+        write_submission(&self.entry_dir(submission.match_id()), submission)
+    }
+
+    /// Submits the due submissions, or all of them if `force` is set, once each. The submitted
+    /// ones are removed and the rejected ones moved to the rejected folder.
+    pub async fn flush<M: MatchSource + ?Sized>(&self, source: &M, force: bool) -> FlushSummary {
+        let mut summary = FlushSummary::default();
+        let submissions = match self.pending() {
+            Ok(submissions) => submissions,
+            Err(e) => {
+                error!("Could not read the submission outbox: {:?}", e);
+                return summary;
+            }
+        };
+
+        for mut submission in submissions {
+            if !force && !submission.is_due() {
+                summary.waiting += 1;
+                continue;
+            }
+            let match_id = submission.match_id();
+            info!(
+                "Submitting result of match {} (attempt {})",
+                match_id,
+                submission.attempts + 1
+            );
+            match source
+                .submit_result(&submission.game_result, submission.logs_and_replays.clone())
+                .await
+            {
+                Ok(()) => {
+                    summary.submitted += 1;
+                    if let Err(e) = self.remove(match_id) {
+                        error!("Could not remove submission of match {}: {:?}", match_id, e);
+                    }
+                }
+                Err(e @ SubmissionError::Rejected(_)) => {
+                    summary.rejected += 1;
+                    error!("Submission of match {} was rejected: {}", match_id, e);
+                    if let Err(e) = self.reject(match_id) {
+                        error!("Could not move submission of match {}: {:?}", match_id, e);
+                    }
+                }
+                Err(e) => {
+                    summary.failed += 1;
+                    error!("Submission of match {} failed: {}", match_id, e);
+                    if let Err(e) = self.record_failure(&mut submission, e.to_string()) {
+                        error!("Could not update submission of match {}: {:?}", match_id, e);
+                    }
+                }
+            }
+        }
+        summary
+    }
+
+    fn entry_dir(&self, match_id: u32) -> PathBuf {
+        self.directory.join(match_id.to_string())
+    }
+}
+
+/// Time before the next attempt after the given number of failed attempts
+pub fn backoff(attempts: u32) -> Duration {
+    let doublings = attempts.saturating_sub(1).min(16);
+    (INITIAL_BACKOFF * 2u32.pow(doublings)).min(MAX_BACKOFF)
+}
+
+/// Copies the artifacts into the folder that is built, returning their paths in the final entry
+fn spool_artifacts(
+    logs_and_replays: &LogsAndReplays,
+    partial_dir: &Path,
+    entry_dir: &Path,
+) -> io::Result<LogsAndReplays> {
+    let copy = |source: &Path, relative_path: &Path| -> io::Result<PathBuf> {
+        if source.exists() {
+            let target = partial_dir.join(relative_path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(source, target)?;
+        }
+        Ok(entry_dir.join(relative_path))
+    };
+    let bot_dir = |source: &Path, folder: &str| -> io::Result<PathBuf> {
+        for file_name in ["data.zip", "logs.zip"] {
+            copy(&source.join(file_name), &Path::new(folder).join(file_name))?;
+        }
+        Ok(entry_dir.join(folder))
+    };
+    let replay_name = logs_and_replays
+        .replay_file
+        .file_name()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("replay.SC2Replay"));
+
+    Ok(LogsAndReplays {
+        upload_url: logs_and_replays.upload_url.clone(),
+        bot1_name: logs_and_replays.bot1_name.clone(),
+        bot2_name: logs_and_replays.bot2_name.clone(),
+        bot1_dir: bot_dir(&logs_and_replays.bot1_dir, "bot1")?,
+        bot2_dir: bot_dir(&logs_and_replays.bot2_dir, "bot2")?,
+        arenaclient_log: copy(&logs_and_replays.arenaclient_log, Path::new("ac_log.zip"))?,
+        replay_file: copy(&logs_and_replays.replay_file, &replay_name)?,
+    })
+}
+
+fn write_submission(entry_dir: &Path, submission: &PendingSubmission) -> io::Result<()> {
+    let json = serde_json::to_vec_pretty(submission)?;
+    write_atomically(&entry_dir.join(SUBMISSION_FILE), &json)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use common::models::aiarena::aiarena_match::Match;
+    use parking_lot::Mutex;
+
+    /// A source that fails its first submissions and records the submitted logs
+    struct FlakySource {
+        rejects: bool,
+        failures: Mutex<u32>,
+        submitted: Mutex<Vec<Option<LogsAndReplays>>>,
+    }
+
+    #[async_trait]
+    impl MatchSource for FlakySource {
+        async fn has_next(&self) -> bool {
+            false
+        }
+
+        async fn next_match(&self) -> Option<Match> {
+            None
+        }
+
+        async fn submit_result(
+            &self,
+            _game_result: &AiArenaGameResult,
+            logs_and_replays: Option<LogsAndReplays>,
+        ) -> Result<(), SubmissionError> {
+            let mut failures = self.failures.lock();
+            if self.rejects {
+                return Err(SubmissionError::Rejected("400 Bad Request".to_string()));
+            }
+            if *failures > 0 {
+                *failures -= 1;
+                return Err(SubmissionError::Upload("Server unavailable".to_string()));
+            }
+            self.submitted.lock().push(logs_and_replays);
+            Ok(())
+        }
+    }
+
+    fn logs_and_replays(folder: &Path) -> LogsAndReplays {
+        fs::create_dir_all(folder.join("zips/bot1")).unwrap();
+        fs::write(folder.join("zips/bot1/logs.zip"), b"bot1 logs").unwrap();
+        fs::write(folder.join("zips/ac_log.zip"), b"ac log").unwrap();
+        LogsAndReplays {
+            upload_url: "http://cache/upload".to_string(),
+            bot1_name: "bot1".to_string(),
+            bot2_name: "bot2".to_string(),
+            bot1_dir: folder.join("zips/bot1"),
+            bot2_dir: folder.join("zips/bot2"),
+            arenaclient_log: folder.join("zips/ac_log.zip"),
+            replay_file: folder.join("7_bot1_vs_bot2.SC2Replay"),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_maximum() {
+        assert_eq!(backoff(1), Duration::from_secs(10));
+        assert_eq!(backoff(2), Duration::from_secs(20));
+        assert_eq!(backoff(4), Duration::from_secs(80));
+        assert_eq!(backoff(20), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_store_copies_the_artifacts_once_per_match() {
+        let logs = tempfile::tempdir().unwrap();
+        let spool = tempfile::tempdir().unwrap();
+        let outbox = Outbox::new(spool.path());
+        let artifacts = logs_and_replays(logs.path());
+
+        outbox
+            .store(&AiArenaGameResult::new_error(7), Some(&artifacts))
+            .unwrap();
+        let submission = outbox
+            .store(&AiArenaGameResult::new_error(7), Some(&artifacts))
+            .unwrap();
+        // The artifacts of the next match replace the originals
+        fs::remove_dir_all(logs.path().join("zips")).unwrap();
+
+        assert_eq!(outbox.pending().unwrap().len(), 1);
+        let spooled = submission.logs_and_replays.unwrap();
+        assert!(spooled.bot1_dir.starts_with(spool.path()));
+        assert_eq!(
+            fs::read(spooled.bot1_dir.join("logs.zip")).unwrap(),
+            b"bot1 logs"
+        );
+        assert_eq!(fs::read(spooled.arenaclient_log).unwrap(), b"ac log");
+        assert!(!spooled.bot2_dir.join("logs.zip").exists());
+        assert!(!spooled.replay_file.exists());
+    }
+
+    #[tokio::test]
+    async fn test_flush_retries_with_backoff_until_submitted() {
+        let spool = tempfile::tempdir().unwrap();
+        let outbox = Outbox::new(spool.path());
+        let source = FlakySource {
+            rejects: false,
+            failures: Mutex::new(1),
+            submitted: Mutex::new(Vec::new()),
+        };
+        outbox
+            .store(&AiArenaGameResult::new_error(3), None)
+            .unwrap();
+
+        let summary = outbox.flush(&source, false).await;
+        assert_eq!(summary.failed, 1);
+        let pending = outbox.pending().unwrap();
+        assert_eq!(pending[0].attempts, 1);
+        assert!(!pending[0].is_due());
+        assert!(pending[0].last_error.is_some());
+
+        let summary = outbox.flush(&source, false).await;
+        assert_eq!(summary.waiting, 1);
+
+        let summary = outbox.flush(&source, true).await;
+        assert_eq!(summary.submitted, 1);
+        assert!(outbox.pending().unwrap().is_empty());
+        assert_eq!(source.submitted.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_flush_moves_rejected_submissions_aside() {
+        let spool = tempfile::tempdir().unwrap();
+        let outbox = Outbox::new(spool.path());
+        let source = FlakySource {
+            rejects: true,
+            failures: Mutex::new(0),
+            submitted: Mutex::new(Vec::new()),
+        };
+        outbox
+            .store(&AiArenaGameResult::new_error(4), None)
+            .unwrap();
+
+        let summary = outbox.flush(&source, false).await;
+        assert_eq!(summary.rejected, 1);
+        assert!(outbox.pending().unwrap().is_empty());
+        assert!(spool
+            .path()
+            .join(REJECTED_DIRECTORY)
+            .join("4")
+            .join(SUBMISSION_FILE)
+            .exists());
+    }
+}
@@ -4,6 +4,7 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use tokio::time::Duration;
+use tokio_util::io::ReaderStream;
 use tracing::{error, info};
 
 #[derive(Debug, Serialize)]
@@ -206,11 +207,16 @@ async fn upload_file(website_url: &str, token: &str, file_path: &Path) -> anyhow
     let upload_id = entry.upload.id;
     let upload_url = entry.upload_url;
 
-    // Step 2: Upload the file to the signed S3 URL
-    let file_bytes = tokio::fs::read(file_path)
+    // Step 2: Stream the file to the signed S3 URL
+    let file = tokio::fs::File::open(file_path)
         .await
-        .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
-    let file_size_kb = file_bytes.len() / 1024;
+        .with_context(|| format!("Failed to open file: {}", file_path.display()))?;
+    let file_size = file
+        .metadata()
+        .await
+        .with_context(|| format!("Failed to read metadata of file: {}", file_path.display()))?
+        .len();
+    let file_size_kb = file_size / 1024;
 
     info!(
         "Uploading {} ({} KB) -> {}",
@@ -220,7 +226,9 @@ async fn upload_file(website_url: &str, token: &str, file_path: &Path) -> anyhow
     );
     client
         .put(&upload_url)
-        .body(file_bytes)
+        // Signed S3 URLs do not accept chunked uploads, so the length is sent up front
+        .header(reqwest::header::CONTENT_LENGTH, file_size)
+        .body(reqwest::Body::wrap_stream(ReaderStream::new(file)))
         .send()
         .await
         .context("Failed to upload file to S3")?
@@ -307,6 +315,14 @@ async fn submit_result(
             .iter()
             .map(|e| format!("{}: {}", e.field, e.messages.join(", ")))
             .collect();
+        // A retried submission whose earlier reply was lost is already stored
+        if msgs
+            .iter()
+            .any(|msg| msg.to_lowercase().contains("already exists"))
+        {
+            info!("Result was already submitted: {}", msgs.join("; "));
+            return Ok(String::new());
+        }
         return Err(anyhow!("submitResult errors: {}", msgs.join("; ")));
     }
 
@@ -5,24 +5,35 @@ use crate::matches::sources::{LogsAndReplays, MatchSource};
 use async_trait::async_trait;
 use common::api::api_reference::aiarena::aiarena_api_client::AiArenaApiClient;
 use common::api::api_reference::aiarena::errors::AiArenaApiError;
-use common::api::api_reference::aiarena::{create_part_from_bytes, AiArenaResultForm};
+use common::api::api_reference::aiarena::{create_part_from_path, AiArenaResultForm};
 use common::api::api_reference::ApiError;
 use common::configuration::ac_config::ACConfig;
 use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
 use common::models::aiarena::aiarena_match::{AiArenaMatch, Match};
 use common::paths::base_dir;
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use std::fs::{File, OpenOptions};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::io::AsyncWriteExt;
 use tracing::log::error;
 use tracing::{debug, info};
 
+// Attempts of a submission to the legacy API without the submission outbox
+const LEGACY_ATTEMPTS: u32 = 60;
+// Attempts of the GraphQL uploads and submission without the submission outbox
+// TODO: Increase retries to 60 before old API is retired
+const GRAPHQL_ATTEMPTS: u32 = 3;
+
 pub struct HttpApiSource {
     api: AiArenaApiClient,
     website_url: String,
     token: String,
+    upload_max_bytes: u64,
+    /// The submission outbox retries failed submissions itself,
+    /// so each of its submissions is only tried once and rejected results are set aside.
+    outbox: bool,
 }
 
 impl HttpApiSource {
@@ -41,6 +52,8 @@ impl HttpApiSource {
             api,
             website_url: settings.base_website_url.clone(),
             token: api_token.clone(),
+            upload_max_bytes: settings.upload_max_bytes,
+            outbox: !settings.submission_outbox_directory.is_empty(),
         })
     }
     async fn download_map(
@@ -57,15 +70,49 @@ impl HttpApiSource {
         Ok(file.write_all(&map_bytes).await?)
     }
 
+    /// Whether the file exists and is within the upload limit
+    fn can_upload(&self, path: &Path) -> bool {
+        match std::fs::metadata(path) {
+            Ok(metadata) if self.upload_max_bytes > 0 && metadata.len() > self.upload_max_bytes => {
+                error!(
+                    "Not uploading {}: {} bytes exceed the limit of {} bytes",
+                    path.display(),
+                    metadata.len(),
+                    self.upload_max_bytes
+                );
+                false
+            }
+            Ok(_) => true,
+            Err(_) => false,
+        }
+    }
+
+    /// Attempts of each submission
+    fn attempts(&self) -> u32 {
+        if self.outbox {
+            1
+        } else {
+            LEGACY_ATTEMPTS
+        }
+    }
+
+    fn graphql_attempts(&self) -> u32 {
+        GRAPHQL_ATTEMPTS.min(self.attempts())
+    }
+
     async fn upload_file(&self, path: &PathBuf) -> Result<String, SubmissionError> {
-        if path.exists() {
-            // TODO: Increase retries to 60 before old API is retired
-            graphql::upload_file_with_retries(&self.website_url, &self.token, path, 3)
-                .await
-                .map_err(|e| {
-                    error!("Failed to upload {}: {}", path.display(), e);
-                    SubmissionError::LogsAndReplaysNull
-                })
+        if self.can_upload(path) {
+            graphql::upload_file_with_retries(
+                &self.website_url,
+                &self.token,
+                path,
+                self.graphql_attempts(),
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to upload {}: {}", path.display(), e);
+                SubmissionError::LogsAndReplaysNull
+            })
         } else {
             Ok(String::new())
         }
@@ -110,13 +157,17 @@ impl HttpApiSource {
             bot2_log: bot2_log_id,
         };
 
-        // TODO: Increase retries to 60 before old API is retired
-        graphql::submit_result_with_retries(&self.website_url, &self.token, &input, 3)
-            .await
-            .map_err(|e| {
-                error!("Failed to submit result via GraphQL: {}", e);
-                SubmissionError::LogsAndReplaysNull
-            })?;
+        graphql::submit_result_with_retries(
+            &self.website_url,
+            &self.token,
+            &input,
+            self.graphql_attempts(),
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to submit result via GraphQL: {}", e);
+            SubmissionError::LogsAndReplaysNull
+        })?;
 
         Ok(())
     }
@@ -158,31 +209,18 @@ impl MatchSource for HttpApiSource {
 
         let mut attempt = 0;
 
-        let bot1_data = get_file_and_filename(&bot1_dir.join("data.zip")).await;
-        let bot2_data = get_file_and_filename(&bot2_dir.join("data.zip")).await;
-
-        let bot1_log = get_file_and_filename(&bot1_dir.join("logs.zip")).await;
-        let bot2_log = get_file_and_filename(&bot2_dir.join("logs.zip")).await;
-
-        let replay = get_file_and_filename(&replay_file).await;
-        let arenaclient_logs = get_file_and_filename(&arenaclient_log).await;
-
-        if let Ok(ref x) = bot1_data {
-            if let Err(e) = self
-                .api
-                .cache_upload(&upload_url, format!("{}_data", bot1_name), &x.0)
-                .await
-            {
-                error!("Error uploading to cache server: {}", e);
-            }
-        }
-        if let Ok(ref x) = bot2_data {
-            if let Err(e) = self
-                .api
-                .cache_upload(&upload_url, format!("{}_data", bot2_name), &x.0)
-                .await
-            {
-                error!("Error uploading to cache server: {}", e);
+        for (bot_name, data) in [
+            (&bot1_name, bot1_dir.join("data.zip")),
+            (&bot2_name, bot2_dir.join("data.zip")),
+        ] {
+            if self.can_upload(&data) {
+                if let Err(e) = self
+                    .api
+                    .cache_upload(&upload_url, format!("{}_data", bot_name), &data)
+                    .await
+                {
+                    error!("Error uploading to cache server: {}", e);
+                }
             }
         }
 
@@ -207,60 +245,64 @@ impl MatchSource for HttpApiSource {
             Err(e) => error!("GraphQL submission failed: {:?}", e),
         }
 
-        while attempt < 60 {
+        // The files are streamed from disk on every attempt
+        let files: Vec<(&str, PathBuf)> = [
+            ("bot1_data", bot1_dir.join("data.zip")),
+            ("bot2_data", bot2_dir.join("data.zip")),
+            ("bot1_log", bot1_dir.join("logs.zip")),
+            ("bot2_log", bot2_dir.join("logs.zip")),
+            ("replay_file", replay_file),
+            ("arenaclient_log", arenaclient_log),
+        ]
+        .into_iter()
+        .filter(|(_, path)| self.can_upload(path))
+        .collect();
+
+        while attempt < self.attempts() {
             debug!("Attempting to submit result. Attempt number: {}", attempt);
 
             let mut form = AiArenaResultForm::from(game_result).to_inner();
-            if let Ok(ref x) = bot1_data {
-                form = form.part(
-                    "bot1_data",
-                    create_part_from_bytes(x.0.clone(), x.1.clone()),
-                );
-            }
-            if let Ok(ref x) = bot2_data {
-                form = form.part(
-                    "bot2_data",
-                    create_part_from_bytes(x.0.clone(), x.1.clone()),
-                );
-            }
-            if let Ok(ref x) = bot1_log {
-                form = form.part("bot1_log", create_part_from_bytes(x.0.clone(), x.1.clone()));
-            }
-            if let Ok(ref x) = bot2_log {
-                form = form.part("bot2_log", create_part_from_bytes(x.0.clone(), x.1.clone()));
-            }
-            if let Ok(ref x) = replay {
-                form = form.part(
-                    "replay_file",
-                    create_part_from_bytes(x.0.clone(), x.1.clone()),
-                );
-            }
-            if let Ok(ref x) = arenaclient_logs {
-                form = form.part(
-                    "arenaclient_log",
-                    create_part_from_bytes(x.0.clone(), x.1.clone()),
-                );
+            for (field, path) in &files {
+                match create_part_from_path(path).await {
+                    Ok(part) => form = form.part(*field, part),
+                    Err(e) => error!("Failed to read {}: {}", path.display(), e),
+                }
             }
 
             info!("{:?}", game_result);
-            let status_result = self.api.submit_result(form).await;
-
-            if status_result.is_err()
-                || (status_result.as_ref().unwrap().is_client_error()
-                    || status_result.as_ref().unwrap().is_server_error())
-            {
-                debug!("Error while submitting result. Sleeping...");
-                error!("{:?}", status_result);
-                attempt += 1;
+            // A result that already exists is reported as success by the API client
+            match self.api.submit_result(form).await {
+                Ok(status) if self.outbox && is_rejected(status) => {
+                    return Err(SubmissionError::Rejected(status.to_string()));
+                }
+                Ok(status) if !status.is_client_error() && !status.is_server_error() => {
+                    return Ok(());
+                }
+                status_result => {
+                    debug!("Error while submitting result. Sleeping...");
+                    error!("{:?}", status_result);
+                }
+            }
+            attempt += 1;
+            if attempt < self.attempts() {
                 tokio::time::sleep(Duration::from_secs(10)).await;
-            } else {
-                break;
             }
         }
-        Ok(())
+        Err(SubmissionError::Upload(format!(
+            "Result was not accepted after {attempt} attempts"
+        )))
     }
 }
 
+/// Whether the API refused the result for good, so submitting it again can't succeed.
+/// Authentication and routing errors can be fixed by the operator, so they are retried.
+fn is_rejected(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_REQUEST | StatusCode::CONFLICT | StatusCode::UNPROCESSABLE_ENTITY
+    )
+}
+
 fn open_results_file(results_file_path: &str) -> Result<File, SubmissionError> {
     let results_file_path = std::path::Path::new(results_file_path);
 
@@ -277,9 +319,17 @@ struct Results {
     results: Vec<AiArenaGameResult>,
 }
 
-pub async fn get_file_and_filename(path: &PathBuf) -> Result<(Vec<u8>, String), std::io::Error> {
-    let file_name = String::from(path.file_name().and_then(|p| p.to_str()).unwrap());
-    let file = tokio::fs::read(path).await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok((file, file_name))
+    #[test]
+    fn test_only_permanent_errors_are_rejected() {
+        for status in [400, 409, 422] {
+            assert!(is_rejected(StatusCode::from_u16(status).unwrap()));
+        }
+        for status in [401, 403, 404, 408, 429, 500, 503] {
+            assert!(!is_rejected(StatusCode::from_u16(status).unwrap()));
+        }
+    }
 }
@@ -10,6 +10,7 @@ pub enum FileMatchExtractError {
     MapName(Vec<String>),
     TooManyFields(Vec<String>),
     MissingFields(Vec<String>),
+    BuiltinAiPlayer1(Vec<String>),
 }
 
 impl Display for FileMatchExtractError {
@@ -22,6 +23,7 @@ impl Display for FileMatchExtractError {
             Self::TooManyFields(vec) => ("Too many fields in line", vec),
             Self::MissingFields(vec) => ("Not enough fields in line", vec),
             Self::PlayerType(vec) => ("Could not extract player 1 type", vec),
+            Self::BuiltinAiPlayer1(vec) => ("Built-in AI is only supported as player 2", vec),
         };
         write!(f, "{e} in {vec:?}")
     }
@@ -45,6 +47,8 @@ pub enum SubmissionError {
     Truncate(std::io::Error),
     Seek(std::io::Error),
     LogsAndReplaysNull,
+    Upload(String),
+    Rejected(String),
 }
 
 impl Display for SubmissionError {
@@ -61,6 +65,8 @@ impl Display for SubmissionError {
                 "Error while reading LogsAndReplays Struct",
                 "NULL".to_string(),
             ),
+            SubmissionError::Upload(e) => ("Error while uploading results", e.clone()),
+            SubmissionError::Rejected(status) => ("Results were rejected", status.clone()),
         };
         write!(f, "{explanation:?}: {error:?}")
     }
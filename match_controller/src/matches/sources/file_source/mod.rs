@@ -5,9 +5,11 @@ use crate::matches::sources::{LogsAndReplays, MatchSource};
 use async_trait::async_trait;
 use common::configuration::ac_config::ACConfig;
 use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
-use common::models::aiarena::aiarena_match::{Match, MatchPlayer};
+use common::models::aiarena::aiarena_match::{Match, MatchPlayer, MatchSettings};
+use common::models::aiarena::builtin_ai::BUILTIN_AI_PREFIX;
+use common::models::aiarena::local_game_result::{LocalGameResult, LocalResults};
 use common::PlayerNum;
-use serde::{Deserialize, Serialize};
+use parking_lot::RwLock;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
@@ -16,11 +18,15 @@ use tracing::log::error;
 
 pub struct FileSource {
     settings: ACConfig,
+    current_match: RwLock<Option<Match>>,
 }
 
 impl FileSource {
     pub const fn new(settings: ACConfig) -> Self {
-        Self { settings }
+        Self {
+            settings,
+            current_match: RwLock::new(None),
+        }
     }
     fn update_matches_file(&self) -> Result<(), SubmissionError> {
         if let Ok(lines) = self.read_matches_file() {
@@ -41,7 +47,7 @@ impl FileSource {
         Ok(())
     }
     fn update_results_file(
-        game_result: &AiArenaGameResult,
+        game_result: LocalGameResult,
         results_file_path: &str,
     ) -> Result<(), SubmissionError> {
         let mut results_file = open_results_file(results_file_path)?;
@@ -50,11 +56,11 @@ impl FileSource {
             .read_to_end(&mut bytes)
             .map_err(SubmissionError::FileRead)?;
 
-        let mut results = match serde_json::from_slice::<Results>(&bytes) {
+        let mut results = match serde_json::from_slice::<LocalResults>(&bytes) {
             Ok(r) => r,
-            Err(_) => Results::default(),
+            Err(_) => LocalResults::default(),
         };
-        results.results.push(game_result.clone());
+        results.results.push(game_result);
         results_file.set_len(0).map_err(SubmissionError::Truncate)?;
         results_file.rewind().map_err(SubmissionError::Seek)?;
         results_file
@@ -72,15 +78,15 @@ impl FileSource {
                 .map_err(SubmissionError::FileRead)
                 .unwrap_or(0);
 
-            let results = match serde_json::from_slice::<Results>(&bytes) {
+            let results = match serde_json::from_slice::<LocalResults>(&bytes) {
                 Ok(r) => r,
-                Err(_) => Results::default(),
+                Err(_) => LocalResults::default(),
             };
             return results
                 .results
                 .iter()
-                .max_by(|x, y| x.match_id.cmp(&y.match_id))
-                .map_or(0, |x| x.match_id);
+                .max_by(|x, y| x.game_result.match_id.cmp(&y.game_result.match_id))
+                .map_or(0, |x| x.game_result.match_id);
         }
 
         0u32
@@ -111,8 +117,10 @@ impl MatchSource for FileSource {
                 if !line.is_empty() && !line.starts_with('#') {
                     return match extract_match(&line) {
                         Ok(mut m) => {
-                            m.match_id =
-                                Self::get_current_match_id(&self.settings.results_file) + 1;
+                            m.match_id = Self::get_current_match_id(&self.settings.results_file)
+                                .max(self.settings.match_id_offset)
+                                + 1;
+                            *self.current_match.write() = Some(m.clone());
                             Some(m)
                         }
                         Err(e) => {
@@ -134,7 +142,8 @@ impl MatchSource for FileSource {
     ) -> Result<(), SubmissionError> {
         //TODO: logs
 
-        Self::update_results_file(game_result, &self.settings.results_file)?;
+        let local_result = LocalGameResult::new(game_result, self.current_match.read().as_ref());
+        Self::update_results_file(local_result, &self.settings.results_file)?;
         self.update_matches_file()?;
 
         Ok(())
@@ -152,18 +161,15 @@ pub fn open_results_file(results_file_path: &str) -> Result<File, SubmissionErro
         .map_err(SubmissionError::FileOpen)
 }
 
-#[derive(Deserialize, Serialize, Default, Debug)]
-struct Results {
-    results: Vec<AiArenaGameResult>,
-}
-
 fn extract_match(line: &str) -> Result<Match, FileMatchExtractError> {
     let mut vec_line: Vec<String> = line
         .split(',')
         .map(std::string::ToString::to_string)
         .collect();
 
-    match vec_line.len().cmp(&9) {
+    let (bot1_len, bot2_len) = player_slot_lens(&vec_line)?;
+
+    match vec_line.len().cmp(&(bot1_len + bot2_len + 1)) {
         Ordering::Greater => {
             return Err(FileMatchExtractError::TooManyFields(vec_line));
         }
@@ -173,8 +179,8 @@ fn extract_match(line: &str) -> Result<Match, FileMatchExtractError> {
         Ordering::Equal => {}
     }
 
-    let bot1: Vec<String> = vec_line.drain(0..4).collect();
-    let bot2: Vec<String> = vec_line.drain(0..4).collect();
+    let bot1: Vec<String> = vec_line.drain(0..bot1_len).collect();
+    let bot2: Vec<String> = vec_line.drain(0..bot2_len).collect();
     let map_name = vec_line
         .pop()
         .ok_or_else(|| FileMatchExtractError::MapName(vec_line.clone()))?;
@@ -188,10 +194,28 @@ fn extract_match(line: &str) -> Result<Match, FileMatchExtractError> {
         match_id: 0,
         players,
         map_name,
+        settings: MatchSettings::default(),
         aiarena_match: None,
     })
 }
 
+/// Number of fields of both player slots at the start of the line.
+/// A bot takes four fields, the built-in AI a single field (e.g. `builtin:VeryHard:Zerg:Macro`).
+pub fn player_slot_lens(fields: &[String]) -> Result<(usize, usize), FileMatchExtractError> {
+    let slot_len = |fields: &[String]| match fields.first() {
+        Some(field) if field.starts_with(BUILTIN_AI_PREFIX) => 1,
+        _ => 4,
+    };
+
+    let bot1_len = slot_len(fields);
+    if bot1_len == 1 {
+        // Player 1 creates the game, so it has to be a bot
+        return Err(FileMatchExtractError::BuiltinAiPlayer1(fields.to_vec()));
+    }
+    let bot2_len = slot_len(fields.get(bot1_len..).unwrap_or(&[]));
+    Ok((bot1_len, bot2_len))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::matches::sources::file_source::{extract_match, FileMatchExtractError};
@@ -230,4 +254,26 @@ mod tests {
         let m_err = m.err().unwrap();
         assert!(matches!(m_err, FileMatchExtractError::TooManyFields { .. }));
     }
+
+    #[test]
+    pub fn test_match_extracts_builtin_ai() {
+        let m =
+            extract_match("bot-id-1,basic_bot,T,python,builtin:VeryHard:Zerg:Macro,AutomatonLE");
+        assert!(m.is_ok());
+        let m = m.unwrap();
+        assert!(!m.players[&PlayerNum::One].is_builtin_ai());
+        assert!(m.players[&PlayerNum::Two].is_builtin_ai());
+        assert_eq!(m.players[&PlayerNum::Two].race, BotRace::Zerg);
+        assert_eq!(m.map_name, "AutomatonLE");
+    }
+
+    #[test]
+    pub fn test_match_extracts_invalid_builtin_ai_player1() {
+        let m =
+            extract_match("builtin:VeryHard:Zerg:Macro,bot-id-2,loser_bot,P,python,AutomatonLE");
+        assert!(matches!(
+            m.err().unwrap(),
+            FileMatchExtractError::BuiltinAiPlayer1 { .. }
+        ));
+    }
 }
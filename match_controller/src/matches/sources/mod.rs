@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
 use common::models::aiarena::aiarena_match::Match;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 pub mod aiarena_api;
@@ -40,7 +41,7 @@ impl<T: MatchSource + ?Sized> MatchSource for Box<T> {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LogsAndReplays {
     pub upload_url: String,
     pub bot1_name: String,
@@ -1,14 +1,14 @@
 use crate::matches::sources::file_source::errors::{FileMatchExtractError, SubmissionError};
-use crate::matches::sources::file_source::open_results_file;
+use crate::matches::sources::file_source::{open_results_file, player_slot_lens};
 use crate::matches::sources::{LogsAndReplays, MatchSource};
 use async_trait::async_trait;
 use common::configuration::ac_config::ACConfig;
 use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
-use common::models::aiarena::aiarena_match::{Match, MatchPlayer};
+use common::models::aiarena::aiarena_match::{Match, MatchPlayer, MatchSettings};
 use common::models::aiarena::aiarena_result::AiArenaResult;
+use common::models::aiarena::local_game_result::{LocalGameResult, LocalResults};
 use common::PlayerNum;
 use parking_lot::RwLock;
-use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fs::File;
@@ -20,6 +20,7 @@ use tracing::error;
 pub struct TestSource {
     settings: ACConfig,
     expected_result: RwLock<Option<AiArenaResult>>,
+    current_match: RwLock<Option<Match>>,
 }
 
 impl TestSource {
@@ -27,6 +28,7 @@ impl TestSource {
         Self {
             settings,
             expected_result: RwLock::new(None),
+            current_match: RwLock::new(None),
         }
     }
     fn update_matches_file(&self) -> Result<(), SubmissionError> {
@@ -48,7 +50,7 @@ impl TestSource {
         Ok(())
     }
     fn update_results_file(
-        game_result: &AiArenaGameResult,
+        game_result: LocalGameResult,
         results_file_path: &str,
     ) -> Result<(), SubmissionError> {
         let mut results_file = open_results_file(results_file_path)?;
@@ -57,14 +59,14 @@ impl TestSource {
             .read_to_end(&mut bytes)
             .map_err(SubmissionError::FileRead)?;
 
-        let mut results = match serde_json::from_slice::<Results>(&bytes) {
+        let mut results = match serde_json::from_slice::<LocalResults>(&bytes) {
             Ok(r) => r,
             Err(e) => {
                 debug!("{:?}", e);
-                Results::default()
+                LocalResults::default()
             }
         };
-        results.results.push(game_result.clone());
+        results.results.push(game_result);
         results_file.set_len(0).map_err(SubmissionError::Truncate)?;
         results_file.rewind().map_err(SubmissionError::Seek)?;
         results_file
@@ -82,15 +84,15 @@ impl TestSource {
                 .map_err(SubmissionError::FileRead)
                 .unwrap_or(0);
 
-            let results = match serde_json::from_slice::<Results>(&bytes) {
+            let results = match serde_json::from_slice::<LocalResults>(&bytes) {
                 Ok(r) => r,
-                Err(_) => Results::default(),
+                Err(_) => LocalResults::default(),
             };
             return results
                 .results
                 .iter()
-                .max_by(|x, y| x.match_id.cmp(&y.match_id))
-                .map_or(0, |x| x.match_id);
+                .max_by(|x, y| x.game_result.match_id.cmp(&y.game_result.match_id))
+                .map_or(0, |x| x.game_result.match_id);
         }
 
         0u32
@@ -123,7 +125,8 @@ impl MatchSource for TestSource {
                         Ok((mut m, expected_result)) => {
                             m.match_id =
                                 Self::get_current_match_id(&self.settings.results_file) + 1;
-                            *self.expected_result.write() = Some(expected_result);
+                            *self.expected_result.write() = expected_result;
+                            *self.current_match.write() = Some(m.clone());
                             Some(m)
                         }
                         Err(e) => {
@@ -143,50 +146,53 @@ impl MatchSource for TestSource {
         game_result: &AiArenaGameResult,
         _logs_and_replays: Option<LogsAndReplays>,
     ) -> Result<(), SubmissionError> {
-        Self::update_results_file(&game_result, &self.settings.results_file)?;
+        let local_result = LocalGameResult::new(game_result, self.current_match.read().as_ref());
+        Self::update_results_file(local_result, &self.settings.results_file)?;
         self.update_matches_file()?;
 
-        let expected_result = self.expected_result.read().unwrap();
-        if expected_result != game_result.result {
-            error!(
-                "Actual result {:?} does not match expected result {:?}",
-                game_result.result, expected_result
-            );
-            std::process::exit(2);
+        // Matches without an expected result, like those of a tournament, take any result
+        if let Some(expected_result) = *self.expected_result.read() {
+            if expected_result != game_result.result {
+                error!(
+                    "Actual result {:?} does not match expected result {:?}",
+                    game_result.result, expected_result
+                );
+                std::process::exit(2);
+            }
         }
 
         Ok(())
     }
 }
 
-#[derive(Deserialize, Serialize, Default, Debug)]
-struct Results {
-    results: Vec<AiArenaGameResult>,
-}
-
-fn extract_match(line: &str) -> Result<(Match, AiArenaResult), FileMatchExtractError> {
+/// Extracts the match of a line and its expected result, which is optional after the map
+fn extract_match(line: &str) -> Result<(Match, Option<AiArenaResult>), FileMatchExtractError> {
     let mut vec_line: Vec<String> = line
         .split(',')
         .map(std::string::ToString::to_string)
         .collect();
 
-    match vec_line.len().cmp(&10) {
+    let (bot1_len, bot2_len) = player_slot_lens(&vec_line)?;
+
+    let has_expected_result = match vec_line.len().cmp(&(bot1_len + bot2_len + 2)) {
         Ordering::Greater => {
             return Err(FileMatchExtractError::TooManyFields(vec_line));
         }
-        Ordering::Less => {
+        Ordering::Less if vec_line.len() < bot1_len + bot2_len + 1 => {
             return Err(FileMatchExtractError::MissingFields(vec_line));
         }
-        Ordering::Equal => {}
-    }
+        Ordering::Less => false,
+        Ordering::Equal => true,
+    };
 
-    let bot1: Vec<String> = vec_line.drain(0..4).collect();
-    let bot2: Vec<String> = vec_line.drain(0..4).collect();
+    let bot1: Vec<String> = vec_line.drain(0..bot1_len).collect();
+    let bot2: Vec<String> = vec_line.drain(0..bot2_len).collect();
 
-    let expected_result = vec_line
-        .pop()
-        .map(|r| AiArenaResult::from_str(&r).unwrap())
-        .ok_or_else(|| FileMatchExtractError::MapName(vec_line.clone()))?;
+    let expected_result = if has_expected_result {
+        vec_line.pop().map(|r| AiArenaResult::from_str(&r).unwrap())
+    } else {
+        None
+    };
 
     let map_name = vec_line
         .pop()
@@ -200,6 +206,7 @@ fn extract_match(line: &str) -> Result<(Match, AiArenaResult), FileMatchExtractE
             match_id: 0,
             players,
             map_name,
+            settings: MatchSettings::default(),
             aiarena_match: None,
         },
         expected_result,
@@ -212,6 +219,7 @@ mod tests {
     use crate::matches::sources::test_source::extract_match;
     use common::models::aiarena::aiarena_result::AiArenaResult;
     use common::models::aiarena::bot_race::BotRace;
+    use common::models::aiarena::local_game_result::{LocalGameResult, LocalResults};
     use common::PlayerNum;
 
     #[test]
@@ -228,7 +236,18 @@ mod tests {
         assert_eq!(m.players[&PlayerNum::One].race, BotRace::Terran);
         assert_eq!(m.players[&PlayerNum::Two].race, BotRace::Protoss);
         assert_eq!(m.map_name, "AutomatonLE");
-        assert_eq!(expected_result, AiArenaResult::Player1Win);
+        assert_eq!(expected_result, Some(AiArenaResult::Player1Win));
+    }
+
+    #[test]
+    pub fn test_match_extracts_without_expected_result() {
+        let m =
+            extract_match("bot-id-1,basic_bot,T,python,bot-id-2,loser_bot,P,python,AutomatonLE");
+        assert!(m.is_ok());
+        let (m, expected_result) = m.unwrap();
+        assert_eq!(m.players[&PlayerNum::Two].name, "loser_bot");
+        assert_eq!(m.map_name, "AutomatonLE");
+        assert_eq!(expected_result, None);
     }
 
     #[test]
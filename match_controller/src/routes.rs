@@ -4,7 +4,9 @@ use common::api::errors::app_error::AppError;
 use common::api::errors::download_error::DownloadError;
 use common::configuration::ac_config::ACConfig;
 use common::models::aiarena::aiarena_match::AiArenaMatch;
+use common::utilities::files::md5_hex;
 use common::PlayerNum;
+use std::future::Future;
 use tracing::{self, error};
 
 pub async fn download_bot(
@@ -32,21 +34,18 @@ pub async fn download_bot(
     let mut url = url::Url::parse(&settings.caching_server_url).unwrap();
     url = url.join("/download").unwrap();
 
-    match api
-        .download_cached_file(url.as_str(), &source_url, &unique_key, &md5_hash)
-        .await
-    {
-        Ok(x) => Ok(x),
-        Err(e) => {
-            error!(
-                "Cached data download failed, downloading from original source: {:?}",
-                e
-            );
+    download_verified(
+        settings,
+        &unique_key,
+        Some(md5_hash.as_str()),
+        Some(api.download_cached_file(url.as_str(), &source_url, &unique_key, &md5_hash)),
+        || async {
             api.download_zip(&source_url, !settings.aws)
                 .await
                 .map_err(|e| AppError::Download(DownloadError::Other(e.to_string())))
-        }
-    }
+        },
+    )
+    .await
 }
 
 pub async fn download_bot_data(
@@ -75,26 +74,20 @@ pub async fn download_bot_data(
                 format!("{}_data", current_match.bot2.name),
             ),
         };
-        match api
-            .download_cached_file(
-                url.as_str(),
-                &source_url,
-                &unique_key,
-                md5_hash.unwrap().as_str(),
-            )
-            .await
-        {
-            Ok(x) => Ok(x),
-            Err(e) => {
-                error!(
-                    "Cached zip download failed, downloading from original source: {:?}",
-                    e
-                );
+        download_verified(
+            settings,
+            &unique_key,
+            md5_hash.as_deref(),
+            md5_hash.as_deref().map(|md5_hash| {
+                api.download_cached_file(url.as_str(), &source_url, &unique_key, md5_hash)
+            }),
+            || async {
                 api.download_zip(&source_url, !settings.aws)
                     .await
                     .map_err(|e| AppError::Download(DownloadError::Other(e.to_string())))
-            }
-        }
+            },
+        )
+        .await
     } else {
         Err(AppError::Download(DownloadError::NotAvailable(
             "No data url for bot".to_string(),
@@ -115,25 +108,76 @@ pub async fn download_map(
     let unique_key = &current_match.map.name;
     let mut url = url::Url::parse(&settings.caching_server_url).unwrap();
     url = url.join("/download").unwrap();
-    if let Some(md5_hash) = &current_match.map.file_hash {
-        match api
-            .download_cached_file(url.as_str(), &source_url, &unique_key, &md5_hash)
-            .await
-        {
-            Ok(x) => Ok(x),
-            Err(e) => {
-                error!(
-                    "Cached map download failed, downloading from original source: {:?}",
-                    e
-                );
-                api.download_map(source_url, !settings.aws)
-                    .await
-                    .map_err(|e| AppError::Download(DownloadError::Other(e.to_string())))
+    let md5_hash = current_match.map.file_hash.as_deref();
+
+    download_verified(
+        settings,
+        unique_key,
+        md5_hash,
+        md5_hash.map(|md5_hash| {
+            api.download_cached_file(url.as_str(), source_url, unique_key, md5_hash)
+        }),
+        || async {
+            api.download_map(source_url, !settings.aws)
+                .await
+                .map_err(|e| AppError::Download(DownloadError::Other(e.to_string())))
+        },
+    )
+    .await
+}
+
+/// Downloads the asset from the caching server, falling back to the original source when that
+/// fails or the file does not match its hash. The file of the original source is verified as well.
+async fn download_verified<C, E, O, F>(
+    settings: &ACConfig,
+    asset: &str,
+    md5_hash: Option<&str>,
+    cached_download: Option<C>,
+    original_download: O,
+) -> Result<Bytes, AppError>
+where
+    C: Future<Output = Result<Bytes, E>>,
+    E: std::fmt::Debug,
+    O: FnOnce() -> F,
+    F: Future<Output = Result<Bytes, AppError>>,
+{
+    if let Some(cached_download) = cached_download {
+        let cached = match cached_download.await {
+            Ok(bytes) => verify_hash(settings, asset, md5_hash, bytes),
+            Err(e) => Err(AppError::Download(DownloadError::Other(format!("{e:?}")))),
+        };
+        match cached {
+            Ok(bytes) => return Ok(bytes),
+            Err(e) => error!(
+                "Cached download of {} failed, downloading from original source: {:?}",
+                asset, e
+            ),
+        }
+    }
+    let bytes = original_download().await?;
+    verify_hash(settings, asset, md5_hash, bytes)
+}
+
+/// Checks the md5 hash of a downloaded asset if `hash_check` is enabled and the hash is known
+fn verify_hash(
+    settings: &ACConfig,
+    asset: &str,
+    md5_hash: Option<&str>,
+    bytes: Bytes,
+) -> Result<Bytes, AppError> {
+    match md5_hash.filter(|hash| settings.hash_check && !hash.is_empty()) {
+        Some(expected) => {
+            let actual = md5_hex(&bytes);
+            if actual.eq_ignore_ascii_case(expected) {
+                Ok(bytes)
+            } else {
+                Err(AppError::Download(DownloadError::HashMismatch {
+                    asset: asset.to_string(),
+                    expected: expected.to_string(),
+                    actual,
+                }))
             }
         }
-    } else {
-        api.download_map(source_url, !settings.aws)
-            .await
-            .map_err(|e| AppError::Download(DownloadError::Other(e.to_string())))
+        None => Ok(bytes),
     }
 }
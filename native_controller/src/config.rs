@@ -0,0 +1,64 @@
+use common::portpicker::Port;
+use config::{Config, Environment, File, FileFormat};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+static PREFIX: &str = "acnative";
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NativeConfig {
+    pub matches_file: String,
+    pub bots_directory: String,
+    pub work_directory: String,
+    pub bin_directory: String,
+    pub logging_level: String,
+    pub player_1_seat: Port,
+    pub player_2_seat: Port,
+    pub event_port: Port,
+    pub control_port: Port,
+    pub startup_timeout_secs: u64,
+}
+
+impl NativeConfig {
+    /// Path of a controller binary, next to this binary unless `BIN_DIRECTORY` is set
+    pub fn controller_binary(&self, name: &str) -> PathBuf {
+        let bin_directory = if self.bin_directory.is_empty() {
+            std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(PathBuf::from))
+                .unwrap_or_default()
+        } else {
+            PathBuf::from(&self.bin_directory)
+        };
+        bin_directory.join(name)
+    }
+
+    pub fn results_file(&self) -> PathBuf {
+        absolute(&self.work_directory).join("results.json")
+    }
+}
+
+pub fn initialize_config() -> NativeConfig {
+    let default_config = include_str!("../config.toml");
+    Config::builder()
+        .add_source(File::from_str(default_config, FileFormat::Toml).required(true))
+        .add_source(File::new("config.toml", FileFormat::Toml).required(false))
+        .add_source(File::new("config.json", FileFormat::Json).required(false))
+        .add_source(Environment::default().prefix(PREFIX))
+        .build()
+        .expect("Could not load config")
+        .try_deserialize::<NativeConfig>()
+        .expect("Could not deserialize config")
+}
+
+/// The controllers run in other working directories, so paths are passed to them as absolute paths
+pub fn absolute(path: &str) -> PathBuf {
+    let path = PathBuf::from(path);
+    if path.is_relative() {
+        std::env::current_dir()
+            .expect("Failed to get current directory")
+            .join(path)
+    } else {
+        path
+    }
+}
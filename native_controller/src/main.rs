@@ -0,0 +1,86 @@
+mod config;
+mod matches;
+mod processes;
+mod runner;
+
+use crate::config::{absolute, initialize_config, NativeConfig};
+use crate::matches::read_matches_file;
+use crate::processes::{handle_termination_signals, terminated};
+use crate::runner::run_match;
+use clap::{crate_name, crate_version, Arg, Command};
+use common::logging::init_logging;
+use tracing::{error, info};
+
+fn main() {
+    let args = Command::new(crate_name!())
+        .version(crate_version!())
+        .about("Runs the matches of a matches file as native processes, without Docker")
+        .arg(
+            Arg::new("matches_file")
+                .value_name("MATCHES_FILE")
+                .help("File with the matches to run, instead of MATCHES_FILE of the configuration"),
+        )
+        .get_matches();
+
+    let mut config = initialize_config();
+    if let Some(matches_file) = args.get_one::<String>("matches_file") {
+        config.matches_file = matches_file.clone();
+    }
+
+    let work_directory = absolute(&config.work_directory);
+    std::fs::create_dir_all(&work_directory).expect("Could not create the work directory");
+    let log_level = &config.logging_level;
+    let env_log = std::env::var("RUST_LOG")
+        .unwrap_or_else(|_| format!("info,common={log_level},native_controller={log_level}"));
+    let (non_blocking_stdout, guard) = tracing_appender::non_blocking(std::io::stdout());
+    let non_blocking_file =
+        tracing_appender::rolling::never(&work_directory, "native_controller.log");
+    init_logging(&env_log, non_blocking_stdout, non_blocking_file);
+
+    let exit_code = run(&config);
+
+    // Flush the logs, as exiting skips the destructors
+    drop(guard);
+    std::process::exit(exit_code);
+}
+
+fn run(config: &NativeConfig) -> i32 {
+    if config.matches_file.is_empty() {
+        error!("Native controller requires a matches file");
+        return 1;
+    }
+    let matches = match read_matches_file(&config.matches_file) {
+        Ok(matches) => matches,
+        Err(e) => {
+            error!("{:?}", e);
+            return 1;
+        }
+    };
+
+    handle_termination_signals();
+
+    let mut failed = 0;
+    for (index, match_line) in matches.iter().enumerate() {
+        if terminated() {
+            break;
+        }
+        info!(
+            "Running match {} of {}: {}",
+            index + 1,
+            matches.len(),
+            match_line.line
+        );
+        if let Err(e) = run_match(config, index + 1, match_line) {
+            error!("{:?}", e);
+            failed += 1;
+        }
+    }
+
+    info!("Results are in {:?}", config.results_file());
+    if failed > 0 || terminated() {
+        error!("{} of {} matches failed", failed, matches.len());
+        return 1;
+    }
+    info!("Native controller exits");
+    0
+}
@@ -0,0 +1,105 @@
+use anyhow::{bail, Context, Result};
+use common::models::aiarena::builtin_ai::BUILTIN_AI_PREFIX;
+use common::PlayerNum;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+/// A bot of a match in the matches file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bot {
+    pub id: String,
+    pub name: String,
+}
+
+/// A line of the matches file, in the format of the client controller
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchLine {
+    pub line: String,
+    pub bot1: Bot,
+    /// None if player 2 is the built-in AI, which plays in the game controller
+    pub bot2: Option<Bot>,
+    /// The id of player 2, which is the slot of the built-in AI if it plays
+    player_2_id: String,
+}
+
+impl MatchLine {
+    pub fn parse(line: &str) -> Result<Self> {
+        let parts = line.split(',').map(str::trim).collect::<Vec<_>>();
+        if parts.len() < 6 {
+            bail!("Missing fields in match {:?}", line);
+        }
+
+        let bot1 = Bot {
+            id: parts[0].to_string(),
+            name: parts[1].to_string(),
+        };
+        let bot2 = if parts[4].starts_with(BUILTIN_AI_PREFIX) {
+            None
+        } else if parts.len() < 9 {
+            bail!("Missing fields in match {:?}", line);
+        } else {
+            Some(Bot {
+                id: parts[4].to_string(),
+                name: parts[5].to_string(),
+            })
+        };
+
+        Ok(Self {
+            line: line.to_string(),
+            bot1,
+            bot2,
+            player_2_id: parts[4].to_string(),
+        })
+    }
+
+    pub fn opponent_id(&self, player_num: PlayerNum) -> &str {
+        match player_num {
+            PlayerNum::One => &self.player_2_id,
+            PlayerNum::Two => &self.bot1.id,
+        }
+    }
+}
+
+/// Reads the matches of the file, skipping empty lines and comments
+pub fn read_matches_file(path: &str) -> Result<Vec<MatchLine>> {
+    let file = File::open(path).with_context(|| format!("Could not open matches file {path}"))?;
+    let mut matches = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| format!("Could not read matches file {path}"))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        matches.push(MatchLine::parse(line)?);
+    }
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_match_line() {
+        let match_line =
+            MatchLine::parse("1,basic_bot,T,python,2,loser_bot,P,python,AutomatonLE,Player1Win")
+                .unwrap();
+        assert_eq!(match_line.bot1.name, "basic_bot");
+        assert_eq!(match_line.bot2.as_ref().unwrap().id, "2");
+        assert_eq!(match_line.opponent_id(PlayerNum::One), "2");
+        assert_eq!(match_line.opponent_id(PlayerNum::Two), "1");
+    }
+
+    #[test]
+    fn test_parse_builtin_ai() {
+        let match_line =
+            MatchLine::parse("1,basic_bot,T,python,builtin:VeryHard:Zerg:Macro,AutomatonLE")
+                .unwrap();
+        assert!(match_line.bot2.is_none());
+        assert_eq!(
+            match_line.opponent_id(PlayerNum::One),
+            "builtin:VeryHard:Zerg:Macro"
+        );
+        assert!(MatchLine::parse("1,basic_bot,T,python,2,loser_bot").is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! Child processes of the controllers.
+//!
+//! Each controller runs in its own process group, so that stopping a controller also stops the
+//! bot or SC2 processes it started, like stopping its container would.
+
+use anyhow::{anyhow, Result};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Time given to a controller to shut down gracefully before it is killed
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+static TERMINATED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_termination_signal(_: libc::c_int) {
+    TERMINATED.store(true, Ordering::SeqCst);
+}
+
+/// Notes SIGINT and SIGTERM, so the runner can stop the controllers before it exits
+pub fn handle_termination_signals() {
+    let handler = on_termination_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: The handler only stores to an atomic, which is async-signal-safe
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+pub fn terminated() -> bool {
+    TERMINATED.load(Ordering::SeqCst)
+}
+
+/// A controller process, which is stopped together with its process group when dropped
+pub struct ControllerProcess {
+    name: String,
+    child: Child,
+    stopped: bool,
+}
+
+impl ControllerProcess {
+    pub fn spawn(name: &str, command: &mut Command) -> Result<Self> {
+        let child = command
+            .process_group(0)
+            .spawn()
+            .map_err(|e| anyhow!("Could not start the {name}: {e}"))?;
+        info!("Started the {} with pid {}", name, child.id());
+        Ok(Self {
+            name: name.to_string(),
+            child,
+            stopped: false,
+        })
+    }
+
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        self.child
+            .try_wait()
+            .map_err(|e| anyhow!("Could not check the {}: {e}", self.name))
+    }
+
+    /// Waits for the controller to exit. Returns None if the runner is terminated first.
+    pub fn wait(&mut self) -> Result<Option<ExitStatus>> {
+        loop {
+            if let Some(exit_status) = self.try_wait()? {
+                return Ok(Some(exit_status));
+            }
+            if terminated() {
+                return Ok(None);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Asks the process group of the controller to shut down and kills what is left after a timeout
+    pub fn stop(&mut self) {
+        if self.stopped {
+            return;
+        }
+        self.stopped = true;
+
+        self.signal_group(libc::SIGTERM);
+        let start_time = Instant::now();
+        while start_time.elapsed() < STOP_TIMEOUT {
+            match self.child.try_wait() {
+                Ok(None) => std::thread::sleep(POLL_INTERVAL),
+                _ => break,
+            }
+        }
+        self.signal_group(libc::SIGKILL);
+        let _ = self.child.wait();
+        info!("Stopped the {}", self.name);
+    }
+
+    fn signal_group(&self, signal: libc::c_int) {
+        let Ok(pgid) = libc::pid_t::try_from(self.child.id()) else {
+            return;
+        };
+        // SAFETY: Sends a signal to the process group created for this controller
+        if unsafe { libc::kill(-pgid, signal) } != 0 {
+            let e = std::io::Error::last_os_error();
+            // The group is gone once all its processes exited
+            if e.raw_os_error() != Some(libc::ESRCH) {
+                error!("Could not signal the {}: {:?}", self.name, e);
+            }
+        }
+    }
+}
+
+impl Drop for ControllerProcess {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,228 @@
+use crate::config::{absolute, NativeConfig};
+use crate::matches::{Bot, MatchLine};
+use crate::processes::{terminated, ControllerProcess};
+use anyhow::{anyhow, bail, Context, Result};
+use common::paths::MatchLayout;
+use common::portpicker::Port;
+use common::PlayerNum;
+use std::fs;
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// The directories of a match, in place of the fixed paths of the containers
+pub struct MatchDirectories {
+    /// The root of the match, which also receives the replay
+    pub root: PathBuf,
+    /// The match request, result and join state, in place of `/match`
+    pub match_folder: PathBuf,
+    /// The logs of all controllers, in place of `/logs`
+    pub log_root: PathBuf,
+}
+
+impl MatchDirectories {
+    pub fn new(work_directory: &str, number: usize) -> Self {
+        let root = absolute(work_directory).join(format!("match-{number}"));
+        Self {
+            match_folder: root.join("match"),
+            log_root: root.join("logs"),
+            root,
+        }
+    }
+
+    /// Creates the directories, removing those of a previous run of the match
+    fn create(&self) -> Result<()> {
+        if self.root.exists() {
+            fs::remove_dir_all(&self.root)
+                .with_context(|| format!("Could not clear {:?}", self.root))?;
+        }
+        for controller in [
+            "match_controller",
+            "game_controller",
+            "bot-controller-1",
+            "bot-controller-2",
+        ] {
+            fs::create_dir_all(self.log_root.join(controller))
+                .with_context(|| format!("Could not create {:?}", self.log_root))?;
+        }
+        fs::create_dir_all(&self.match_folder)
+            .with_context(|| format!("Could not create {:?}", self.match_folder))
+    }
+
+    /// The layout of a controller of the match, with its logs in the given folder
+    fn layout(&self, log_folder: PathBuf) -> MatchLayout {
+        MatchLayout {
+            match_folder: self.match_folder.clone(),
+            log_folder,
+            replay_folder: self.root.clone(),
+            ..MatchLayout::default()
+        }
+    }
+
+    fn matches_file(&self) -> PathBuf {
+        self.match_folder.join("match-request.csv")
+    }
+}
+
+/// Runs the match like the containers of the client controller, but as local processes
+pub fn run_match(config: &NativeConfig, number: usize, match_line: &MatchLine) -> Result<()> {
+    let dirs = MatchDirectories::new(&config.work_directory, number);
+    dirs.create()?;
+    fs::write(dirs.matches_file(), &match_line.line)
+        .with_context(|| format!("Could not write {:?}", dirs.matches_file()))?;
+
+    info!("Preparing match {} in {:?}", number, dirs.root);
+    let prepare_status = match_controller_command(config, &dirs, "prepare")
+        .status()
+        .context("Could not run the match controller")?;
+    if !prepare_status.success() || !dirs.match_folder.join("match-request.toml").exists() {
+        bail!("Match {number} could not be prepared");
+    }
+
+    let mut game_controller = ControllerProcess::spawn(
+        "game controller",
+        &mut game_controller_command(config, &dirs),
+    )?;
+    wait_for_seats(config, &mut game_controller)?;
+
+    let mut match_controller = ControllerProcess::spawn(
+        "match controller",
+        &mut match_controller_command(config, &dirs, "submit"),
+    )?;
+    let mut bot_controllers = Vec::new();
+    for (player_num, bot, seat) in [
+        (PlayerNum::One, Some(&match_line.bot1), config.player_1_seat),
+        (
+            PlayerNum::Two,
+            match_line.bot2.as_ref(),
+            config.player_2_seat,
+        ),
+    ] {
+        if let Some(bot) = bot {
+            let opponent_id = match_line.opponent_id(player_num);
+            bot_controllers.push(ControllerProcess::spawn(
+                &format!("bot controller {}", bot_number(player_num)),
+                &mut bot_controller_command(config, &dirs, player_num, bot, opponent_id, seat),
+            )?);
+        }
+    }
+
+    let submit_status = match_controller.wait()?;
+    for bot_controller in &mut bot_controllers {
+        bot_controller.stop();
+    }
+    game_controller.stop();
+
+    match submit_status {
+        None => Err(anyhow!("Match {number} was terminated")),
+        Some(exit_status) if exit_status.success() => {
+            info!("Match {} finished", number);
+            Ok(())
+        }
+        Some(exit_status) => Err(anyhow!(
+            "Match controller of match {number} exited with {exit_status}"
+        )),
+    }
+}
+
+fn match_controller_command(
+    config: &NativeConfig,
+    dirs: &MatchDirectories,
+    run_type: &str,
+) -> Command {
+    let mut command = Command::new(config.controller_binary("match_controller"));
+    command
+        .env("ACMATCH_RUN_TYPE", run_type)
+        .env("ACMATCH_MATCHES_FILE", dirs.matches_file())
+        .env("ACMATCH_RESULTS_FILE", config.results_file())
+        .env("ACMATCH_LOG_ROOT", &dirs.log_root)
+        .env("ACMATCH_BOT_DIRECTORY", absolute(&config.bots_directory))
+        .env("ACMATCH_GAME_DIRECTORY", &dirs.root)
+        .env("ACMATCH_EVENT_PORT", config.event_port.to_string())
+        .env("ACMATCH_MATCH_DIRECTORY", &dirs.match_folder)
+        .current_dir(&dirs.root);
+    command
+}
+
+fn game_controller_command(config: &NativeConfig, dirs: &MatchDirectories) -> Command {
+    let mut command = Command::new(config.controller_binary("sc2_controller"));
+    command
+        .env("PLAYER_1_SEAT", config.player_1_seat.to_string())
+        .env("PLAYER_2_SEAT", config.player_2_seat.to_string())
+        .env("MATCH_CONTROLLER_URL", match_controller_url(config))
+        .env("CONTROL_PORT", config.control_port.to_string())
+        .envs(dirs.layout(dirs.log_root.join("game_controller")).envs())
+        .current_dir(&dirs.root);
+    command
+}
+
+fn bot_controller_command(
+    config: &NativeConfig,
+    dirs: &MatchDirectories,
+    player_num: PlayerNum,
+    bot: &Bot,
+    opponent_id: &str,
+    seat: Port,
+) -> Command {
+    let layout = MatchLayout {
+        bot_folder: absolute(&config.bots_directory).join(&bot.name),
+        ..dirs
+            .layout(dirs.log_root.clone())
+            .bot_controller_layout(player_num)
+    };
+    let mut command = Command::new(config.controller_binary("bot_controller"));
+    command
+        .env("BOT_NAME", &bot.name)
+        .env("GAME_HOST", "127.0.0.1")
+        .env("GAME_PORT", seat.to_string())
+        .env("OPPONENT_ID", opponent_id)
+        .env("PLAYER_NUM", bot_number(player_num))
+        .env("MATCH_CONTROLLER_URL", match_controller_url(config))
+        .envs(layout.envs())
+        .current_dir(&dirs.root);
+    command
+}
+
+fn match_controller_url(config: &NativeConfig) -> String {
+    format!("http://127.0.0.1:{}", config.event_port)
+}
+
+fn bot_number(player_num: PlayerNum) -> &'static str {
+    match player_num {
+        PlayerNum::One => "1",
+        PlayerNum::Two => "2",
+    }
+}
+
+/// Waits until the game controller accepts connections on both player seats
+fn wait_for_seats(config: &NativeConfig, game_controller: &mut ControllerProcess) -> Result<()> {
+    let timeout = Duration::from_secs(config.startup_timeout_secs);
+    let start_time = Instant::now();
+    loop {
+        if [config.player_1_seat, config.player_2_seat]
+            .iter()
+            .all(|seat| seat_open(*seat))
+        {
+            return Ok(());
+        }
+        if let Some(exit_status) = game_controller.try_wait()? {
+            bail!("Game controller exited with {exit_status} before opening the player seats");
+        }
+        if terminated() {
+            bail!("Terminated while waiting for the player seats");
+        }
+        if start_time.elapsed() >= timeout {
+            bail!(
+                "Game controller did not open the player seats within {} seconds",
+                config.startup_timeout_secs
+            );
+        }
+        std::thread::sleep(Duration::from_secs(1));
+    }
+}
+
+fn seat_open(seat: Port) -> bool {
+    TcpStream::connect_timeout(&([127, 0, 0, 1], seat).into(), Duration::from_secs(1)).is_ok()
+}
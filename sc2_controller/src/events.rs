@@ -0,0 +1,121 @@
+//! Reports the progress of the match to the match controller.
+//! The result and join state files stay the source of truth, so failed reports are only logged by the client.
+
+use crate::game::game_config::GameConfig;
+use axum::Json;
+use common::api::api_reference::match_controller_client::MatchController;
+use common::models::match_controller::{ControllerEvent, JoinState};
+use common::paths::match_layout;
+use common::PlayerNum;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use tracing::error;
+
+const MAX_RETRIES: u32 = 30;
+
+static PROGRESS: Lazy<Mutex<MatchProgress>> = Lazy::new(|| Mutex::new(MatchProgress::default()));
+
+/// The players that joined the current match and whether its start was reported
+#[derive(Default)]
+struct MatchProgress {
+    join_state: JoinState,
+    game_started: bool,
+}
+
+impl MatchProgress {
+    fn reset(&mut self, match_id: u32) {
+        self.join_state = JoinState::new(match_id);
+        self.game_started = false;
+    }
+
+    fn join(&mut self, match_id: u32, player_num: PlayerNum) -> JoinState {
+        if self.join_state.match_id != match_id {
+            self.reset(match_id);
+        }
+        self.join_state.join(player_num);
+        self.join_state.clone()
+    }
+
+    /// Whether the game of the match has just started, so the start is reported once
+    fn start_game(&mut self, match_id: u32, builtin_ai: bool) -> bool {
+        let join_state = &self.join_state;
+        // The built-in AI joins together with player 1
+        let all_joined = join_state.match_id == match_id
+            && join_state.has_joined(PlayerNum::One)
+            && (builtin_ai || join_state.has_joined(PlayerNum::Two));
+        if all_joined && !self.game_started {
+            self.game_started = true;
+            return true;
+        }
+        false
+    }
+}
+
+/// Sends the event in the background if the match controller URL is configured
+pub fn report(event: ControllerEvent) {
+    if let Some(match_controller) = MatchController::from_env() {
+        tokio::spawn(async move {
+            match_controller
+                .send_event_with_retry(&event, MAX_RETRIES)
+                .await
+        });
+    }
+}
+
+/// Publishes the player to the join state file and the match controller once its join request
+/// is forwarded to SC2. SC2 only answers the join requests once all players joined, so the
+/// players that did not join can only be told apart before the answer.
+pub fn report_player_joined(config: &GameConfig, player_num: PlayerNum) {
+    let join_state = PROGRESS.lock().join(config.match_id, player_num);
+    if let Err(e) = join_state.to_json_file(match_layout()) {
+        error!("Could not store the join state: {:?}", e);
+    }
+    report(ControllerEvent::PlayerJoined { player_num });
+}
+
+/// Reports the start of the game once SC2 answered the join requests of all players
+pub fn report_game_started(config: &GameConfig) {
+    let builtin_ai = config.players[&PlayerNum::Two].builtin_ai.is_some();
+    if PROGRESS.lock().start_game(config.match_id, builtin_ai) {
+        report(ControllerEvent::GameStarted);
+    }
+}
+
+/// Clears the joined players for a new match
+pub fn reset_join_state(match_id: u32) {
+    PROGRESS.lock().reset(match_id);
+}
+
+/// Handler of `/join_state` on the player seats
+pub async fn join_state() -> Json<JoinState> {
+    Json(PROGRESS.lock().join_state.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_player_that_never_joins() {
+        let mut progress = MatchProgress::default();
+        progress.reset(5);
+
+        // SC2 does not answer the join request of player 1 while player 2 is missing
+        let join_state = progress.join(5, PlayerNum::One);
+        assert!(join_state.has_joined(PlayerNum::One));
+        assert!(!join_state.has_joined(PlayerNum::Two));
+        assert!(!progress.start_game(5, false));
+
+        progress.join(5, PlayerNum::Two);
+        assert!(progress.start_game(5, false));
+        assert!(!progress.start_game(5, false));
+    }
+
+    #[test]
+    fn test_builtin_ai_starts_with_player_1() {
+        let mut progress = MatchProgress::default();
+        progress.join(6, PlayerNum::One);
+        assert!(!progress.start_game(7, true));
+        assert!(progress.start_game(6, true));
+    }
+}
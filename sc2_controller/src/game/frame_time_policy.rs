@@ -0,0 +1,179 @@
+//! Time budget of a bot for each game step
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::game::game_config::GameConfig;
+
+/// Tracks the time a bot takes for each game step.
+/// A step over the budget first draws the overrun from the time bank.
+/// When the time bank is used up, the step counts as a strike.
+#[derive(Debug, Clone)]
+pub struct FrameTimePolicy {
+    enforce: bool,
+    step_budget: Option<Duration>,
+    hard_limit: Option<Duration>,
+    time_bank: Duration,
+    max_strikes: u32,
+    strikes: u32,
+    steps_over_budget: u32,
+}
+
+/// Reason for timing out a bot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameTimeViolation {
+    HardLimit {
+        step_time: Duration,
+        limit: Duration,
+    },
+    Strikes {
+        strikes: u32,
+        budget: Duration,
+    },
+}
+
+impl fmt::Display for FrameTimeViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HardLimit { step_time, limit } => write!(
+                f,
+                "Frame time violation: step took {}ms, hard limit is {}ms",
+                step_time.as_millis(),
+                limit.as_millis()
+            ),
+            Self::Strikes { strikes, budget } => write!(
+                f,
+                "Frame time violation: {} steps over {}ms after time bank was used up",
+                strikes,
+                budget.as_millis()
+            ),
+        }
+    }
+}
+
+impl FrameTimePolicy {
+    pub fn new(config: &GameConfig) -> Self {
+        Self {
+            // Step time has no meaning when the game doesn't wait for the bots
+            enforce: config.enforce_frame_time && !config.realtime(),
+            step_budget: u64::try_from(config.max_frame_time)
+                .ok()
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis),
+            hard_limit: Some(config.hard_frame_time)
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis),
+            time_bank: Duration::from_millis(config.frame_time_bank),
+            max_strikes: config.max_frame_time_strikes,
+            strikes: 0,
+            steps_over_budget: 0,
+        }
+    }
+
+    pub const fn steps_over_budget(&self) -> u32 {
+        self.steps_over_budget
+    }
+
+    /// Records the time the bot took for a game step.
+    /// Returns the violation when the bot has to be timed out.
+    pub fn record_step(&mut self, step_time: Duration) -> Option<FrameTimeViolation> {
+        if let Some(limit) = self.hard_limit {
+            if step_time > limit && self.enforce {
+                return Some(FrameTimeViolation::HardLimit { step_time, limit });
+            }
+        }
+
+        let budget = self.step_budget?;
+        if step_time <= budget {
+            return None;
+        }
+        self.steps_over_budget += 1;
+
+        let overrun = step_time - budget;
+        if overrun <= self.time_bank {
+            self.time_bank -= overrun;
+            return None;
+        }
+        self.time_bank = Duration::ZERO;
+        self.strikes += 1;
+
+        if self.enforce && self.strikes > self.max_strikes {
+            Some(FrameTimeViolation::Strikes {
+                strikes: self.strikes,
+                budget,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(budget_ms: u64, bank_ms: u64, max_strikes: u32, hard_ms: u64) -> FrameTimePolicy {
+        FrameTimePolicy {
+            enforce: true,
+            step_budget: Some(Duration::from_millis(budget_ms)),
+            hard_limit: Some(hard_ms)
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis),
+            time_bank: Duration::from_millis(bank_ms),
+            max_strikes,
+            strikes: 0,
+            steps_over_budget: 0,
+        }
+    }
+
+    #[test]
+    fn test_steps_within_budget() {
+        let mut policy = policy(40, 0, 0, 0);
+        for _ in 0..100 {
+            assert!(policy.record_step(Duration::from_millis(40)).is_none());
+        }
+        assert_eq!(policy.steps_over_budget, 0);
+    }
+
+    #[test]
+    fn test_time_bank_covers_overrun() {
+        let mut policy = policy(40, 100, 0, 0);
+        assert!(policy.record_step(Duration::from_millis(90)).is_none());
+        assert_eq!(policy.time_bank, Duration::from_millis(50));
+        assert!(policy.record_step(Duration::from_millis(90)).is_none());
+        assert_eq!(policy.time_bank, Duration::ZERO);
+        assert!(policy.record_step(Duration::from_millis(41)).is_some());
+        assert_eq!(policy.steps_over_budget, 3);
+    }
+
+    #[test]
+    fn test_strikes() {
+        let mut policy = policy(40, 0, 2, 0);
+        assert!(policy.record_step(Duration::from_millis(50)).is_none());
+        assert!(policy.record_step(Duration::from_millis(50)).is_none());
+        assert_eq!(
+            policy.record_step(Duration::from_millis(50)),
+            Some(FrameTimeViolation::Strikes {
+                strikes: 3,
+                budget: Duration::from_millis(40)
+            })
+        );
+    }
+
+    #[test]
+    fn test_hard_limit() {
+        let mut policy = policy(40, 10_000, 10, 1_000);
+        assert!(matches!(
+            policy.record_step(Duration::from_millis(1_001)),
+            Some(FrameTimeViolation::HardLimit { .. })
+        ));
+    }
+
+    #[test]
+    fn test_not_enforced() {
+        let mut policy = policy(40, 0, 0, 100);
+        policy.enforce = false;
+        assert!(policy.record_step(Duration::from_millis(500)).is_none());
+        assert_eq!(policy.steps_over_budget, 1);
+    }
+}
@@ -1,13 +1,28 @@
+use common::configuration::ac_config::{RateLimitMode, RealTimeLimitResult};
 use common::models::aiarena::aiarena_match::{MatchPlayer, MatchRequest};
 use common::models::aiarena::bot_race::BotRace;
+use common::paths::match_layout;
 use common::PlayerNum;
 use std::collections::HashMap;
+use std::time::Duration;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GameConfig {
     pub map: String,
     pub max_game_time: u32,
     pub max_frame_time: i32,
+    pub enforce_frame_time: bool,
+    pub frame_time_bank: u64,
+    pub max_frame_time_strikes: u32,
+    pub hard_frame_time: u64,
+    pub max_actions_per_step: u32,
+    pub max_queries_per_step: u32,
+    pub max_apm: u32,
+    pub rate_limit_mode: RateLimitMode,
+    pub allowed_requests: Vec<String>,
+    pub allowed_debug_commands: Vec<String>,
+    pub max_real_time: Option<Duration>,
+    pub real_time_limit_result: RealTimeLimitResult,
     pub timeout_secs: u64,
     pub replay_path: String,
     pub match_id: u32,
@@ -35,6 +50,7 @@ impl GameConfig {
                     race: BotRace::from_str(&match_request.player_1_race.to_string()),
                     bot_type: "linux".to_string(), // Bot type is irrelevant for the game controller
                     bot_base: "".to_string(),      // Bot base is irrelevant for the game controller
+                    builtin_ai: None,
                 },
             ),
             (
@@ -45,6 +61,7 @@ impl GameConfig {
                     race: BotRace::from_str(&match_request.player_2_race.to_string()),
                     bot_type: "linux".to_string(), // Bot type is irrelevant for the game controller
                     bot_base: "".to_string(),      // Bot base is irrelevant for the game controller
+                    builtin_ai: match_request.player_2_builtin_ai,
                 },
             ),
         ]);
@@ -58,14 +75,29 @@ impl GameConfig {
             map: map_name.to_string(),
             players: players,
 
-            max_game_time: 80640,
-            max_frame_time: 40,
-            timeout_secs: 30,
-            replay_path: "/root/StarCraftII/maps".to_string(),
+            max_game_time: match_request.max_game_time,
+            max_frame_time: match_request.max_frame_time,
+            enforce_frame_time: match_request.enforce_frame_time,
+            frame_time_bank: match_request.frame_time_bank,
+            max_frame_time_strikes: match_request.max_frame_time_strikes,
+            hard_frame_time: match_request.hard_frame_time,
+            max_actions_per_step: match_request.max_actions_per_step,
+            max_queries_per_step: match_request.max_queries_per_step,
+            max_apm: match_request.max_apm,
+            rate_limit_mode: match_request.rate_limit_mode,
+            allowed_requests: match_request.allowed_requests.clone(),
+            allowed_debug_commands: match_request.allowed_debug_commands.clone(),
+            max_real_time: u64::try_from(match_request.max_real_time)
+                .ok()
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            real_time_limit_result: match_request.real_time_limit_result,
+            timeout_secs: match_request.timeout_secs,
+            replay_path: match_layout().replay_folder.to_string_lossy().into_owned(),
             replay_name: replay_name,
-            disable_debug: true,
-            real_time: false,
-            validate_race: true,
+            disable_debug: match_request.disable_debug,
+            real_time: match_request.realtime,
+            validate_race: match_request.validate_race,
             visualize: false, // Not used
         }
     }
@@ -2,13 +2,14 @@ use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
 use common::models::aiarena::aiarena_result::AiArenaResult;
 use common::PlayerNum;
 use once_cell::sync::Lazy;
+use serde::Serialize;
 use std::sync::{Arc, RwLock};
 use tracing::debug;
 
 use crate::game::player_result::PlayerResult;
 use crate::game::sc2_result::Sc2Result;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct GameResult {
     pub match_id: u32,
     pub player1_result: Option<PlayerResult>,
@@ -81,6 +82,8 @@ impl From<&GameResult> for AiArenaGameResult {
         let mut bot1_tags = None;
         let mut bot2_avg_step_time = None;
         let mut bot2_tags = None;
+        let mut bot1_step_time_stats = None;
+        let mut bot2_step_time_stats = None;
         let mut p1_result = None;
         let mut p2_result = None;
 
@@ -90,6 +93,7 @@ impl From<&GameResult> for AiArenaGameResult {
                 debug!("Player1Result: {:?}", player1_result);
                 bot1_avg_step_time = Some(player1_result.frame_time);
                 bot1_tags = Some(player1_result.tags.iter().cloned().collect());
+                bot1_step_time_stats = player1_result.step_time_stats;
                 game_steps = player1_result.game_loops;
                 p1_result = Some(player1_result.result);
             }
@@ -100,6 +104,7 @@ impl From<&GameResult> for AiArenaGameResult {
                 debug!("Player2Result: {:?}", player2_result);
                 bot2_avg_step_time = Some(player2_result.frame_time);
                 bot2_tags = Some(player2_result.tags.iter().cloned().collect());
+                bot2_step_time_stats = player2_result.step_time_stats;
                 game_steps = player2_result.game_loops;
                 p2_result = Some(player2_result.result);
             }
@@ -107,9 +112,8 @@ impl From<&GameResult> for AiArenaGameResult {
         let result = game_result
             .result
             .unwrap_or_else(|| match (p1_result, p2_result) {
-                (Some(Sc2Result::SC2Crash), _) | (_, Some(Sc2Result::SC2Crash)) => {
-                    AiArenaResult::Error
-                }
+                (Some(Sc2Result::SC2Crash | Sc2Result::RealTimeLimit), _)
+                | (_, Some(Sc2Result::SC2Crash | Sc2Result::RealTimeLimit)) => AiArenaResult::Error,
                 (Some(Sc2Result::Tie), _) | (_, Some(Sc2Result::Tie)) => AiArenaResult::Tie,
                 (Some(Sc2Result::Crash), _) => AiArenaResult::Player1Crash,
                 (_, Some(Sc2Result::Crash)) => AiArenaResult::Player2Crash,
@@ -131,6 +135,8 @@ impl From<&GameResult> for AiArenaGameResult {
             bot1_tags,
             bot2_avg_step_time,
             bot2_tags,
+            bot1_step_time_stats,
+            bot2_step_time_stats,
             result,
             game_steps,
         }
@@ -146,7 +152,7 @@ mod tests {
     use crate::game::game_result::GameResult;
     use crate::game::player_result::PlayerResult;
     use crate::game::sc2_result::Sc2Result;
-    use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
+    use common::models::aiarena::aiarena_game_result::{AiArenaGameResult, StepTimeStats};
     use common::models::aiarena::aiarena_result::AiArenaResult;
 
     fn game_result() -> GameResult {
@@ -157,6 +163,7 @@ mod tests {
                 frame_time: 0.0,
                 player_id: 0,
                 tags: Default::default(),
+                step_time_stats: None,
                 result: Sc2Result::Placeholder,
             }),
             player2_result: Some(PlayerResult {
@@ -164,6 +171,7 @@ mod tests {
                 frame_time: 0.0,
                 player_id: 0,
                 tags: Default::default(),
+                step_time_stats: None,
                 result: Sc2Result::Placeholder,
             }),
             result: Some(AiArenaResult::Placeholder),
@@ -252,6 +260,18 @@ mod tests {
         assert_eq!(serialized["type"], "Error");
     }
 
+    #[test]
+    fn test_result_serialization_real_time_limit() {
+        let mut game_result = game_result();
+        game_result.player1_result.as_mut().unwrap().result = Sc2Result::RealTimeLimit;
+        game_result.player2_result.as_mut().unwrap().result = Sc2Result::RealTimeLimit;
+        game_result.result = None;
+        let aiarena_game_result = AiArenaGameResult::from(&game_result);
+        let serialized =
+            serde_json::to_value(aiarena_game_result).expect("Could not serialize GameResult");
+        assert_eq!(serialized["type"], "Error");
+    }
+
     #[test]
     fn test_result_serialization_p1_crash() {
         let mut game_result = game_result();
@@ -287,6 +307,37 @@ mod tests {
         assert_eq!(serialized["match"], match_id);
     }
 
+    #[test]
+    fn test_result_serialization_builtin_ai_opponent() {
+        let mut game_result = game_result();
+        let player1_result = game_result.player1_result.as_mut().unwrap();
+        player1_result.result = Sc2Result::Defeat;
+        game_result.player2_result = Some(player1_result.builtin_ai_opponent());
+        game_result.result = None;
+        let aiarena_game_result = AiArenaGameResult::from(&game_result);
+        let serialized =
+            serde_json::to_value(aiarena_game_result).expect("Could not serialize GameResult");
+        assert_eq!(serialized["type"], "Player2Win");
+    }
+
+    #[test]
+    fn test_result_serialization_step_time_stats() {
+        let mut game_result = game_result();
+        game_result.result = Some(AiArenaResult::Tie);
+        game_result.player1_result.as_mut().unwrap().step_time_stats = Some(StepTimeStats {
+            p50: 0.01,
+            p95: 0.02,
+            p99: 0.03,
+            max: 0.5,
+            steps_over_budget: 4,
+        });
+        let aiarena_game_result = AiArenaGameResult::from(&game_result);
+        let serialized =
+            serde_json::to_value(aiarena_game_result).expect("Could not serialize GameResult");
+        assert_eq!(serialized["bot1_step_time_stats"]["steps_over_budget"], 4);
+        assert!(serialized.get("bot2_step_time_stats").is_none());
+    }
+
     // #[test]
     // fn test_game_result_serialization() {
     //     let match_id = 9999;
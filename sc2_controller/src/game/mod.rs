@@ -1,5 +1,9 @@
+pub mod frame_time_policy;
 pub mod game_config;
 pub mod game_result;
 pub mod player_data;
 pub mod player_result;
+pub mod request_filter;
+pub mod request_rate_policy;
 pub mod sc2_result;
+pub mod step_time_histogram;
@@ -1,3 +1,4 @@
+use common::models::aiarena::aiarena_game_result::StepTimeStats;
 use serde::{Deserialize, Serialize};
 
 use crate::game::sc2_result::Sc2Result;
@@ -13,10 +14,24 @@ pub struct PlayerResult {
     /// Tags
     #[serde(skip_serializing_if = "indexmap::IndexSet::is_empty")]
     pub tags: indexmap::IndexSet<String>,
+    /// Step time distribution, if the bot made any game steps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_time_stats: Option<StepTimeStats>,
     /// Result
     pub result: Sc2Result,
 }
 impl PlayerResult {
+    /// Result of the built-in AI that played against this player
+    pub fn builtin_ai_opponent(&self) -> Self {
+        Self {
+            game_loops: self.game_loops,
+            frame_time: 0.0,
+            player_id: if self.player_id == 1 { 2 } else { 1 },
+            tags: indexmap::IndexSet::default(),
+            step_time_stats: None,
+            result: self.result.opponent_result(),
+        }
+    }
     pub fn set_game_loops(&mut self, game_loops: u32) {
         self.game_loops = game_loops;
     }
@@ -0,0 +1,137 @@
+//! Allowlist of the requests a bot can send to SC2
+
+use std::collections::HashSet;
+
+use protobuf::MessageFull;
+use sc2_proto::debug::DebugCommand;
+use sc2_proto::sc2api::Request;
+
+use crate::game::game_config::GameConfig;
+
+// Requests of a spectator. `obs_action` moves the camera of the spectator only.
+const OBSERVER_REQUESTS: &[&str] = &[
+    "leave_game",
+    "quit",
+    "game_info",
+    "observation",
+    "obs_action",
+    "step",
+    "data",
+    "ping",
+];
+
+/// Refuses requests and debug commands that are not on the allowlists of the match.
+/// Request types are named by their field in `sc2api.Request`, e.g. `observation`,
+/// debug commands by their field in `sc2api.DebugCommand`, e.g. `draw`.
+#[derive(Debug, Clone)]
+pub struct RequestFilter {
+    allowed_requests: HashSet<String>,
+    allowed_debug_commands: HashSet<String>,
+}
+
+impl RequestFilter {
+    pub fn new(config: &GameConfig) -> Self {
+        Self {
+            allowed_requests: config.allowed_requests.iter().cloned().collect(),
+            allowed_debug_commands: config.allowed_debug_commands.iter().cloned().collect(),
+        }
+    }
+
+    /// The allowlist of a spectator, which can only watch the game
+    pub fn observer() -> Self {
+        Self {
+            allowed_requests: OBSERVER_REQUESTS.iter().map(|r| r.to_string()).collect(),
+            allowed_debug_commands: HashSet::new(),
+        }
+    }
+
+    /// Returns the name of the request type or debug command, if the request is refused
+    pub fn refused(&self, request: &Request) -> Option<String> {
+        let Some(request_type) = oneof_field_name(request, "request") else {
+            return Some("empty request".to_string());
+        };
+        if !self.allowed_requests.contains(&request_type) {
+            return Some(request_type);
+        }
+        request
+            .debug()
+            .debug
+            .iter()
+            .map(|command| oneof_field_name(command, "command").unwrap_or_default())
+            .find(|command| !self.allowed_debug_commands.contains(command))
+            .map(|command| format!("debug {}", command))
+    }
+}
+
+/// The name of the field that is set in a oneof of the message
+pub fn oneof_field_name<M: MessageFull>(message: &M, oneof: &str) -> Option<String> {
+    M::descriptor()
+        .oneof_by_name(oneof)
+        .and_then(|oneof| oneof.fields().find(|field| field.has_field(message)))
+        .map(|field| field.name().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sc2_proto::debug::DebugDraw;
+    use sc2_proto::sc2api::{
+        RequestDebug, RequestObservation, RequestRestartGame, RequestStartReplay,
+    };
+
+    fn filter(requests: &[&str], debug_commands: &[&str]) -> RequestFilter {
+        RequestFilter {
+            allowed_requests: requests.iter().map(|r| r.to_string()).collect(),
+            allowed_debug_commands: debug_commands.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_allowed_request() {
+        let mut request = Request::new();
+        request.set_observation(RequestObservation::new());
+        assert_eq!(filter(&["observation"], &[]).refused(&request), None);
+    }
+
+    #[test]
+    fn test_refused_requests() {
+        let filter = filter(&["observation"], &[]);
+        let mut request = Request::new();
+        request.set_start_replay(RequestStartReplay::new());
+        assert_eq!(filter.refused(&request), Some("start_replay".to_string()));
+        request.set_restart_game(RequestRestartGame::new());
+        assert_eq!(filter.refused(&request), Some("restart_game".to_string()));
+        assert_eq!(
+            filter.refused(&Request::new()),
+            Some("empty request".to_string())
+        );
+    }
+
+    #[test]
+    fn test_observer_requests() {
+        let filter = RequestFilter::observer();
+        let mut request = Request::new();
+        request.set_observation(RequestObservation::new());
+        assert_eq!(filter.refused(&request), None);
+        request.set_restart_game(RequestRestartGame::new());
+        assert_eq!(filter.refused(&request), Some("restart_game".to_string()));
+        request.set_start_replay(RequestStartReplay::new());
+        assert_eq!(filter.refused(&request), Some("start_replay".to_string()));
+    }
+
+    #[test]
+    fn test_debug_commands() {
+        let mut command = DebugCommand::new();
+        command.set_draw(DebugDraw::new());
+        let mut debug = RequestDebug::new();
+        debug.debug.push(command);
+        let mut request = Request::new();
+        request.set_debug(debug);
+
+        assert_eq!(filter(&["debug"], &["draw"]).refused(&request), None);
+        assert_eq!(
+            filter(&["debug"], &["kill_unit"]).refused(&request),
+            Some("debug draw".to_string())
+        );
+    }
+}
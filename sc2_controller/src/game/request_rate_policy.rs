@@ -0,0 +1,332 @@
+//! Rate limits of the actions and queries of a bot
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use common::configuration::ac_config::RateLimitMode;
+use sc2_proto::sc2api::Request;
+
+use crate::game::game_config::GameConfig;
+
+/// Window of the actions per minute
+const APM_WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits the actions and queries a bot sends per game step, and its APM in realtime games.
+/// Violations are counted, and depending on the mode the request is truncated or rejected.
+#[derive(Debug, Clone)]
+pub struct RequestRatePolicy {
+    mode: RateLimitMode,
+    max_actions_per_step: Option<u32>,
+    max_queries_per_step: Option<u32>,
+    max_apm: Option<u32>,
+    game_loop: u32,
+    step_actions: u32,
+    step_queries: u32,
+    recent_actions: VecDeque<(Instant, u32)>,
+    window_actions: u32,
+    violations: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimit {
+    ActionsPerStep(u32),
+    QueriesPerStep(u32),
+    Apm(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitViolation {
+    pub limit: RateLimit,
+    pub requested: u32,
+    pub allowed: u32,
+}
+
+impl fmt::Display for RateLimitViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let limit = match self.limit {
+            RateLimit::ActionsPerStep(max) => format!("{} actions per step", max),
+            RateLimit::QueriesPerStep(max) => format!("{} queries per step", max),
+            RateLimit::Apm(max) => format!("{} actions per minute", max),
+        };
+        write!(
+            f,
+            "Rate limit violation: {} requested, {} allowed by the limit of {}",
+            self.requested, self.allowed, limit
+        )
+    }
+}
+
+/// What to do with a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitDecision {
+    Forward,
+    Truncate(u32),
+    Reject(RateLimitViolation),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RequestKind {
+    Actions,
+    Queries,
+}
+
+impl RequestRatePolicy {
+    pub fn new(config: &GameConfig) -> Self {
+        Self::with_limits(
+            config.rate_limit_mode,
+            config.max_actions_per_step,
+            config.max_queries_per_step,
+            // Outside of realtime games, the actions per step limit the APM already
+            if config.realtime() { config.max_apm } else { 0 },
+        )
+    }
+
+    fn with_limits(
+        mode: RateLimitMode,
+        max_actions_per_step: u32,
+        max_queries_per_step: u32,
+        max_apm: u32,
+    ) -> Self {
+        Self {
+            mode,
+            max_actions_per_step: Some(max_actions_per_step).filter(|max| *max > 0),
+            max_queries_per_step: Some(max_queries_per_step).filter(|max| *max > 0),
+            max_apm: Some(max_apm).filter(|max| *max > 0),
+            game_loop: 0,
+            step_actions: 0,
+            step_queries: 0,
+            recent_actions: VecDeque::new(),
+            window_actions: 0,
+            violations: 0,
+        }
+    }
+
+    pub const fn violations(&self) -> u32 {
+        self.violations
+    }
+
+    /// Starts counting a new game step when the game loop has advanced
+    pub fn start_game_loop(&mut self, game_loop: u32) {
+        if game_loop != self.game_loop {
+            self.game_loop = game_loop;
+            self.step_actions = 0;
+            self.step_queries = 0;
+        }
+    }
+
+    /// Applies the limits to a request of the bot, truncating it in truncate mode.
+    /// Returns the violation if the request has to be rejected.
+    pub fn apply(&mut self, request: &mut Request, now: Instant) -> Option<RateLimitViolation> {
+        if request.has_action() {
+            let requested = request.action().actions.len() as u32;
+            match self.decide(RequestKind::Actions, requested, now) {
+                RateLimitDecision::Forward => None,
+                RateLimitDecision::Truncate(allowed) => {
+                    request.mut_action().actions.truncate(allowed as usize);
+                    None
+                }
+                RateLimitDecision::Reject(violation) => Some(violation),
+            }
+        } else if request.has_query() {
+            let query = request.query();
+            let requested =
+                (query.pathing.len() + query.abilities.len() + query.placements.len()) as u32;
+            match self.decide(RequestKind::Queries, requested, now) {
+                RateLimitDecision::Forward => None,
+                RateLimitDecision::Truncate(allowed) => {
+                    let query = request.mut_query();
+                    let mut allowed = allowed as usize;
+                    query.pathing.truncate(allowed);
+                    allowed -= query.pathing.len();
+                    query.abilities.truncate(allowed);
+                    allowed -= query.abilities.len();
+                    query.placements.truncate(allowed);
+                    None
+                }
+                RateLimitDecision::Reject(violation) => Some(violation),
+            }
+        } else {
+            None
+        }
+    }
+
+    fn decide(&mut self, kind: RequestKind, requested: u32, now: Instant) -> RateLimitDecision {
+        let violation = self.check(kind, requested, now);
+        let counted = match (violation, self.mode) {
+            (None, _) | (Some(_), RateLimitMode::Tag) => requested,
+            (Some(violation), RateLimitMode::Truncate) => violation.allowed,
+            (Some(_), RateLimitMode::Reject) => 0,
+        };
+        self.count(kind, counted, now);
+
+        match violation {
+            None => RateLimitDecision::Forward,
+            Some(violation) => {
+                self.violations += 1;
+                match self.mode {
+                    RateLimitMode::Tag => RateLimitDecision::Forward,
+                    RateLimitMode::Truncate => RateLimitDecision::Truncate(violation.allowed),
+                    RateLimitMode::Reject => RateLimitDecision::Reject(violation),
+                }
+            }
+        }
+    }
+
+    /// Returns the strictest limit the request exceeds
+    fn check(
+        &mut self,
+        kind: RequestKind,
+        requested: u32,
+        now: Instant,
+    ) -> Option<RateLimitViolation> {
+        let mut limits = Vec::with_capacity(2);
+        match kind {
+            RequestKind::Actions => {
+                if let Some(max) = self.max_actions_per_step {
+                    let allowed = max.saturating_sub(self.step_actions);
+                    limits.push((RateLimit::ActionsPerStep(max), allowed));
+                }
+                if let Some(max) = self.max_apm {
+                    self.expire_actions(now);
+                    let allowed = max.saturating_sub(self.window_actions);
+                    limits.push((RateLimit::Apm(max), allowed));
+                }
+            }
+            RequestKind::Queries => {
+                if let Some(max) = self.max_queries_per_step {
+                    let allowed = max.saturating_sub(self.step_queries);
+                    limits.push((RateLimit::QueriesPerStep(max), allowed));
+                }
+            }
+        }
+        limits
+            .into_iter()
+            .filter(|(_, allowed)| requested > *allowed)
+            .min_by_key(|(_, allowed)| *allowed)
+            .map(|(limit, allowed)| RateLimitViolation {
+                limit,
+                requested,
+                allowed,
+            })
+    }
+
+    fn count(&mut self, kind: RequestKind, count: u32, now: Instant) {
+        match kind {
+            RequestKind::Actions => {
+                self.step_actions += count;
+                if self.max_apm.is_some() && count > 0 {
+                    self.recent_actions.push_back((now, count));
+                    self.window_actions += count;
+                }
+            }
+            RequestKind::Queries => self.step_queries += count,
+        }
+    }
+
+    fn expire_actions(&mut self, now: Instant) {
+        while let Some((time, count)) = self.recent_actions.front().copied() {
+            if now.duration_since(time) < APM_WINDOW {
+                break;
+            }
+            self.recent_actions.pop_front();
+            self.window_actions -= count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_within_limits() {
+        let mut policy = RequestRatePolicy::with_limits(RateLimitMode::Reject, 10, 5, 0);
+        let now = Instant::now();
+        for game_loop in 1..100 {
+            policy.start_game_loop(game_loop);
+            let decision = policy.decide(RequestKind::Actions, 10, now);
+            assert_eq!(decision, RateLimitDecision::Forward);
+            let decision = policy.decide(RequestKind::Queries, 5, now);
+            assert_eq!(decision, RateLimitDecision::Forward);
+        }
+        assert_eq!(policy.violations(), 0);
+    }
+
+    #[test]
+    fn test_truncate_actions_per_step() {
+        let mut policy = RequestRatePolicy::with_limits(RateLimitMode::Truncate, 10, 0, 0);
+        let now = Instant::now();
+        assert_eq!(
+            policy.decide(RequestKind::Actions, 6, now),
+            RateLimitDecision::Forward
+        );
+        assert_eq!(
+            policy.decide(RequestKind::Actions, 6, now),
+            RateLimitDecision::Truncate(4)
+        );
+        assert_eq!(
+            policy.decide(RequestKind::Actions, 1, now),
+            RateLimitDecision::Truncate(0)
+        );
+        policy.start_game_loop(1);
+        assert_eq!(
+            policy.decide(RequestKind::Actions, 10, now),
+            RateLimitDecision::Forward
+        );
+        assert_eq!(policy.violations(), 2);
+    }
+
+    #[test]
+    fn test_reject_queries_per_step() {
+        let mut policy = RequestRatePolicy::with_limits(RateLimitMode::Reject, 0, 3, 0);
+        let now = Instant::now();
+        assert_eq!(
+            policy.decide(RequestKind::Queries, 4, now),
+            RateLimitDecision::Reject(RateLimitViolation {
+                limit: RateLimit::QueriesPerStep(3),
+                requested: 4,
+                allowed: 3,
+            })
+        );
+        // Rejected queries are not counted
+        assert_eq!(
+            policy.decide(RequestKind::Queries, 3, now),
+            RateLimitDecision::Forward
+        );
+    }
+
+    #[test]
+    fn test_tag_forwards_violations() {
+        let mut policy = RequestRatePolicy::with_limits(RateLimitMode::Tag, 1, 1, 0);
+        let now = Instant::now();
+        assert_eq!(
+            policy.decide(RequestKind::Actions, 5, now),
+            RateLimitDecision::Forward
+        );
+        assert_eq!(
+            policy.decide(RequestKind::Queries, 5, now),
+            RateLimitDecision::Forward
+        );
+        assert_eq!(policy.violations(), 2);
+    }
+
+    #[test]
+    fn test_apm_window() {
+        let mut policy = RequestRatePolicy::with_limits(RateLimitMode::Truncate, 0, 0, 100);
+        let start = Instant::now();
+        assert_eq!(
+            policy.decide(RequestKind::Actions, 80, start),
+            RateLimitDecision::Forward
+        );
+        assert_eq!(
+            policy.decide(RequestKind::Actions, 30, start + Duration::from_secs(30)),
+            RateLimitDecision::Truncate(20)
+        );
+        // The first actions leave the window after a minute
+        assert_eq!(
+            policy.decide(RequestKind::Actions, 80, start + Duration::from_secs(60)),
+            RateLimitDecision::Forward
+        );
+    }
+}
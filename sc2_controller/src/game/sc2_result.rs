@@ -11,6 +11,8 @@ pub enum Sc2Result {
     #[allow(clippy::upper_case_acronyms)]
     SC2Crash,
     Timeout,
+    /// The game exceeded the real time limit and ends with an error
+    RealTimeLimit,
     #[cfg(test)]
     Placeholder,
 }
@@ -25,6 +27,19 @@ impl Sc2Result {
         }
     }
 
+    /// Result of the opponent, for players that don't report their own result
+    pub const fn opponent_result(self) -> Self {
+        match self {
+            Self::Victory => Self::Defeat,
+            Self::Defeat | Self::Crash | Self::Timeout => Self::Victory,
+            Self::Tie => Self::Tie,
+            Self::SC2Crash => Self::SC2Crash,
+            Self::RealTimeLimit => Self::RealTimeLimit,
+            #[cfg(test)]
+            Self::Placeholder => Self::Placeholder,
+        }
+    }
+
     pub const fn to_proto(self) -> sc2_proto::sc2api::Result {
         use sc2_proto::sc2api::Result;
         match self {
@@ -33,7 +48,7 @@ impl Sc2Result {
             Self::Tie => Result::Tie,
             Self::Crash => Result::Defeat,
             Self::Timeout => Result::Defeat,
-            Self::SC2Crash => Result::Undecided,
+            Self::SC2Crash | Self::RealTimeLimit => Result::Undecided,
             #[cfg(test)]
             Self::Placeholder => Result::Undecided,
         }
@@ -0,0 +1,125 @@
+//! Compact histogram of the time a bot takes for its game steps
+
+use std::time::Duration;
+
+// Number of buckets for each power of two microseconds. Bounds the relative error to 1/16.
+const SUB_BUCKET_BITS: u32 = 4;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+
+/// Log-linear histogram of step durations with microsecond resolution.
+/// Keeps a few hundred counters at most, regardless of the length of the game.
+#[derive(Debug, Clone, Default)]
+pub struct StepTimeHistogram {
+    counts: Vec<u32>,
+    total: u64,
+    max: Duration,
+}
+
+impl StepTimeHistogram {
+    pub fn record(&mut self, step_time: Duration) {
+        let micros = u64::try_from(step_time.as_micros()).unwrap_or(u64::MAX);
+        let index = bucket_index(micros);
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] = self.counts[index].saturating_add(1);
+        self.total += 1;
+        self.max = self.max.max(step_time);
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    pub const fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Step time at the given quantile (between 0 and 1).
+    /// The result is the upper bound of the matching bucket, but never more than the maximum.
+    pub fn quantile(&self, quantile: f64) -> Duration {
+        if self.total == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * self.total as f64).ceil() as u64).max(1);
+        let mut seen = 0u64;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += u64::from(*count);
+            if seen >= rank {
+                return Duration::from_micros(bucket_upper_bound(index)).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+fn bucket_index(micros: u64) -> usize {
+    if micros < SUB_BUCKETS {
+        return micros as usize;
+    }
+    let exponent = 63 - micros.leading_zeros();
+    let shift = exponent - SUB_BUCKET_BITS;
+    let sub_bucket = (micros >> shift) - SUB_BUCKETS;
+    ((u64::from(shift) + 1) * SUB_BUCKETS + sub_bucket) as usize
+}
+
+fn bucket_upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let sub_bucket = index % SUB_BUCKETS;
+    let lower = (SUB_BUCKETS + sub_bucket) << shift;
+    lower + ((1 << shift) - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_bounds_are_contiguous() {
+        let mut previous_index = 0;
+        for micros in 0..100_000u64 {
+            let index = bucket_index(micros);
+            assert!(index == previous_index || index == previous_index + 1);
+            assert!(bucket_upper_bound(index) >= micros);
+            previous_index = index;
+        }
+    }
+
+    #[test]
+    fn test_empty_histogram() {
+        let histogram = StepTimeHistogram::default();
+        assert!(histogram.is_empty());
+        assert_eq!(histogram.quantile(0.5), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_quantiles() {
+        let mut histogram = StepTimeHistogram::default();
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+        let p50 = histogram.quantile(0.5).as_secs_f64();
+        let p99 = histogram.quantile(0.99).as_secs_f64();
+        assert!((0.050..=0.050 * 1.0625).contains(&p50), "p50 = {p50}");
+        assert!((0.099..=0.100).contains(&p99), "p99 = {p99}");
+        assert_eq!(histogram.quantile(1.0), Duration::from_millis(100));
+        assert_eq!(histogram.max(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_spike_is_visible() {
+        let mut histogram = StepTimeHistogram::default();
+        for _ in 0..990 {
+            histogram.record(Duration::from_millis(5));
+        }
+        for _ in 0..10 {
+            histogram.record(Duration::from_secs(2));
+        }
+        assert!(histogram.quantile(0.95) < Duration::from_millis(6));
+        assert!(histogram.quantile(0.995) > Duration::from_secs(1));
+    }
+}
@@ -1,13 +1,12 @@
+use common::paths::match_layout;
 use std::fs::{File, OpenOptions};
-use std::path::Path;
 use tracing::info;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
 fn create_log_file(file_name: &str) -> File {
-    let log_folder = std::env::var("LOG_FOLDER").unwrap_or_else(|_| "/logs".into());
-    let log_path = Path::new(&log_folder).join(file_name);
+    let log_path = match_layout().log_file(file_name);
 
     OpenOptions::new()
         .create(true)
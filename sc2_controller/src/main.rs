@@ -1,32 +1,214 @@
+mod events;
 mod game;
 mod logging;
+mod match_session;
+mod observer_slot;
 mod player_seats;
+mod replay;
 mod routes;
+mod status;
+mod supervisor;
 mod websocket;
 mod ws_routes;
 
 use crate::logging::init_logs;
-use crate::routes::open_player_seat;
-use tracing::info;
+use crate::player_seats::player_seat_port;
+use crate::replay::{replay_recording, ReplayOutcome};
+use crate::routes::{open_observer_seat, open_player_seat, open_unused_player_seat};
+use crate::status::{open_control_port, set_seats_ready};
+use crate::supervisor::kill_all;
+use crate::websocket::protocol_recorder::{read_recording, Direction, RecordedMessage};
+use anyhow::Result;
+use clap::{crate_name, crate_version, Arg, ArgMatches, Command};
+use common::models::aiarena::aiarena_match::MatchRequest;
+use common::paths::match_layout;
+use common::PlayerNum;
+use std::path::Path;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
 
 #[tokio::main]
 async fn main() {
     let _guards = init_logs();
 
-    let seat1 = open_player_seat(1).await;
-    let seat2 = open_player_seat(2).await;
+    let matches = Command::new(crate_name!())
+        .version(crate_version!())
+        .subcommand(
+            Command::new("replay")
+                .about("Replays recorded bot protocols against fresh SC2 instances")
+                .arg(
+                    Arg::new("player1")
+                        .long("player1")
+                        .value_name("RECORDING")
+                        .required(true)
+                        .help("Protocol recording of player 1"),
+                )
+                .arg(
+                    Arg::new("player2")
+                        .long("player2")
+                        .value_name("RECORDING")
+                        .help("Protocol recording of player 2, unless it is the built-in AI"),
+                )
+                .arg(
+                    Arg::new("match-request")
+                        .long("match-request")
+                        .value_name("FILE")
+                        .help("Match request to replay with, instead of the one in the recordings"),
+                ),
+        )
+        .get_matches();
 
-    match (seat1, seat2) {
-        (Ok(ws1), Ok(ws2)) => {
+    match matches.subcommand() {
+        Some(("replay", args)) => replay(args).await,
+        _ => run().await,
+    }
+}
+
+async fn open_seats() -> Result<(JoinHandle<()>, JoinHandle<()>, Option<JoinHandle<()>>)> {
+    let seat1 = open_player_seat(1).await?;
+    let builtin_ai = MatchRequest::try_read(match_layout()).and_then(|r| r.player_2_builtin_ai);
+    let seat2 = match builtin_ai {
+        Some(builtin_ai) => {
+            info!("Player 2 is the built-in AI {}", builtin_ai.id());
+            open_unused_player_seat(2).await?
+        }
+        None => open_player_seat(2).await?,
+    };
+    let observer_seat = open_observer_seat().await?;
+    Ok((seat1, seat2, observer_seat))
+}
+
+async fn run() {
+    if let Err(e) = open_control_port().await {
+        error!("{:?}", e);
+    }
+
+    match open_seats().await {
+        Ok((ws1, ws2, observer_ws)) => {
             info!("Player seats opened successfully.");
+            set_seats_ready();
+
+            let observer_ws = async {
+                match observer_ws {
+                    Some(ws) => {
+                        let _ = ws.await;
+                    }
+                    None => std::future::pending().await,
+                }
+            };
 
             tokio::select! {
                 _ = ws1 => info!("Player seat 1 exited."),
                 _ = ws2 => info!("Player seat 2 exited."),
+                _ = observer_ws => info!("Observer seat exited."),
+                _ = shutdown_signal() => info!("Received shutdown signal."),
             }
+            kill_all();
         }
-        (Err(e), _) | (_, Err(e)) => {
+        Err(e) => {
+            kill_all();
             panic!("Failed to start SC2: {:?}", e);
         }
     }
 }
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut sigterm) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = sigterm.recv() => {}
+                _ = tokio::signal::ctrl_c() => {}
+            }
+            return;
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+async fn replay(args: &ArgMatches) {
+    // The recordings are read first, because the replay may record to the same files
+    let mut recordings = Vec::new();
+    for (arg, player_num, num) in [
+        ("player1", PlayerNum::One, 1),
+        ("player2", PlayerNum::Two, 2),
+    ] {
+        if let Some(path) = args.get_one::<String>(arg) {
+            match read_recording(Path::new(path)) {
+                Ok(recording) => recordings.push((player_num, player_seat_port(num), recording)),
+                Err(e) => panic!("Could not read recording {}: {:?}", path, e),
+            }
+        }
+    }
+
+    let match_request = args.get_one::<String>("match-request");
+    if let Err(e) = restore_match_request(match_request.map(Path::new), &recordings) {
+        error!("Could not restore the match request: {}", e);
+        std::process::exit(1);
+    }
+
+    if let Err(e) = open_seats().await {
+        kill_all();
+        panic!("Failed to start SC2: {:?}", e);
+    }
+
+    let replays = recordings
+        .into_iter()
+        .map(|(player_num, port, recording)| {
+            tokio::spawn(replay_recording(player_num, port, recording))
+        })
+        .collect::<Vec<_>>();
+
+    let mut diverged = false;
+    for replay in replays {
+        match replay.await {
+            Ok(ReplayOutcome::Completed { requests }) => {
+                info!("Replay completed after {} requests", requests)
+            }
+            Ok(ReplayOutcome::Diverged(divergence)) => {
+                error!("{}", divergence);
+                diverged = true;
+            }
+            Ok(ReplayOutcome::Failed(e)) => {
+                error!("Replay failed: {}", e);
+                diverged = true;
+            }
+            Err(e) => {
+                error!("Replay failed: {:?}", e);
+                diverged = true;
+            }
+        }
+    }
+    kill_all();
+    std::process::exit(i32::from(diverged));
+}
+
+/// Writes the match request of the replay to the match folder, where the player seats read it.
+/// A given file is used as is, otherwise the match request recorded first in the recordings.
+fn restore_match_request(
+    path: Option<&Path>,
+    recordings: &[(PlayerNum, u16, Vec<RecordedMessage>)],
+) -> Result<()> {
+    let layout = match_layout();
+    if let Some(path) = path {
+        if path != layout.match_request_file() {
+            std::fs::create_dir_all(&layout.match_folder)?;
+            std::fs::copy(path, layout.match_request_file())?;
+        }
+        return Ok(());
+    }
+
+    let recorded = recordings
+        .iter()
+        .flat_map(|(_, _, recording)| recording.first())
+        .find(|message| message.direction == Direction::MatchRequest);
+    match recorded {
+        Some(message) => {
+            let match_request: MatchRequest = serde_json::from_slice(&message.payload)?;
+            match_request.write(layout)?;
+            Ok(())
+        }
+        None => anyhow::bail!("The recordings contain no match request, pass --match-request"),
+    }
+}
@@ -0,0 +1,91 @@
+//! The match served by the controller.
+//!
+//! The controller serves consecutive matches without a restart. The first connection for a new
+//! match id in the match request resets the state of the previous match, and connections for a
+//! match that already finished are refused.
+
+use crate::events::reset_join_state;
+use crate::observer_slot::reset_observer_slot;
+use crate::status::reset_seats;
+use crate::supervisor::prepare_sc2_processes;
+use crate::ws_routes::reset_game_state;
+use common::models::aiarena::aiarena_match::MatchRequest;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use tracing::info;
+
+static SESSION: Lazy<Mutex<MatchSession>> = Lazy::new(|| Mutex::new(MatchSession::default()));
+
+#[derive(Debug, Default)]
+struct MatchSession {
+    match_id: Option<u32>,
+    finished: HashSet<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Admission {
+    Current,
+    New,
+    Finished,
+}
+
+impl MatchSession {
+    fn admit(&mut self, match_id: u32) -> Admission {
+        if self.finished.contains(&match_id) {
+            Admission::Finished
+        } else if self.match_id == Some(match_id) {
+            Admission::Current
+        } else {
+            self.match_id = Some(match_id);
+            Admission::New
+        }
+    }
+
+    fn finish(&mut self, match_id: u32) {
+        self.finished.insert(match_id);
+    }
+}
+
+/// Admits a connection for the requested match, starting the match if it is new.
+/// Returns false if the match already finished.
+pub fn begin_match(match_request: &MatchRequest) -> bool {
+    let mut session = SESSION.lock();
+    match session.admit(match_request.match_id) {
+        Admission::Current => true,
+        Admission::Finished => false,
+        Admission::New => {
+            info!("Starting match {}", match_request.match_id);
+            let builtin_ai = match_request.player_2_builtin_ai.is_some();
+            // The SC2 processes are prepared first, as they depend on the seat states of the previous match
+            prepare_sc2_processes(builtin_ai);
+            reset_seats(builtin_ai);
+            reset_join_state(match_request.match_id);
+            reset_observer_slot(match_request.match_id);
+            reset_game_state(match_request);
+            true
+        }
+    }
+}
+
+/// Marks the match as finished, so no more bots are admitted to it
+pub fn finish_match(match_id: u32) {
+    SESSION.lock().finish(match_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admit_consecutive_matches() {
+        let mut session = MatchSession::default();
+        assert_eq!(session.admit(1), Admission::New);
+        assert_eq!(session.admit(1), Admission::Current);
+        session.finish(1);
+        assert_eq!(session.admit(1), Admission::Finished);
+        assert_eq!(session.admit(2), Admission::New);
+        assert_eq!(session.admit(2), Admission::Current);
+        assert_eq!(session.admit(1), Admission::Finished);
+    }
+}
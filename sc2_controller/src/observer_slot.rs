@@ -0,0 +1,174 @@
+//! The observer slot of the game.
+//!
+//! SC2 only starts the game when all of its slots are joined. A spectator that doesn't join in
+//! time must not hold up the bots, so the controller then joins the observer slot itself and
+//! leaves it again right away. Spectators connecting after that are refused.
+
+use crate::player_seats::PlayerSeat;
+use crate::websocket::port_config::PortConfig;
+use crate::ws_routes::connect;
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
+use protobuf::{Message, MessageField};
+use sc2_proto::sc2api::{InterfaceOptions, Request, RequestJoinGame, RequestLeaveGame};
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+use tokio_tungstenite::tungstenite::Message as TMessage;
+use tracing::{error, info};
+
+/// Time a spectator has to join, after player 1 created the game
+const JOIN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Time SC2 has to answer the requests of the controller in the observer slot
+const SC2_TIMEOUT: Duration = Duration::from_secs(120);
+
+static SLOT: Lazy<Mutex<ObserverSlot>> = Lazy::new(|| Mutex::new(ObserverSlot::default()));
+
+static OBSERVER_SEAT: OnceCell<PlayerSeat> = OnceCell::new();
+
+#[derive(Debug, Default)]
+struct ObserverSlot {
+    match_id: Option<u32>,
+    state: SlotState,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum SlotState {
+    #[default]
+    Open,
+    /// A spectator or the controller is about to join
+    Claimed,
+    Joined,
+}
+
+impl ObserverSlot {
+    fn reset(&mut self, match_id: u32) {
+        self.match_id = Some(match_id);
+        self.state = SlotState::Open;
+    }
+
+    fn claim(&mut self, match_id: u32) -> bool {
+        let open = self.match_id == Some(match_id) && self.state == SlotState::Open;
+        if open {
+            self.state = SlotState::Claimed;
+        }
+        open
+    }
+
+    fn join(&mut self, match_id: u32) {
+        if self.match_id == Some(match_id) {
+            self.state = SlotState::Joined;
+        }
+    }
+
+    fn is_claimed(&self, match_id: u32) -> bool {
+        self.match_id == Some(match_id) && self.state == SlotState::Claimed
+    }
+}
+
+/// Remembers the observer seat, so the controller can join its slot
+pub fn register_observer_seat(player_seat: &PlayerSeat) {
+    let _ = OBSERVER_SEAT.set(player_seat.clone());
+}
+
+pub fn reset_observer_slot(match_id: u32) {
+    SLOT.lock().reset(match_id);
+}
+
+/// Claims the slot for a spectator. Returns false if it is already taken.
+pub fn claim_observer_slot(match_id: u32) -> bool {
+    SLOT.lock().claim(match_id)
+}
+
+pub fn join_observer_slot(match_id: u32) {
+    SLOT.lock().join(match_id);
+}
+
+/// Joins and leaves the slot claimed by a spectator that failed before joining
+pub async fn release_observer_slot(match_id: u32, port_config: PortConfig) {
+    let claimed = SLOT.lock().is_claimed(match_id);
+    if claimed {
+        fill_observer_slot(match_id, port_config).await;
+    }
+}
+
+/// Joins and leaves the slot, unless a spectator claimed it in time
+pub async fn observer_join_timeout(match_id: u32, port_config: PortConfig) {
+    sleep(JOIN_TIMEOUT).await;
+    if claim_observer_slot(match_id) {
+        info!("No spectator joined within {:?}", JOIN_TIMEOUT);
+        fill_observer_slot(match_id, port_config).await;
+    }
+}
+
+async fn fill_observer_slot(match_id: u32, port_config: PortConfig) {
+    let Some(player_seat) = OBSERVER_SEAT.get() else {
+        return;
+    };
+    info!("Joining and leaving the observer slot without a spectator");
+    let Some(mut sc2_ws) = connect(player_seat.internal_port).await else {
+        error!("Could not connect to SC2 of the observer slot");
+        return;
+    };
+
+    join_observer_slot(match_id);
+    for request in [proto_join_game(&port_config), proto_leave_game()] {
+        let bytes = request.write_to_bytes().expect("Invalid protobuf message");
+        if let Err(e) = sc2_ws.send(TMessage::binary(bytes)).await {
+            error!("Could not send to SC2 of the observer slot: {:?}", e);
+            return;
+        }
+        match timeout(SC2_TIMEOUT, sc2_ws.next()).await {
+            Ok(Some(Ok(_))) => {}
+            other => {
+                error!("No response from SC2 of the observer slot: {:?}", other);
+                return;
+            }
+        }
+    }
+}
+
+fn proto_join_game(port_config: &PortConfig) -> Request {
+    let mut options = InterfaceOptions::new();
+    options.set_raw(true);
+
+    let mut r_join_game = RequestJoinGame::new();
+    r_join_game.set_observed_player_id(0);
+    r_join_game.set_player_name("Observer".to_string());
+    r_join_game.options = MessageField::some(options);
+    port_config.apply_proto(&mut r_join_game);
+
+    let mut request = Request::new();
+    request.set_join_game(r_join_game);
+    request
+}
+
+fn proto_leave_game() -> Request {
+    let mut request = Request::new();
+    request.set_leave_game(RequestLeaveGame::new());
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_is_claimed_once_per_match() {
+        let mut slot = ObserverSlot::default();
+        assert!(!slot.claim(1));
+
+        slot.reset(1);
+        assert!(slot.claim(1));
+        assert!(slot.is_claimed(1));
+        assert!(!slot.claim(1));
+
+        slot.join(1);
+        assert!(!slot.is_claimed(1));
+
+        slot.reset(2);
+        assert!(!slot.claim(1));
+        assert!(slot.claim(2));
+    }
+}
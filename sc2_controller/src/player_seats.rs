@@ -1,3 +1,8 @@
+/// Player number of the observer seat
+const OBSERVER_NUM: u8 = 0;
+
+const OBSERVER_NAME: &str = "OBSERVER";
+
 #[derive(Clone)]
 pub struct PlayerSeat {
     pub player_num: u8,
@@ -13,17 +18,42 @@ pub struct PlayerSeat {
 
 impl PlayerSeat {
     pub fn new(num: u8, port: u16) -> Self {
+        let name = num.to_string();
         PlayerSeat {
             player_num: num,
-            pass_port: get_pass_port(num),
-            external_port: get_external_port(num),
+            pass_port: get_pass_port(&name),
+            external_port: get_external_port(&name),
+            internal_port: port,
+        }
+    }
+
+    /// The seat for a spectator client, opened only when `PLAYER_OBSERVER_SEAT` is set
+    pub fn observer(port: u16) -> Self {
+        PlayerSeat {
+            player_num: OBSERVER_NUM,
+            pass_port: get_pass_port(OBSERVER_NAME),
+            external_port: get_external_port(OBSERVER_NAME),
             internal_port: port,
         }
     }
+
+    pub const fn is_observer(&self) -> bool {
+        self.player_num == OBSERVER_NUM
+    }
+}
+
+/// Whether the game has a third seat for a spectator client
+pub fn observer_seat_enabled() -> bool {
+    std::env::var(format!("PLAYER_{}_SEAT", OBSERVER_NAME)).is_ok()
+}
+
+/// The port exposed to the given player
+pub fn player_seat_port(num: u8) -> u16 {
+    get_external_port(&num.to_string())
 }
 
-fn get_external_port(num: u8) -> u16 {
-    let env_var = format!("PLAYER_{}_SEAT", num);
+fn get_external_port(name: &str) -> u16 {
+    let env_var = format!("PLAYER_{}_SEAT", name);
     let value = std::env::var(&env_var).unwrap_or_else(|_| {
         panic!("Missing {} environment variable", env_var);
     });
@@ -32,9 +62,9 @@ fn get_external_port(num: u8) -> u16 {
     })
 }
 
-fn get_pass_port(num: u8) -> u32 {
-    let env_var = format!("PLAYER_{}_PASS", num);
-    let value = std::env::var(&env_var).unwrap_or_else(|_| get_external_port(num).to_string());
+fn get_pass_port(name: &str) -> u32 {
+    let env_var = format!("PLAYER_{}_PASS", name);
+    let value = std::env::var(&env_var).unwrap_or_else(|_| get_external_port(name).to_string());
     value.parse().unwrap_or_else(|_| {
         panic!("Invalid {} environment variable", env_var);
     })
@@ -0,0 +1,203 @@
+//! Offline replay of recorded bot protocols.
+//!
+//! The replayer connects to the player seats like a bot and sends the recorded requests,
+//! so they pass through the same controller as in the original match.
+//! Each recorded response is compared with the replayed one and the first divergence is reported.
+
+use crate::game::request_filter::oneof_field_name;
+use crate::websocket::protocol_recorder::{Direction, RecordedMessage};
+use crate::ws_routes::connect;
+use common::PlayerNum;
+use futures_util::{SinkExt, StreamExt};
+use protobuf::Message;
+use sc2_proto::sc2api::{Request, Response};
+use std::fmt;
+use tokio_tungstenite::tungstenite::Message as TMessage;
+use tracing::{debug, info};
+
+/// Maximum number of characters of a message shown in a divergence
+const MAX_MESSAGE_CHARS: usize = 500;
+
+#[derive(Debug)]
+pub enum ReplayOutcome {
+    Completed { requests: usize },
+    Diverged(Divergence),
+    Failed(String),
+}
+
+/// The first request with a response that differs from the recording
+#[derive(Debug)]
+pub struct Divergence {
+    pub player_num: PlayerNum,
+    pub request_num: usize,
+    pub request: String,
+    pub recorded: String,
+    pub replayed: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} diverged at request {}\nRequest: {}\nRecorded: {}\nReplayed: {}",
+            self.player_num, self.request_num, self.request, self.recorded, self.replayed
+        )
+    }
+}
+
+/// The parts of a response that are compared with the recording.
+/// Unit data differs between SC2 runs, so only the outcome of each request is compared.
+#[derive(Debug, PartialEq)]
+struct ResponseSummary {
+    kind: Option<String>,
+    error: Vec<String>,
+    status: Option<String>,
+    game_loop: Option<u32>,
+    action_results: Vec<String>,
+    player_results: Vec<String>,
+}
+
+impl ResponseSummary {
+    fn new(response: &Response) -> Self {
+        let kind = oneof_field_name(response, "response");
+        let observation = response.has_observation().then(|| response.observation());
+
+        Self {
+            kind,
+            error: response.error.clone(),
+            status: response.status.map(|status| format!("{:?}", status)),
+            game_loop: observation.map(|o| o.observation.game_loop()),
+            action_results: response
+                .action()
+                .result
+                .iter()
+                .map(|result| format!("{:?}", result))
+                .collect(),
+            player_results: observation
+                .map(|o| o.player_result.iter().map(|r| format!("{:?}", r)).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Replays the recording of a player against the player seat on the given port
+pub async fn replay_recording(
+    player_num: PlayerNum,
+    port: u16,
+    recording: Vec<RecordedMessage>,
+) -> ReplayOutcome {
+    let mut ws = match connect(port).await {
+        Some(ws) => ws,
+        None => return ReplayOutcome::Failed(format!("Could not connect to port {}", port)),
+    };
+
+    let mut messages = recording.into_iter().peekable();
+    let mut request_num = 0;
+    while let Some(message) = messages.next() {
+        if message.direction != Direction::Request {
+            continue;
+        }
+        let recorded = messages.next_if(|m| m.direction == Direction::Response);
+        request_num += 1;
+
+        let request = match Request::parse_from_bytes(&message.payload) {
+            Ok(request) => request,
+            Err(e) => return ReplayOutcome::Failed(format!("Invalid recorded request: {:?}", e)),
+        };
+        debug!("Replaying request {} of {:?}", request_num, player_num);
+
+        let divergence = |recorded: String, replayed: String| {
+            ReplayOutcome::Diverged(Divergence {
+                player_num,
+                request_num,
+                request: truncate(&request),
+                recorded,
+                replayed,
+            })
+        };
+
+        if let Err(e) = ws.send(TMessage::binary(message.payload)).await {
+            return divergence(
+                "Request accepted".to_string(),
+                format!("Request failed: {:?}", e),
+            );
+        }
+
+        let replayed = match ws.next().await {
+            Some(Ok(TMessage::Binary(bytes))) => bytes,
+            other => {
+                return divergence("Response".to_string(), format!("No response: {:?}", other))
+            }
+        };
+
+        if let Some(recorded) = recorded {
+            let recorded = Response::parse_from_bytes(&recorded.payload);
+            let replayed = Response::parse_from_bytes(&replayed);
+            match (recorded, replayed) {
+                (Ok(recorded), Ok(replayed)) => {
+                    if ResponseSummary::new(&recorded) != ResponseSummary::new(&replayed) {
+                        return divergence(truncate(&recorded), truncate(&replayed));
+                    }
+                }
+                (Err(e), _) => {
+                    return ReplayOutcome::Failed(format!("Invalid recorded response: {:?}", e))
+                }
+                (_, Err(e)) => {
+                    return divergence("Response".to_string(), format!("Invalid response: {:?}", e))
+                }
+            }
+        }
+    }
+
+    let _ = ws.close(None).await;
+    info!("Replayed {} requests of {:?}", request_num, player_num);
+    ReplayOutcome::Completed {
+        requests: request_num,
+    }
+}
+
+fn truncate(message: &impl fmt::Display) -> String {
+    message
+        .to_string()
+        .chars()
+        .take(MAX_MESSAGE_CHARS)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sc2_proto::sc2api::{ResponseObservation, ResponsePing, Status};
+
+    fn observation_response(game_loop: u32) -> Response {
+        let mut observation = ResponseObservation::new();
+        observation.mut_observation().set_game_loop(game_loop);
+        let mut response = Response::new();
+        response.set_observation(observation);
+        response.set_status(Status::in_game);
+        response
+    }
+
+    #[test]
+    fn test_equal_responses() {
+        assert_eq!(
+            ResponseSummary::new(&observation_response(22)),
+            ResponseSummary::new(&observation_response(22))
+        );
+    }
+
+    #[test]
+    fn test_diverging_responses() {
+        let recorded = ResponseSummary::new(&observation_response(22));
+        assert_ne!(recorded, ResponseSummary::new(&observation_response(23)));
+
+        let mut ping = Response::new();
+        ping.set_ping(ResponsePing::new());
+        ping.set_status(Status::in_game);
+        assert_ne!(recorded, ResponseSummary::new(&ping));
+
+        let mut error = observation_response(22);
+        error.error.push("Game has already ended".to_string());
+        assert_ne!(recorded, ResponseSummary::new(&error));
+    }
+}
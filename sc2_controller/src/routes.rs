@@ -1,22 +1,52 @@
-use crate::player_seats::PlayerSeat;
+use crate::events::join_state;
+use crate::observer_slot::register_observer_seat;
+use crate::player_seats::{observer_seat_enabled, PlayerSeat};
+use crate::status::register_seat;
+use crate::supervisor::{register_unused_seat, start_sc2_process};
 use crate::ws_routes::websocket_handler;
 use anyhow::{anyhow, Result};
 use axum::routing::get;
 use axum::Router;
-use common::paths;
+use common::models::sc2_controller::SeatState;
 use common::portpicker::pick_unused_port_in_range;
 use std::net::SocketAddr;
 use std::str::FromStr;
-use tempfile::TempDir;
 use tokio::task::JoinHandle;
 
 pub async fn open_player_seat(player_num: u8) -> Result<JoinHandle<()>> {
-    // TODO: Use fixed ports instead
-    let port = pick_unused_port_in_range(9000..10000)
-        .ok_or_else(|| anyhow!("Could not allocate port".to_string()))?;
+    let player_seat = PlayerSeat::new(player_num, pick_internal_port()?);
+    open_seat(player_seat).await
+}
 
-    let player_seat = PlayerSeat::new(player_num, port);
+/// Opens the seat of player 2 without an SC2 process.
+/// The built-in AI plays in the SC2 process of player 1, but the port stays open for health checks.
+pub async fn open_unused_player_seat(player_num: u8) -> Result<JoinHandle<()>> {
+    let player_seat = PlayerSeat::new(player_num, pick_internal_port()?);
+    register_seat(&player_seat, SeatState::Unused);
+    register_unused_seat(&player_seat);
+    start_ws_server(&player_seat)
+        .await
+        .map_err(|e| anyhow!("Failed to start WebSocket server: {e}"))
+}
 
+/// Opens the seat for a spectator client, if it is enabled
+pub async fn open_observer_seat() -> Result<Option<JoinHandle<()>>> {
+    if !observer_seat_enabled() {
+        return Ok(None);
+    }
+    let player_seat = PlayerSeat::observer(pick_internal_port()?);
+    register_observer_seat(&player_seat);
+    open_seat(player_seat).await.map(Some)
+}
+
+fn pick_internal_port() -> Result<u16> {
+    // TODO: Use fixed ports instead
+    pick_unused_port_in_range(9000..10000)
+        .ok_or_else(|| anyhow!("Could not allocate port".to_string()))
+}
+
+async fn open_seat(player_seat: PlayerSeat) -> Result<JoinHandle<()>> {
+    register_seat(&player_seat, SeatState::Open);
     start_sc2_process(&player_seat)
         .await
         .map_err(|e| anyhow!("Failed to start SC2 process: {e}"))?;
@@ -28,56 +58,11 @@ pub async fn open_player_seat(player_num: u8) -> Result<JoinHandle<()>> {
     Ok(ws_server)
 }
 
-async fn start_sc2_process(player_seat: &PlayerSeat) -> Result<()> {
-    let tempdir = TempDir::new().map_err(|e| anyhow!("Could not create temp dir: {e:?}"))?;
-
-    // TODO: Move to logging module
-    let stdout_path = format!("/logs/stdout-{}.log", player_seat.external_port);
-    let stdout_file = std::fs::File::create(&stdout_path)
-        .map_err(|e| anyhow!("Could not create stdout file: {e:?}"))?;
-    let stdout = async_process::Stdio::from(stdout_file);
-    let stderr_path = format!("/logs/stderr-{}.log", player_seat.external_port);
-    let stderr_file = std::fs::File::create(&stderr_path)
-        .map_err(|e| anyhow!("Could not create stderr file: {e:?}"))?;
-    let stderr = async_process::Stdio::from(stderr_file);
-
-    if let Ok(executable) = paths::executable() {
-        let process_result = (async_process::Command::new(executable)
-            .stdout(stdout)
-            .stderr(stderr)
-            .arg("-listen")
-            .arg("0.0.0.0")
-            .arg("-port")
-            .arg(player_seat.internal_port.to_string())
-            .arg("-dataDir")
-            .arg(paths::base_dir().to_str().unwrap())
-            .arg("-displayMode")
-            .arg("0")
-            .arg("-tempDir")
-            .arg(tempdir.path().to_str().unwrap())
-            .current_dir(paths::cwd_dir()))
-        .spawn();
-
-        match process_result {
-            Ok(_) => {
-                tracing::info!(
-                    "SC2 process for player seat {:?} started at port {:?}",
-                    &player_seat.external_port,
-                    &player_seat.internal_port
-                );
-                Ok(())
-            }
-            Err(e) => Err(anyhow!("Failed to start SC2 process: {e}")),
-        }
-    } else {
-        Err(anyhow!("Could not find SC2 executable"))
-    }
-}
-
 async fn start_ws_server(player_seat: &PlayerSeat) -> Result<JoinHandle<()>> {
     let addr = SocketAddr::from_str(&format!("0.0.0.0:{}", player_seat.external_port)).unwrap();
     let app = Router::new()
         .route("/sc2api", get(websocket_handler))
+        .route("/join_state", get(join_state))
         .with_state(player_seat.clone());
     let ws_server =
         axum::Server::bind(&addr).serve(app.into_make_service_with_connect_info::<SocketAddr>());
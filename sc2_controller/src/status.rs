@@ -0,0 +1,196 @@
+//! Control port of the game controller with the health, status and stats of the player seats
+
+use crate::game::game_result::{GameResult, GAME_RESULT};
+use crate::player_seats::PlayerSeat;
+use crate::supervisor::process_map;
+use anyhow::{anyhow, Result};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use common::api::process::{stats, stats_host, status};
+use common::models::aiarena::aiarena_match::MatchRequest;
+use common::models::sc2_controller::{ControllerStatus, SeatState, SeatStatus};
+use common::paths::match_layout;
+use common::PlayerNum;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+/// The seats by their external port
+static SEATS: Lazy<RwLock<BTreeMap<u16, SeatStatus>>> = Lazy::new(|| RwLock::new(BTreeMap::new()));
+
+/// Whether all seats have been opened
+static SEATS_READY: Lazy<RwLock<bool>> = Lazy::new(|| RwLock::new(false));
+
+/// Opens the control port given by `CONTROL_PORT`, if it is set
+pub async fn open_control_port() -> Result<Option<JoinHandle<()>>> {
+    let port = match std::env::var("CONTROL_PORT") {
+        Ok(port) => port
+            .parse::<u16>()
+            .map_err(|e| anyhow!("Invalid CONTROL_PORT environment variable: {e}"))?,
+        Err(_) => return Ok(None),
+    };
+
+    let app = Router::new()
+        .route("/health", get(health))
+        .route("/status", get(controller_status))
+        .route("/status/:port", get(status))
+        .route("/stats/host", get(stats_host))
+        .route("/stats/:port", get(stats))
+        .route("/result", get(result))
+        .with_state(process_map());
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let server = axum::Server::try_bind(&addr)
+        .map_err(|e| anyhow!("Failed to open control port {port}: {e}"))?
+        .serve(app.into_make_service());
+
+    info!("Control port opened on {}", addr);
+    Ok(Some(tokio::spawn(async move {
+        if let Err(e) = server.await {
+            error!("Control port failed: {:?}", e);
+        }
+    })))
+}
+
+pub fn register_seat(player_seat: &PlayerSeat, state: SeatState) {
+    let player_num = match player_seat.player_num {
+        1 => Some(PlayerNum::One),
+        2 => Some(PlayerNum::Two),
+        _ => None,
+    };
+    SEATS.write().insert(
+        player_seat.external_port,
+        SeatStatus {
+            player_num,
+            port: player_seat.external_port,
+            state,
+            pid: None,
+            game_loop: None,
+        },
+    );
+}
+
+/// Opens all seats for a new match. Seat 2 is not used when player 2 is the built-in AI.
+pub fn reset_seats(builtin_ai: bool) {
+    for seat in SEATS.write().values_mut() {
+        seat.state = if builtin_ai && seat.player_num == Some(PlayerNum::Two) {
+            SeatState::Unused
+        } else {
+            SeatState::Open
+        };
+        seat.game_loop = None;
+    }
+}
+
+pub fn set_seats_ready() {
+    *SEATS_READY.write() = true;
+}
+
+pub fn seat_state(port: u16) -> Option<SeatState> {
+    SEATS.read().get(&port).map(|seat| seat.state)
+}
+
+/// Whether a player has joined the game, even if it has left since
+pub fn any_player_joined() -> bool {
+    SEATS.read().values().any(|seat| {
+        seat.player_num.is_some() && matches!(seat.state, SeatState::Joined | SeatState::Finished)
+    })
+}
+
+/// Sets the state of the seat on the port, unless the seat is not used
+pub fn set_seat_state(port: u16, state: SeatState) {
+    update_seat(
+        |seat| seat.port == port && seat.state != SeatState::Unused,
+        |seat| seat.state = state,
+    );
+}
+
+pub fn set_player_state(player_num: PlayerNum, state: SeatState) {
+    update_seat(
+        |seat| seat.player_num == Some(player_num),
+        |seat| seat.state = state,
+    );
+}
+
+/// Sets the game loop of the player, as seen in its last observation
+pub fn set_game_loop(player_num: PlayerNum, game_loop: u32) {
+    update_seat(
+        |seat| seat.player_num == Some(player_num),
+        |seat| seat.game_loop = Some(game_loop),
+    );
+}
+
+fn update_seat(filter: impl Fn(&SeatStatus) -> bool, update: impl FnOnce(&mut SeatStatus)) {
+    if let Some(seat) = SEATS.write().values_mut().find(|seat| filter(seat)) {
+        update(seat);
+    }
+}
+
+/// Ready once all seats have been opened
+async fn health() -> impl IntoResponse {
+    if *SEATS_READY.read() {
+        (StatusCode::OK, "Ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "Starting")
+    }
+}
+
+async fn controller_status() -> Json<ControllerStatus> {
+    let process_map = process_map();
+    let processes = process_map.read();
+    let seats = SEATS
+        .read()
+        .values()
+        .map(|seat| SeatStatus {
+            pid: processes.get(&seat.port).map(async_process::Child::id),
+            ..seat.clone()
+        })
+        .collect();
+
+    Json(ControllerStatus {
+        match_id: MatchRequest::try_read(match_layout())
+            .map(|match_request| match_request.match_id),
+        seats,
+    })
+}
+
+async fn result() -> Json<GameResult> {
+    Json(GAME_RESULT.read().unwrap().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seat_states() {
+        let seat = PlayerSeat {
+            player_num: 1,
+            pass_port: 0,
+            external_port: 1,
+            internal_port: 0,
+        };
+        let unused_seat = PlayerSeat {
+            player_num: 2,
+            external_port: 2,
+            ..seat.clone()
+        };
+        register_seat(&seat, SeatState::Open);
+        register_seat(&unused_seat, SeatState::Unused);
+
+        set_seat_state(1, SeatState::Connected);
+        set_seat_state(2, SeatState::Connected);
+        assert_eq!(seat_state(1), Some(SeatState::Connected));
+        assert_eq!(seat_state(2), Some(SeatState::Unused));
+
+        set_player_state(PlayerNum::One, SeatState::Joined);
+        set_game_loop(PlayerNum::One, 22);
+        assert_eq!(seat_state(1), Some(SeatState::Joined));
+        assert_eq!(SEATS.read()[&1].game_loop, Some(22));
+    }
+}
@@ -0,0 +1,315 @@
+//! Supervision of the SC2 processes of the player seats.
+//!
+//! The supervisor owns each SC2 process and its temp dir for the whole match.
+//! If SC2 exits before a bot joined, the match ends with an SC2 crash, unless `restart_sc2`
+//! allows one restart while no bot has joined yet. Processes of a finished match are replaced
+//! when the next match starts.
+
+use crate::game::game_result::GAME_RESULT;
+use crate::game::player_result::PlayerResult;
+use crate::game::sc2_result::Sc2Result;
+use crate::player_seats::PlayerSeat;
+use crate::status::{any_player_joined, seat_state};
+use crate::ws_routes::store_game_result;
+use anyhow::{anyhow, Result};
+use common::api::process::ProcessMap;
+use common::models::aiarena::aiarena_match::MatchRequest;
+use common::models::sc2_controller::SeatState;
+use common::paths;
+use common::paths::match_layout;
+use common::PlayerNum;
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::time::sleep;
+use tracing::{error, info};
+
+/// Interval for checking whether the SC2 processes are still running
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Number of lines of the stderr log of SC2 that are logged when it exits
+const STDERR_TAIL_LINES: usize = 20;
+
+/// The SC2 processes by the external port of their seat
+static PROCESSES: Lazy<ProcessMap> = Lazy::new(|| Arc::new(RwLock::new(HashMap::new())));
+
+/// The supervised seats by their external port
+static SUPERVISED: Lazy<Mutex<HashMap<u16, Supervised>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct Supervised {
+    player_seat: PlayerSeat,
+    temp_dir: Option<TempDir>,
+    restarts: u32,
+    /// Incremented when the process is replaced for a new match, which ends its supervision
+    generation: u32,
+}
+
+pub fn process_map() -> ProcessMap {
+    PROCESSES.clone()
+}
+
+/// Starts the SC2 process of the seat and supervises it until the process exits or is killed
+pub async fn start_sc2_process(player_seat: &PlayerSeat) -> Result<()> {
+    spawn_sc2_process(player_seat)?;
+    tokio::spawn(supervise(player_seat.clone(), 0));
+    Ok(())
+}
+
+/// Registers a seat without an SC2 process, which a later match may need
+pub fn register_unused_seat(player_seat: &PlayerSeat) {
+    SUPERVISED.lock().insert(
+        player_seat.external_port,
+        Supervised {
+            player_seat: player_seat.clone(),
+            temp_dir: None,
+            restarts: 0,
+            generation: 0,
+        },
+    );
+}
+
+/// Prepares the SC2 processes for a new match.
+/// Processes that are still waiting for their first game are reused, the others are restarted.
+/// The seat of player 2 has no process when player 2 is the built-in AI.
+pub fn prepare_sc2_processes(builtin_ai: bool) {
+    let player_seats = SUPERVISED
+        .lock()
+        .values()
+        .map(|supervised| supervised.player_seat.clone())
+        .collect::<Vec<_>>();
+
+    for player_seat in player_seats {
+        let port = player_seat.external_port;
+        let needs_sc2 = !(builtin_ai && player_seat.player_num == 2);
+        let unused = matches!(seat_state(port), Some(SeatState::Open));
+        let running = PROCESSES
+            .write()
+            .get_mut(&port)
+            .is_some_and(|process| matches!(process.try_status(), Ok(None)));
+
+        let generation = {
+            let mut supervised = SUPERVISED.lock();
+            let Some(supervised) = supervised.get_mut(&port) else {
+                continue;
+            };
+            supervised.restarts = 0;
+            if needs_sc2 && running && unused {
+                continue;
+            }
+            supervised.generation += 1;
+            supervised.generation
+        };
+
+        if let Some(mut process) = PROCESSES.write().remove(&port) {
+            info!("Stopping SC2 process of player seat {}", port);
+            let _ = process.kill();
+        }
+        if needs_sc2 {
+            match spawn_sc2_process(&player_seat) {
+                Ok(()) => {
+                    tokio::spawn(supervise(player_seat, generation));
+                }
+                Err(e) => error!("Could not start SC2 process for the new match: {:?}", e),
+            }
+        } else if let Some(supervised) = SUPERVISED.lock().get_mut(&port) {
+            supervised.temp_dir = None;
+        }
+    }
+}
+
+/// Kills all SC2 processes and removes their temp dirs
+pub fn kill_all() {
+    for (port, mut process) in PROCESSES.write().drain() {
+        info!("Killing SC2 process of player seat {}", port);
+        if let Err(e) = process.kill() {
+            error!(
+                "Could not kill SC2 process of player seat {}: {:?}",
+                port, e
+            );
+        }
+    }
+    SUPERVISED.lock().clear();
+}
+
+fn spawn_sc2_process(player_seat: &PlayerSeat) -> Result<()> {
+    let temp_dir = TempDir::new().map_err(|e| anyhow!("Could not create temp dir: {e:?}"))?;
+
+    let stdout_path = match_layout().log_file(&format!("stdout-{}.log", player_seat.external_port));
+    let stdout_file = std::fs::File::create(&stdout_path)
+        .map_err(|e| anyhow!("Could not create stdout file: {e:?}"))?;
+    let stdout = async_process::Stdio::from(stdout_file);
+    let stderr_file = std::fs::File::create(stderr_path(player_seat))
+        .map_err(|e| anyhow!("Could not create stderr file: {e:?}"))?;
+    let stderr = async_process::Stdio::from(stderr_file);
+
+    let executable = paths::executable().map_err(|_| anyhow!("Could not find SC2 executable"))?;
+    let process = async_process::Command::new(executable)
+        .stdout(stdout)
+        .stderr(stderr)
+        .arg("-listen")
+        .arg("0.0.0.0")
+        .arg("-port")
+        .arg(player_seat.internal_port.to_string())
+        .arg("-dataDir")
+        .arg(paths::base_dir().to_str().unwrap())
+        .arg("-displayMode")
+        .arg("0")
+        .arg("-tempDir")
+        .arg(temp_dir.path().to_str().unwrap())
+        .current_dir(paths::cwd_dir())
+        .spawn()
+        .map_err(|e| anyhow!("Failed to start SC2 process: {e}"))?;
+
+    info!(
+        "SC2 process for player seat {:?} started at port {:?}",
+        &player_seat.external_port, &player_seat.internal_port
+    );
+
+    PROCESSES.write().insert(player_seat.external_port, process);
+    let mut supervised = SUPERVISED.lock();
+    let supervised = supervised
+        .entry(player_seat.external_port)
+        .or_insert_with(|| Supervised {
+            player_seat: player_seat.clone(),
+            temp_dir: None,
+            restarts: 0,
+            generation: 0,
+        });
+    // The temp dir of a previous process is removed only now that it has exited
+    supervised.temp_dir = Some(temp_dir);
+    Ok(())
+}
+
+async fn supervise(player_seat: PlayerSeat, generation: u32) {
+    let port = player_seat.external_port;
+    loop {
+        sleep(POLL_INTERVAL).await;
+
+        let current_generation = SUPERVISED.lock().get(&port).map(|s| s.generation);
+        if current_generation != Some(generation) {
+            // The process was replaced for a new match
+            return;
+        }
+
+        let exit_status = match PROCESSES.write().get_mut(&port).map(|p| p.try_status()) {
+            // The process was killed on shutdown
+            None => return,
+            Some(Ok(None)) => continue,
+            Some(Ok(Some(exit_status))) => exit_status,
+            Some(Err(e)) => {
+                error!(
+                    "Could not check SC2 process of player seat {}: {:?}",
+                    port, e
+                );
+                return;
+            }
+        };
+
+        error!(
+            "SC2 process of player seat {} exited with {}\n{}",
+            port,
+            exit_status,
+            stderr_tail(&player_seat)
+        );
+
+        if can_restart(&player_seat) {
+            if let Some(supervised) = SUPERVISED.lock().get_mut(&port) {
+                supervised.restarts += 1;
+            }
+            match spawn_sc2_process(&player_seat) {
+                Ok(()) => {
+                    info!("SC2 process of player seat {} restarted", port);
+                    continue;
+                }
+                Err(e) => error!("Could not restart SC2 process: {:?}", e),
+            }
+        }
+
+        report_crash(&player_seat, exit_status);
+        return;
+    }
+}
+
+/// SC2 is restarted once, if enabled and no bot has joined the game or connected to the seat yet
+fn can_restart(player_seat: &PlayerSeat) -> bool {
+    let restart_sc2 = MatchRequest::try_read(match_layout()).is_some_and(|r| r.restart_sc2);
+    let restarts = SUPERVISED
+        .lock()
+        .get(&player_seat.external_port)
+        .map_or(0, |s| s.restarts);
+    restart_sc2
+        && restarts == 0
+        && seat_state(player_seat.external_port) == Some(SeatState::Open)
+        && !any_player_joined()
+}
+
+/// Ends the match with an SC2 crash, unless the player already joined.
+/// After joining, the player notices the crash itself.
+fn report_crash(player_seat: &PlayerSeat, exit_status: ExitStatus) {
+    let player_num = match player_seat.player_num {
+        1 => PlayerNum::One,
+        2 => PlayerNum::Two,
+        _ => return,
+    };
+    let state = seat_state(player_seat.external_port);
+    if matches!(state, Some(SeatState::Joined | SeatState::Finished)) {
+        return;
+    }
+    let Some(match_request) = MatchRequest::try_read(match_layout()) else {
+        return;
+    };
+
+    let match_id = match_request.match_id;
+    let exit_code = exit_status
+        .code()
+        .map_or_else(|| "unknown".to_string(), |code| code.to_string());
+    let player_result = PlayerResult {
+        game_loops: 0,
+        frame_time: 0.0,
+        player_id: 0,
+        tags: indexmap::IndexSet::from([format!("SC2 exited with code {}", exit_code)]),
+        step_time_stats: None,
+        result: Sc2Result::SC2Crash,
+    };
+    {
+        let mut game_result = GAME_RESULT.write().unwrap();
+        game_result.set(match_id);
+        game_result.add_player_result(match_id, player_num, player_result);
+        game_result.set_error(match_id);
+    }
+    store_game_result(match_id);
+}
+
+fn stderr_path(player_seat: &PlayerSeat) -> PathBuf {
+    match_layout().log_file(&format!("stderr-{}.log", player_seat.external_port))
+}
+
+/// The last lines of the stderr log of the SC2 process
+fn stderr_tail(player_seat: &PlayerSeat) -> String {
+    match std::fs::read_to_string(stderr_path(player_seat)) {
+        Ok(stderr) => last_lines(&stderr, STDERR_TAIL_LINES),
+        Err(e) => format!("Could not read stderr log: {:?}", e),
+    }
+}
+
+fn last_lines(text: &str, count: usize) -> String {
+    let lines = text.lines().collect::<Vec<_>>();
+    lines[lines.len().saturating_sub(count)..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_lines() {
+        assert_eq!(last_lines("a\nb\nc\n", 2), "b\nc");
+        assert_eq!(last_lines("a\nb", 5), "a\nb");
+        assert_eq!(last_lines("", 5), "");
+    }
+}
@@ -1,4 +1,5 @@
 pub mod errors;
 pub mod player;
 pub mod port_config;
+pub mod protocol_recorder;
 pub mod runtime_vars;
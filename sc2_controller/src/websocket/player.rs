@@ -1,22 +1,30 @@
+use crate::events::{report_game_started, report_player_joined};
 use crate::game::game_config::GameConfig;
 use crate::game::player_data::PlayerData;
 use crate::game::player_result::PlayerResult;
+use crate::game::request_filter::RequestFilter;
+use crate::game::request_rate_policy::RateLimitViolation;
 use crate::game::sc2_result::Sc2Result;
+use crate::status::{set_game_loop, set_player_state};
 use crate::websocket::errors::player_error::PlayerError;
 use crate::websocket::port_config::PortConfig;
+use crate::websocket::protocol_recorder::ProtocolRecorder;
 use crate::websocket::runtime_vars::RuntimeVars;
 use axum::extract::ws::{Message as AMessage, WebSocket};
+use common::configuration::ac_config::RealTimeLimitResult;
 use common::models::aiarena::bot_race::BotRace;
+use common::models::aiarena::builtin_ai::{AiBuild, AiDifficulty, BuiltinAi};
+use common::models::sc2_controller::SeatState;
 use common::PlayerNum;
 use futures_util::{SinkExt, StreamExt};
 use protobuf::{EnumOrUnknown, Message, MessageField};
 use sc2_proto::common::Race;
 use sc2_proto::sc2api::{
     Request, RequestJoinGame, RequestLeaveGame, RequestPing, RequestSaveReplay, Response,
-    ResponseDebug, Status,
+    ResponseAction, ResponseDebug, ResponseQuery, Status,
 };
 use std::path::PathBuf;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
@@ -30,6 +38,7 @@ pub struct Player {
     sc2_ws: WebSocketStream<TcpStream>,
     bot_ws_timeout: Duration,
     sc2_ws_timeout: Duration,
+    recorder: Option<ProtocolRecorder>,
 }
 
 impl Player {
@@ -39,9 +48,15 @@ impl Player {
             sc2_ws,
             bot_ws_timeout: Duration::from_secs(30),
             sc2_ws_timeout: Duration::from_secs(60),
+            recorder: None,
         }
     }
 
+    /// Records the requests and responses of the bot from now on
+    pub fn record_protocol(&mut self, recorder: ProtocolRecorder) {
+        self.recorder = Some(recorder);
+    }
+
     /// Receive a message from the client
     /// Returns None if the connection is already closed
     pub async fn bot_recv_message(&mut self) -> Result<AMessage, PlayerError> {
@@ -72,11 +87,13 @@ impl Player {
             "Response to client: [{}]",
             format!("{r:?}").chars().take(10).collect::<String>()
         );
+        let bytes = r.write_to_bytes().expect("Invalid protobuf message");
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record_response(&bytes);
+        }
         timeout(
             self.bot_ws_timeout,
-            self.bot_send_message(AMessage::Binary(
-                r.write_to_bytes().expect("Invalid protobuf message"),
-            )),
+            self.bot_send_message(AMessage::Binary(bytes)),
         )
         .await
         .map_err(|_| PlayerError::BotTimeout(self.bot_ws_timeout))
@@ -101,10 +118,13 @@ impl Player {
     pub async fn bot_recv_request(&mut self) -> Result<Request, PlayerError> {
         match self.bot_recv_message().await? {
             AMessage::Binary(bytes) => {
-                let resp =
+                let req: Request =
                     Message::parse_from_bytes(&bytes).map_err(PlayerError::ProtoParseError)?;
-                trace!("Message from client parsed:\n{}", &resp);
-                Ok(resp)
+                trace!("Message from client parsed:\n{}", &req);
+                if let Some(recorder) = self.recorder.as_mut() {
+                    recorder.record_request(&bytes, req.has_observation());
+                }
+                Ok(req)
             }
             other => Err(PlayerError::BotUnexpectedMessage(other)),
         }
@@ -157,7 +177,13 @@ impl Player {
         request
     }
 
-    pub async fn create_game(&mut self, map: &str, realtime: bool) -> Result<(), PlayerError> {
+    pub async fn create_game(
+        &mut self,
+        map: &str,
+        realtime: bool,
+        builtin_ai: Option<BuiltinAi>,
+        with_observer: bool,
+    ) -> Result<(), PlayerError> {
         let ping_request = create_ping_request();
         for _ in 0..10 {
             match self.sc2_query(&ping_request).await {
@@ -171,7 +197,14 @@ impl Player {
             }
         }
         // Craft CreateGame request
-        let player_configs: Vec<CreateGamePlayer> = vec![CreateGamePlayer::Participant; 2];
+        let mut player_configs: Vec<CreateGamePlayer> = vec![CreateGamePlayer::Participant];
+        player_configs.push(match builtin_ai {
+            Some(builtin_ai) => CreateGamePlayer::Computer(builtin_ai),
+            None => CreateGamePlayer::Participant,
+        });
+        if with_observer {
+            player_configs.push(CreateGamePlayer::Observer);
+        }
 
         // Send CreateGame request to first procs
         let proto = Self::proto_create_game(&player_configs, map, realtime);
@@ -306,12 +339,11 @@ impl Player {
             false
         }
     }
+    /// Forwards the join request of the bot to SC2, calling `on_join` before SC2 answers it
     async fn wait_for_join_game(
         &mut self,
-        port_config: PortConfig,
-        config: &GameConfig,
-        player_num: PlayerNum,
-        player_pass: u32,
+        join_request: impl Fn(&Request) -> Option<Request>,
+        on_join: impl FnOnce(),
     ) -> Result<Option<u32>, PlayerError> {
         loop {
             let msg = self.bot_recv_request().await?;
@@ -322,19 +354,16 @@ impl Player {
                 let resp = self.sc2_query(&msg).await?;
                 self.bot_send_response(&resp).await?;
             } else if msg.has_join_game() {
-                let req_raw = proto_join_game_participant(
-                    &msg,
-                    &port_config,
-                    config,
-                    player_num,
-                    player_pass,
-                );
+                let req_raw = join_request(&msg);
 
                 if req_raw.is_none() {
                     return Err(PlayerError::NoMessageAvailable);
                 }
 
-                let resp = self.sc2_query(&req_raw.unwrap()).await?;
+                // SC2 answers the join requests once all players joined
+                self.sc2_send_request(&req_raw.unwrap()).await?;
+                on_join();
+                let resp = self.sc2_recv_response().await?;
                 self.bot_send_response(&resp).await?;
 
                 let ping_request = create_ping_request();
@@ -368,17 +397,69 @@ impl Player {
         let mut response: Response;
 
         r_vars.player_id = self
-            .wait_for_join_game(port_config, &config, player_num, player_pass)
+            .wait_for_join_game(
+                |msg| {
+                    proto_join_game_participant(msg, &port_config, &config, player_num, player_pass)
+                },
+                || report_player_joined(&config, player_num),
+            )
             .await?;
+        set_player_state(player_num, SeatState::Joined);
+        report_game_started(&config);
+        r_vars.start_real_time_limit(config.max_real_time);
 
         loop {
-            match self.bot_recv_request().await {
+            if r_vars.real_time_exceeded() {
+                return Ok(self.leave_on_real_time_limit(r_vars, &config).await);
+            }
+
+            let bot_request = match r_vars.real_time_left() {
+                Some(time_left) => match timeout(time_left, self.bot_recv_request()).await {
+                    Ok(bot_request) => bot_request,
+                    Err(_) => return Ok(self.leave_on_real_time_limit(r_vars, &config).await),
+                },
+                None => self.bot_recv_request().await,
+            };
+
+            match bot_request {
                 Ok(mut request) => {
                     r_vars.record_frame_time();
 
+                    if request.has_step() {
+                        if let Some(violation) = r_vars.end_step() {
+                            error!("{}", violation);
+                            self.save_replay(r_vars.replay_path()).await;
+                            r_vars.record_avg_frame_time();
+                            r_vars.add_violation_tag(&violation);
+                            let mut request = Request::new();
+                            let leave_game = RequestLeaveGame::new();
+                            request.set_leave_game(leave_game);
+                            let _resp = self.sc2_query(&request).await;
+
+                            return Ok(r_vars.build_result(Sc2Result::Timeout));
+                        }
+                    }
+
                     if config.disable_debug && request.has_debug() {
                         let debug_response = create_empty_debug_response(&request);
                         self.bot_send_response(&debug_response).await?;
+                        r_vars.answered_locally();
+                        continue;
+                    } else if let Some(refused) = r_vars.request_filter.refused(&request) {
+                        error!("Refused request from the bot: {}", refused);
+                        r_vars.add_refused_request_tag(&refused);
+                        let refused_response = create_refused_response(&request, &refused);
+                        self.bot_send_response(&refused_response).await?;
+                        r_vars.answered_locally();
+                        continue;
+                    } else if let Some(violation) = r_vars
+                        .request_rate_policy
+                        .apply(&mut request, Instant::now())
+                    {
+                        debug!("{}", violation);
+                        let rejected_response = create_rejected_response(&request, &violation);
+                        self.bot_send_response(&rejected_response).await?;
+                        r_vars.answered_locally();
                         continue;
                     } else if request.has_leave_game() || request.has_quit() {
                         self.save_replay(r_vars.replay_path()).await;
@@ -424,6 +505,7 @@ impl Player {
 
                         let observation = response.observation();
                         r_vars.set_game_loops(observation.observation.game_loop());
+                        set_game_loop(player_num, r_vars.game_loops);
 
                         let observation_results = &observation.player_result;
 
@@ -506,13 +588,85 @@ impl Player {
             }
         }
     }
+
+    /// Proxies a spectator client in the observer slot until the game ends.
+    /// Debug and action requests are answered with empty responses,
+    /// so the observer can't influence the game.
+    pub async fn run_observer(
+        &mut self,
+        config: GameConfig,
+        port_config: PortConfig,
+        observer_pass: u32,
+        on_join: impl FnOnce(),
+    ) -> Result<(), PlayerError> {
+        self.bot_ws_timeout = Duration::from_secs(config.timeout_secs);
+        let request_filter = RequestFilter::observer();
+
+        self.wait_for_join_game(
+            |msg| proto_join_game_observer(msg, &port_config, observer_pass),
+            on_join,
+        )
+        .await?;
+
+        loop {
+            let request = self.bot_recv_request().await?;
+
+            if request.has_debug() {
+                let debug_response = create_empty_debug_response(&request);
+                self.bot_send_response(&debug_response).await?;
+                continue;
+            } else if request.has_action() {
+                debug!("Blocked action request from observer");
+                let action_response = create_empty_action_response(&request);
+                self.bot_send_response(&action_response).await?;
+                continue;
+            } else if let Some(refused) = request_filter.refused(&request) {
+                debug!("Refused request from observer: {}", refused);
+                let refused_response = create_refused_response(&request, &refused);
+                self.bot_send_response(&refused_response).await?;
+                continue;
+            }
+
+            let response = self.sc2_query(&request).await?;
+            self.bot_send_response(&response).await?;
+
+            if response.has_leave_game()
+                || response.has_quit()
+                || (response.has_observation() && !response.observation().player_result.is_empty())
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Saves the replay and leaves the game when the wall-clock limit of the match is reached
+    async fn leave_on_real_time_limit(
+        &mut self,
+        mut r_vars: RuntimeVars,
+        config: &GameConfig,
+    ) -> PlayerResult {
+        error!("Real time limit of {:?} reached", config.max_real_time);
+        self.save_replay(r_vars.replay_path()).await;
+        r_vars.record_avg_frame_time();
+        let mut request = Request::new();
+        let leave_game = RequestLeaveGame::new();
+        request.set_leave_game(leave_game);
+        let _resp = self.sc2_query(&request).await;
+
+        r_vars.add_real_time_limit_tag();
+        let result = match config.real_time_limit_result {
+            RealTimeLimitResult::Tie => Sc2Result::Tie,
+            RealTimeLimitResult::Error => Sc2Result::RealTimeLimit,
+        };
+        r_vars.build_result(result)
+    }
 }
 
 /// Used to pass player setup info to CreateGame
-#[allow(dead_code)]
 #[derive(Clone, Copy)]
 enum CreateGamePlayer {
     Participant,
+    Computer(BuiltinAi),
     Observer,
 }
 
@@ -524,6 +678,12 @@ impl CreateGamePlayer {
             Self::Participant => {
                 ps.type_ = Some(EnumOrUnknown::new(PlayerType::Participant));
             }
+            Self::Computer(builtin_ai) => {
+                ps.type_ = Some(EnumOrUnknown::new(PlayerType::Computer));
+                ps.race = Some(EnumOrUnknown::new(to_race(&builtin_ai.race)));
+                ps.difficulty = Some(EnumOrUnknown::new(to_difficulty(builtin_ai.difficulty)));
+                ps.ai_build = Some(EnumOrUnknown::new(to_ai_build(builtin_ai.build)));
+            }
             Self::Observer => {
                 ps.type_ = Some(EnumOrUnknown::new(PlayerType::Observer));
             }
@@ -562,6 +722,30 @@ fn proto_join_game_participant(
     Some(request)
 }
 
+fn proto_join_game_observer(
+    request: &Request,
+    port_config: &PortConfig,
+    observer_pass: u32,
+) -> Option<Request> {
+    let mut r_join_game = RequestJoinGame::new();
+    let player_data = PlayerData::from_join_request(request.join_game());
+
+    if !do_passes_match(player_data.pass_port, observer_pass) {
+        return None;
+    }
+
+    // Zero observes all players
+    r_join_game.set_observed_player_id(request.join_game().observed_player_id());
+    r_join_game.set_player_name("Observer".to_string());
+    r_join_game.options = MessageField::from_option(Some(player_data.interface_options));
+
+    port_config.apply_proto(&mut r_join_game);
+
+    let mut request = request.clone();
+    request.set_join_game(r_join_game);
+    Some(request)
+}
+
 fn do_passes_match(a: u32, b: u32) -> bool {
     // The player is allowed to provide the pass port with a small offset
     // because it gets the pass port with the "--StartPort" parameter and is expected
@@ -586,6 +770,34 @@ fn to_race(race: &BotRace) -> Race {
     }
 }
 
+fn to_difficulty(difficulty: AiDifficulty) -> sc2_proto::sc2api::Difficulty {
+    use sc2_proto::sc2api::Difficulty;
+    match difficulty {
+        AiDifficulty::VeryEasy => Difficulty::VeryEasy,
+        AiDifficulty::Easy => Difficulty::Easy,
+        AiDifficulty::Medium => Difficulty::Medium,
+        AiDifficulty::MediumHard => Difficulty::MediumHard,
+        AiDifficulty::Hard => Difficulty::Hard,
+        AiDifficulty::Harder => Difficulty::Harder,
+        AiDifficulty::VeryHard => Difficulty::VeryHard,
+        AiDifficulty::CheatVision => Difficulty::CheatVision,
+        AiDifficulty::CheatMoney => Difficulty::CheatMoney,
+        AiDifficulty::CheatInsane => Difficulty::CheatInsane,
+    }
+}
+
+fn to_ai_build(build: AiBuild) -> sc2_proto::sc2api::AIBuild {
+    use sc2_proto::sc2api::AIBuild;
+    match build {
+        AiBuild::RandomBuild => AIBuild::RandomBuild,
+        AiBuild::Rush => AIBuild::Rush,
+        AiBuild::Timing => AIBuild::Timing,
+        AiBuild::Power => AIBuild::Power,
+        AiBuild::Macro => AIBuild::Macro,
+        AiBuild::Air => AIBuild::Air,
+    }
+}
+
 fn create_empty_debug_response(request: &Request) -> Response {
     let mut debug_response = Response::new();
     let debug_response_debug = ResponseDebug::new();
@@ -595,6 +807,37 @@ fn create_empty_debug_response(request: &Request) -> Response {
     debug_response
 }
 
+fn create_empty_action_response(request: &Request) -> Response {
+    let mut action_response = Response::new();
+    action_response.set_id(request.id());
+    action_response.set_status(Status::in_game);
+    action_response.set_action(ResponseAction::new());
+    action_response
+}
+
+fn create_refused_response(request: &Request, refused: &str) -> Response {
+    let mut refused_response = Response::new();
+    refused_response.set_id(request.id());
+    refused_response.set_status(Status::in_game);
+    refused_response
+        .error
+        .push(format!("Request not allowed: {}", refused));
+    refused_response
+}
+
+fn create_rejected_response(request: &Request, violation: &RateLimitViolation) -> Response {
+    let mut rejected_response = Response::new();
+    rejected_response.set_id(request.id());
+    rejected_response.set_status(Status::in_game);
+    if request.has_query() {
+        rejected_response.set_query(ResponseQuery::new());
+    } else {
+        rejected_response.set_action(ResponseAction::new());
+    }
+    rejected_response.error.push(violation.to_string());
+    rejected_response
+}
+
 fn create_ping_request() -> Request {
     let mut request = Request::new();
     let ping = RequestPing::new();
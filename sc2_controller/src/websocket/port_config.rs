@@ -13,6 +13,7 @@ pub struct PortConfig {
     server_base: u16,
     client_game: u16,
     client_base: u16,
+    observer_client: Option<(u16, u16)>,
 }
 
 impl PortConfig {
@@ -24,9 +25,19 @@ impl PortConfig {
             server_base: pick_unused_port_in_range(9000..10000)?,
             client_game: pick_unused_port_in_range(9000..10000)?,
             client_base: pick_unused_port_in_range(9000..10000)?,
+            observer_client: None,
         })
     }
 
+    /// Add the client ports of an observer, which joins the game as a third client
+    pub fn with_observer(mut self) -> Option<Self> {
+        self.observer_client = Some((
+            pick_unused_port_in_range(9000..10000)?,
+            pick_unused_port_in_range(9000..10000)?,
+        ));
+        Some(self)
+    }
+
     /// Apply port configuration to a handler join request
     pub fn apply_proto(&self, req: &mut RequestJoinGame) {
         req.set_shared_port(self.shared as i32);
@@ -40,6 +51,13 @@ impl PortConfig {
         client_ps.set_game_port(self.client_game as i32);
         client_ps.set_base_port(self.client_base as i32);
         req.client_ports = vec![client_ps];
+
+        if let Some((game, base)) = self.observer_client {
+            let mut observer_ps = PortSet::new();
+            observer_ps.set_game_port(game as i32);
+            observer_ps.set_base_port(base as i32);
+            req.client_ports.push(observer_ps);
+        }
     }
 }
 
@@ -55,5 +73,16 @@ mod tests {
         port_config.apply_proto(&mut request);
         assert!(request.server_ports.is_some());
         assert!(request.has_shared_port());
+        assert_eq!(request.client_ports.len(), 1);
+    }
+
+    #[test]
+    fn test_portconfig_with_observer() {
+        let mut request = RequestJoinGame::new();
+        let port_config = PortConfig::new()
+            .and_then(PortConfig::with_observer)
+            .expect("Could not create port configuration");
+        port_config.apply_proto(&mut request);
+        assert_eq!(request.client_ports.len(), 2);
     }
 }
@@ -0,0 +1,276 @@
+//! Recording of the requests and responses between a bot and SC2
+//!
+//! The recording is a stream of length-delimited protobuf messages:
+//! ```protobuf
+//! message RecordedMessage {
+//!   uint64 timestamp_micros = 1; // Microseconds since the UNIX epoch
+//!   Direction direction = 2;
+//!   bytes payload = 3;           // Serialized sc2api.Request, sc2api.Response or match request
+//! }
+//! enum Direction {
+//!   REQUEST = 0;       // From the bot to SC2
+//!   RESPONSE = 1;      // From SC2 to the bot
+//!   MATCH_REQUEST = 2; // The match request as JSON, the first message of the recording
+//! }
+//! ```
+
+use protobuf::{CodedInputStream, CodedOutputStream};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{error, info};
+
+const TIMESTAMP_FIELD: u32 = 1;
+const DIRECTION_FIELD: u32 = 2;
+const PAYLOAD_FIELD: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Request = 0,
+    Response = 1,
+    MatchRequest = 2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedMessage {
+    pub timestamp_micros: u64,
+    pub direction: Direction,
+    pub payload: Vec<u8>,
+}
+
+pub struct ProtocolRecorder {
+    writer: BufWriter<File>,
+    max_bytes: u64,
+    written_bytes: u64,
+    observation_interval: u32,
+    observations: u32,
+    skip_response: bool,
+    stopped: bool,
+}
+
+impl ProtocolRecorder {
+    /// Creates the recording file.
+    /// Zero `max_bytes` records without a size limit.
+    /// An `observation_interval` of N records the response of only every Nth observation.
+    pub fn create(path: &Path, max_bytes: u64, observation_interval: u32) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        info!("Recording protocol to {:?}", path);
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            max_bytes,
+            written_bytes: 0,
+            observation_interval: observation_interval.max(1),
+            observations: 0,
+            skip_response: false,
+            stopped: false,
+        })
+    }
+
+    /// Records a serialized request of the bot
+    pub fn record_request(&mut self, payload: &[u8], is_observation: bool) {
+        if is_observation {
+            self.observations += 1;
+            self.skip_response = !self.observations.is_multiple_of(self.observation_interval);
+        } else {
+            self.skip_response = false;
+        }
+        self.record(Direction::Request, payload);
+    }
+
+    /// Records the serialized match request, so the recording can be replayed without it
+    pub fn record_match_request(&mut self, payload: &[u8]) {
+        self.record(Direction::MatchRequest, payload);
+    }
+
+    /// Records a serialized response to the last recorded request
+    pub fn record_response(&mut self, payload: &[u8]) {
+        if !std::mem::take(&mut self.skip_response) {
+            self.record(Direction::Response, payload);
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if let Err(e) = self.writer.flush() {
+            error!("Could not flush protocol recording: {:?}", e);
+        }
+    }
+
+    fn record(&mut self, direction: Direction, payload: &[u8]) {
+        if self.stopped {
+            return;
+        }
+        let message = encode_message(direction, payload);
+
+        if self.max_bytes > 0 && self.written_bytes + message.len() as u64 > self.max_bytes {
+            info!(
+                "Protocol recording stopped at its size limit of {} bytes",
+                self.max_bytes
+            );
+            self.stop();
+            return;
+        }
+
+        match self.writer.write_all(&message) {
+            Ok(()) => self.written_bytes += message.len() as u64,
+            Err(e) => {
+                error!("Could not write protocol recording: {:?}", e);
+                self.stop();
+            }
+        }
+    }
+
+    fn stop(&mut self) {
+        self.stopped = true;
+        self.flush();
+    }
+}
+
+impl Drop for ProtocolRecorder {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// Encodes a recorded message with its length prefix
+fn encode_message(direction: Direction, payload: &[u8]) -> Vec<u8> {
+    let timestamp_micros = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_micros() as u64);
+
+    let mut message = Vec::with_capacity(payload.len() + 16);
+    let mut os = CodedOutputStream::vec(&mut message);
+    os.write_uint64(TIMESTAMP_FIELD, timestamp_micros)
+        .and_then(|_| os.write_enum(DIRECTION_FIELD, direction as i32))
+        .and_then(|_| os.write_bytes(PAYLOAD_FIELD, payload))
+        .and_then(|_| os.flush())
+        .expect("Writing to a vector can't fail");
+    drop(os);
+
+    let mut delimited = Vec::with_capacity(message.len() + 5);
+    let mut os = CodedOutputStream::vec(&mut delimited);
+    os.write_raw_varint32(message.len() as u32)
+        .and_then(|_| os.write_raw_bytes(&message))
+        .and_then(|_| os.flush())
+        .expect("Writing to a vector can't fail");
+    drop(os);
+    delimited
+}
+
+/// Reads all messages of a recording
+pub fn read_recording(path: &Path) -> protobuf::Result<Vec<RecordedMessage>> {
+    let bytes = std::fs::read(path)?;
+    let mut is = CodedInputStream::from_bytes(&bytes);
+    let mut messages = Vec::new();
+    while !is.eof()? {
+        let len = is.read_raw_varint32()?;
+        let old_limit = is.push_limit(len as u64)?;
+        let mut message = RecordedMessage {
+            timestamp_micros: 0,
+            direction: Direction::Request,
+            payload: Vec::new(),
+        };
+        while let Some(tag) = is.read_raw_tag_or_eof()? {
+            match tag >> 3 {
+                TIMESTAMP_FIELD => message.timestamp_micros = is.read_uint64()?,
+                DIRECTION_FIELD => {
+                    message.direction = match is.read_int32()? {
+                        1 => Direction::Response,
+                        2 => Direction::MatchRequest,
+                        _ => Direction::Request,
+                    }
+                }
+                PAYLOAD_FIELD => message.payload = is.read_bytes()?,
+                field => {
+                    let error = format!("Unknown field {} in protocol recording", field);
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, error).into());
+                }
+            }
+        }
+        is.pop_limit(old_limit);
+        messages.push(message);
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_messages(path: &Path) -> Vec<(u64, Direction, Vec<u8>)> {
+        read_recording(path)
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.timestamp_micros, m.direction, m.payload))
+            .collect()
+    }
+
+    #[test]
+    fn test_records_requests_and_responses() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("protocol.sc2proto");
+        let mut recorder = ProtocolRecorder::create(&path, 0, 1).unwrap();
+        recorder.record_request(b"request", false);
+        recorder.record_response(b"response");
+        drop(recorder);
+
+        let messages = read_messages(&path);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].1, Direction::Request);
+        assert_eq!(messages[0].2, b"request");
+        assert_eq!(messages[1].1, Direction::Response);
+        assert_eq!(messages[1].2, b"response");
+        assert!(messages[0].0 > 0);
+    }
+
+    #[test]
+    fn test_samples_observations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("protocol.sc2proto");
+        let mut recorder = ProtocolRecorder::create(&path, 0, 3).unwrap();
+        for _ in 0..6 {
+            recorder.record_request(b"observation", true);
+            recorder.record_response(b"observation response");
+            recorder.record_request(b"action", false);
+            recorder.record_response(b"action");
+        }
+        drop(recorder);
+
+        let messages = read_messages(&path);
+        let count = |payload: &[u8]| messages.iter().filter(|m| m.2 == payload).count();
+        assert_eq!(count(b"observation"), 6);
+        assert_eq!(count(b"observation response"), 2);
+        assert_eq!(count(b"action"), 12);
+    }
+
+    #[test]
+    fn test_records_match_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("protocol.sc2proto");
+        let mut recorder = ProtocolRecorder::create(&path, 0, 1).unwrap();
+        recorder.record_match_request(b"match request");
+        recorder.record_request(b"request", false);
+        drop(recorder);
+
+        let messages = read_messages(&path);
+        assert_eq!(messages[0].1, Direction::MatchRequest);
+        assert_eq!(messages[0].2, b"match request");
+        assert_eq!(messages[1].1, Direction::Request);
+    }
+
+    #[test]
+    fn test_stops_at_size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("protocol.sc2proto");
+        let mut recorder = ProtocolRecorder::create(&path, 100, 1).unwrap();
+        for _ in 0..10 {
+            recorder.record_request(&[0; 20], false);
+        }
+        drop(recorder);
+
+        assert!(std::fs::metadata(&path).unwrap().len() <= 100);
+        assert_eq!(read_messages(&path).len(), 2);
+    }
+}
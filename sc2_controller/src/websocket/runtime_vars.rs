@@ -1,11 +1,16 @@
+use common::models::aiarena::aiarena_game_result::StepTimeStats;
 use indexmap::IndexSet;
 use sc2_proto::sc2api::Request;
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
+use crate::game::frame_time_policy::{FrameTimePolicy, FrameTimeViolation};
 use crate::game::game_config::GameConfig;
 use crate::game::player_result::PlayerResult;
+use crate::game::request_filter::RequestFilter;
+use crate::game::request_rate_policy::RequestRatePolicy;
 use crate::game::sc2_result::Sc2Result;
+use crate::game::step_time_histogram::StepTimeHistogram;
 
 pub struct RuntimeVars {
     pub timeout_secs: Duration,
@@ -18,6 +23,12 @@ pub struct RuntimeVars {
     pub player_id: Option<u32>,
     pub game_loops: u32,
     pub frame_time: f32,
+    pub real_time_deadline: Option<Instant>,
+    pub step_time: Duration,
+    pub frame_time_policy: FrameTimePolicy,
+    pub request_rate_policy: RequestRatePolicy,
+    pub request_filter: RequestFilter,
+    pub step_time_histogram: StepTimeHistogram,
 }
 
 impl RuntimeVars {
@@ -36,6 +47,12 @@ impl RuntimeVars {
             player_id: None,
             game_loops: 0,
             frame_time: 0.0,
+            real_time_deadline: None,
+            step_time: Duration::ZERO,
+            frame_time_policy: FrameTimePolicy::new(config),
+            request_rate_policy: RequestRatePolicy::new(config),
+            request_filter: RequestFilter::new(config),
+            step_time_histogram: StepTimeHistogram::default(),
         }
     }
 
@@ -50,11 +67,37 @@ impl RuntimeVars {
     }
     pub fn record_frame_time(&mut self) {
         if self.start_timer {
-            self.frame_time += self.start_time.elapsed().as_secs_f32();
+            let elapsed = self.start_time.elapsed();
+            self.frame_time += elapsed.as_secs_f32();
+            self.step_time += elapsed;
         }
     }
+    /// Restarts the timer after a request that was answered without SC2, so the time until then
+    /// is not recorded again with the next request
+    pub fn answered_locally(&mut self) {
+        self.start_time();
+    }
+    /// Checks the time the bot spent on the game step that it has just finished
+    pub fn end_step(&mut self) -> Option<FrameTimeViolation> {
+        let step_time = std::mem::take(&mut self.step_time);
+        self.step_time_histogram.record(step_time);
+        self.frame_time_policy.record_step(step_time)
+    }
+    /// Starts the wall-clock limit of the game from now
+    pub fn start_real_time_limit(&mut self, max_real_time: Option<Duration>) {
+        self.real_time_deadline = max_real_time.map(|limit| Instant::now() + limit);
+    }
+    /// Time left until the wall-clock limit of the game is reached, if there is a limit
+    pub fn real_time_left(&self) -> Option<Duration> {
+        self.real_time_deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+    pub fn real_time_exceeded(&self) -> bool {
+        self.real_time_left().map_or(false, |left| left.is_zero())
+    }
     pub fn set_game_loops(&mut self, game_loops: u32) {
         self.game_loops = game_loops;
+        self.request_rate_policy.start_game_loop(game_loops);
     }
     pub fn replay_path(&self) -> &str {
         self.replay_path.to_str().unwrap()
@@ -88,8 +131,36 @@ impl RuntimeVars {
             self.tags.insert(tag);
         }
     }
-    pub fn build_result(self, result: Sc2Result) -> PlayerResult {
+    pub fn add_violation_tag(&mut self, violation: &FrameTimeViolation) {
+        self.tags.insert(violation.to_string());
+    }
+    pub fn add_real_time_limit_tag(&mut self) {
+        self.tags.insert("Real time limit exceeded".to_string());
+    }
+    pub fn add_refused_request_tag(&mut self, refused: &str) {
+        self.tags.insert(format!("Refused request: {}", refused));
+    }
+    fn step_time_stats(&self) -> Option<StepTimeStats> {
+        let histogram = &self.step_time_histogram;
+        if histogram.is_empty() {
+            return None;
+        }
+        Some(StepTimeStats {
+            p50: histogram.quantile(0.50).as_secs_f32(),
+            p95: histogram.quantile(0.95).as_secs_f32(),
+            p99: histogram.quantile(0.99).as_secs_f32(),
+            max: histogram.max().as_secs_f32(),
+            steps_over_budget: self.frame_time_policy.steps_over_budget(),
+        })
+    }
+    pub fn build_result(mut self, result: Sc2Result) -> PlayerResult {
+        let rate_limit_violations = self.request_rate_policy.violations();
+        if rate_limit_violations > 0 {
+            self.tags
+                .insert(format!("Rate limit violations: {}", rate_limit_violations));
+        }
         PlayerResult {
+            step_time_stats: self.step_time_stats(),
             game_loops: self.game_loops,
             frame_time: self.avg_frame_time,
             player_id: self.player_id.unwrap(),
@@ -106,3 +177,36 @@ fn nan_check(number: f32) -> f32 {
         number
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::models::aiarena::aiarena_match::MatchRequest;
+
+    fn runtime_vars() -> RuntimeVars {
+        let match_request: MatchRequest = serde_json::from_str(
+            r#"{"match_id": 1, "player_1_id": "1", "player_1_name": "bot1", "player_2_id": "2",
+            "player_2_name": "bot2", "map_name": "Map", "player_1_race": 0, "player_2_race": 0}"#,
+        )
+        .unwrap();
+        let mut r_vars = RuntimeVars::new(&GameConfig::from_file(&match_request));
+        r_vars.start_timing();
+        r_vars
+    }
+
+    #[test]
+    fn test_local_responses_keep_the_step_time() {
+        let mut r_vars = runtime_vars();
+        r_vars.start_time = Instant::now() - Duration::from_millis(30);
+        // The bot sends a debug request that is answered locally, then its step
+        r_vars.record_frame_time();
+        r_vars.answered_locally();
+        r_vars.start_time -= Duration::from_millis(10);
+        r_vars.record_frame_time();
+
+        // Only the 30 ms before the debug request and the 10 ms after its response are recorded
+        let step_time = r_vars.step_time;
+        assert!(step_time >= Duration::from_millis(40), "{step_time:?}");
+        assert!(step_time < Duration::from_millis(60), "{step_time:?}");
+    }
+}
@@ -7,16 +7,25 @@ use std::time::Duration;
 use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
 use tokio_tungstenite::WebSocketStream;
 
+use crate::events::report;
 use crate::game::game_config::GameConfig;
 use crate::game::game_result::GAME_RESULT;
 use crate::game::player_result::PlayerResult;
 use crate::game::sc2_result::Sc2Result;
-use crate::player_seats::PlayerSeat;
+use crate::match_session::{begin_match, finish_match};
+use crate::observer_slot::{
+    claim_observer_slot, join_observer_slot, observer_join_timeout, release_observer_slot,
+};
+use crate::player_seats::{observer_seat_enabled, PlayerSeat};
+use crate::status::set_seat_state;
 use axum::extract::ws::WebSocket;
 use axum::extract::{ConnectInfo, State, WebSocketUpgrade};
 use axum::response::IntoResponse;
 use common::models::aiarena::aiarena_game_result::AiArenaGameResult;
 use common::models::aiarena::aiarena_match::MatchRequest;
+use common::models::match_controller::ControllerEvent;
+use common::models::sc2_controller::SeatState;
+use common::paths::match_layout;
 use common::PlayerNum;
 use tokio::net::TcpStream;
 use tokio::time::sleep;
@@ -25,6 +34,7 @@ use tracing::{debug, error, info, Instrument};
 use crate::websocket::errors::player_error::PlayerError;
 use crate::websocket::player::Player;
 use crate::websocket::port_config::PortConfig;
+use crate::websocket::protocol_recorder::ProtocolRecorder;
 
 struct GameReadyFlag {
     pub ready: bool,
@@ -33,11 +43,34 @@ struct GameReadyFlag {
 static GAME_READY_FLAG: Lazy<Arc<RwLock<GameReadyFlag>>> =
     Lazy::new(|| Arc::new(RwLock::new(GameReadyFlag { ready: false })));
 
-static PORT_CONFIG: Lazy<Arc<RwLock<PortConfig>>> = Lazy::new(|| {
-    Arc::new(RwLock::new(
-        PortConfig::new().expect("Could not create port configuration"),
-    ))
-});
+static PORT_CONFIG: Lazy<Arc<RwLock<PortConfig>>> =
+    Lazy::new(|| Arc::new(RwLock::new(new_port_config(false))));
+
+fn new_port_config(with_observer: bool) -> PortConfig {
+    let port_config = PortConfig::new().and_then(|port_config| {
+        if with_observer {
+            port_config.with_observer()
+        } else {
+            Some(port_config)
+        }
+    });
+    port_config.expect("Could not create port configuration")
+}
+
+/// Resets the game of the previous match for a new match
+pub fn reset_game_state(match_request: &MatchRequest) {
+    GAME_READY_FLAG.write().unwrap().ready = false;
+    *PORT_CONFIG.write().unwrap() = new_port_config(observer_slot_enabled(match_request));
+    let mut game_result = GAME_RESULT.write().unwrap();
+    game_result.reset();
+    game_result.set(match_request.match_id);
+}
+
+/// Whether the game has a slot for a spectator.
+/// A spectator would hold up the steps of the bots in a game that is not in real time.
+fn observer_slot_enabled(match_request: &MatchRequest) -> bool {
+    observer_seat_enabled() && match_request.realtime
+}
 
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
@@ -57,8 +90,30 @@ async fn websocket(bot_ws: WebSocket, player_seat: PlayerSeat, addr: SocketAddr)
         addr, player_seat.internal_port
     );
 
-    let match_request = MatchRequest::read();
+    let Some(match_request) = MatchRequest::try_read(match_layout()) else {
+        error!("Refusing connection, there is no match request");
+        return;
+    };
     debug!("Match Request: {:?}", match_request);
+    if !begin_match(&match_request) {
+        error!(
+            "Refusing connection, match {} has already finished",
+            match_request.match_id
+        );
+        return;
+    }
+
+    let port = player_seat.external_port;
+    set_seat_state(port, SeatState::Connected);
+    if player_seat.is_observer() {
+        observer_websocket(bot_ws, player_seat, match_request).await;
+    } else {
+        player_websocket(bot_ws, player_seat, match_request).await;
+    }
+    set_seat_state(port, SeatState::Finished);
+}
+
+async fn player_websocket(bot_ws: WebSocket, player_seat: PlayerSeat, match_request: MatchRequest) {
     let match_id = match_request.match_id;
     GAME_RESULT.write().unwrap().set(match_id);
 
@@ -85,12 +140,50 @@ async fn websocket(bot_ws: WebSocket, player_seat: PlayerSeat, addr: SocketAddr)
         }
     };
 
+    if let (PlayerNum::Two, Some(builtin_ai)) = (player_num, match_request.player_2_builtin_ai) {
+        error!(
+            "Player seat 2 is not in use, player 2 is the built-in AI {}",
+            builtin_ai.id()
+        );
+        return;
+    }
+
+    if match_request.record_protocol {
+        let path = match_layout().log_file(&MatchRequest::protocol_recording_file(player_num));
+        match ProtocolRecorder::create(
+            &path,
+            match_request.protocol_record_max_bytes,
+            match_request.protocol_record_observation_interval,
+        ) {
+            Ok(mut recorder) => {
+                match serde_json::to_vec(&match_request) {
+                    Ok(payload) => recorder.record_match_request(&payload),
+                    Err(e) => error!("Could not record the match request: {:?}", e),
+                }
+                client_ws.record_protocol(recorder)
+            }
+            Err(e) => error!("Could not create protocol recording {:?}: {:?}", path, e),
+        }
+    }
+
     if let PlayerNum::One = player_num {
-        match client_ws.create_game(&map, false).await {
+        match client_ws
+            .create_game(
+                &map,
+                match_request.realtime,
+                match_request.player_2_builtin_ai,
+                observer_slot_enabled(&match_request),
+            )
+            .await
+        {
             Ok(_) => {
                 let mut s = GAME_READY_FLAG.write().unwrap();
                 debug!("Setting port_config and ready state");
                 s.ready = true;
+                if observer_slot_enabled(&match_request) {
+                    let port_config = PORT_CONFIG.read().unwrap().clone();
+                    tokio::spawn(observer_join_timeout(match_id, port_config));
+                }
             }
             Err(e) => {
                 error!("{:?}", e);
@@ -102,23 +195,10 @@ async fn websocket(bot_ws: WebSocket, player_seat: PlayerSeat, addr: SocketAddr)
         };
     }
 
-    let max_counter = 200;
-    let mut counter = 0;
-    loop {
-        debug!("Waiting for state to become ready");
-        counter += 1;
-        let ready = { GAME_READY_FLAG.read().unwrap().ready };
-        if ready || counter > max_counter {
-            break;
-        } else {
-            sleep(Duration::from_millis(250)).await;
-        }
-    }
-
     let game_config = GameConfig::from_file(&match_request);
     let port_config = PORT_CONFIG.read().unwrap().clone();
 
-    if counter <= max_counter {
+    if wait_for_game_ready().await {
         debug!("Starting Client Run");
         let p_result = match client_ws
             .run(game_config, port_config, player_num, player_seat.pass_port)
@@ -189,11 +269,20 @@ async fn websocket(bot_ws: WebSocket, player_seat: PlayerSeat, addr: SocketAddr)
                     frame_time: 0.0,
                     player_id: 0,
                     tags: indexmap::IndexSet::default(),
+                    step_time_stats: None,
                     result: temp_result,
                 }
             }
         };
         debug!("{:?}", &p_result);
+        if match_request.player_2_builtin_ai.is_some() {
+            // The built-in AI has no seat, so its result is derived from the result of player 1
+            GAME_RESULT.write().unwrap().add_player_result(
+                match_id,
+                PlayerNum::Two,
+                p_result.builtin_ai_opponent(),
+            );
+        }
         GAME_RESULT
             .write()
             .unwrap()
@@ -207,6 +296,79 @@ async fn websocket(bot_ws: WebSocket, player_seat: PlayerSeat, addr: SocketAddr)
     return store_game_result(match_id);
 }
 
+/// Proxies a spectator client to the observer slot of the game.
+/// The observer has no result and can't influence the game.
+async fn observer_websocket(
+    bot_ws: WebSocket,
+    player_seat: PlayerSeat,
+    match_request: MatchRequest,
+) {
+    let match_id = match_request.match_id;
+    if !observer_slot_enabled(&match_request) {
+        error!("Refusing spectator, the observer slot is only available in real time games");
+        return;
+    }
+
+    if !wait_for_game_ready().await {
+        error!("Timeout while waiting for game to become ready");
+        return;
+    }
+
+    if !claim_observer_slot(match_id) {
+        error!("Refusing spectator, the observer slot is already taken");
+        return;
+    }
+
+    let port_config = PORT_CONFIG.read().unwrap().clone();
+    let sc2_ws = match connect(player_seat.internal_port).await {
+        Some(sc2_ws) => sc2_ws,
+        None => {
+            error!("Could not connect observer to SC2");
+            return release_observer_slot(match_id, port_config).await;
+        }
+    };
+
+    let game_config = GameConfig::from_file(&match_request);
+    let mut client_ws = Player::new(bot_ws, sc2_ws);
+
+    match client_ws
+        .run_observer(
+            game_config,
+            port_config.clone(),
+            player_seat.pass_port,
+            || join_observer_slot(match_id),
+        )
+        .instrument(tracing::Span::current())
+        .await
+    {
+        Ok(()) => info!("Observer left the game"),
+        Err(e) => error!("Observer error: {:?}", e),
+    }
+
+    // The connection to SC2 is closed first, so the slot can be joined again
+    drop(client_ws);
+    release_observer_slot(match_id, port_config).await;
+}
+
+/// Waits until player 1 has created the game.
+/// Returns false if the game doesn't become ready in time.
+async fn wait_for_game_ready() -> bool {
+    let max_counter = 200;
+    let mut counter = 0;
+    loop {
+        debug!("Waiting for state to become ready");
+        counter += 1;
+        let ready = { GAME_READY_FLAG.read().unwrap().ready };
+        if ready {
+            return true;
+        } else if counter > max_counter {
+            return false;
+        } else {
+            sleep(Duration::from_millis(250)).await;
+        }
+    }
+}
+
 pub async fn connect(port: u16) -> Option<WebSocketStream<TcpStream>> {
     let url = format!("ws://127.0.0.1:{}/sc2api", port);
     let addr = format!("127.0.0.1:{}", port);
@@ -248,7 +410,7 @@ pub async fn connect(port: u16) -> Option<WebSocketStream<TcpStream>> {
 
 /// Store the game result on disk.
 /// The input match id ensures that thread that process previous matches don't overwrite the current match result.
-fn store_game_result(match_id: u32) {
+pub fn store_game_result(match_id: u32) {
     let game_result = GAME_RESULT.read().unwrap().clone();
 
     if game_result.match_id == match_id {
@@ -257,9 +419,14 @@ fn store_game_result(match_id: u32) {
 
             info!("Game result: {:?}", &aiarena_game_result);
 
-            aiarena_game_result.to_json_file();
+            aiarena_game_result.to_json_file(match_layout());
 
             info!("Game result stored successfully");
+            finish_match(match_id);
+
+            report(ControllerEvent::GameEnded {
+                result: aiarena_game_result,
+            });
         } else {
             info!("Waiting for results from both players before storing the game result");
         }